@@ -0,0 +1,403 @@
+// Paths of straight and Bezier segments. Lines and quadratics are handled as cubics, which
+// describe them exactly after degree elevation.
+use serde::{Deserialize, Serialize};
+
+use crate::drawing::format_number;
+use crate::error::GeometryError;
+use crate::flatten;
+use crate::svg_import::{dedup_closing_point, parse_path_data, PathCommand};
+use crate::{checked_area, ring_bounding_box, ring_centroid, ring_contains, ring_signed_area, Point, Polyline, Rectangle};
+
+// Arc length integration stops once halving the steps changes a cubic's length by less than
+// this fraction of its control polygon's length, which is never shorter than the curve
+const LENGTH_TOLERANCE: f64 = 1e-9;
+// Always subdivide a few times, so a lucky first estimate cannot end the integration early
+const MIN_LENGTH_DEPTH: u32 = 3;
+const MAX_LENGTH_DEPTH: u32 = 24;
+
+// Centroids come from an outline this close to the curve, far below what rounding keeps
+const CENTROID_TOLERANCE: f64 = 1e-4;
+
+// Decimal places of coordinates in path data written by `Path::to_data`
+const DECIMALS: usize = 6;
+
+// One piece of a path, starting where the previous one ends
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum PathSegment {
+    Line { to: Point },
+    Quadratic { control: Point, to: Point },
+    Cubic { control1: Point, control2: Point, to: Point },
+}
+
+// A single subpath from `start` through `segments`. Closed paths return to `start` with a
+// straight line and enclose an area; open ones only have a length.
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct Path {
+    pub start: Point,
+    pub segments: Vec<PathSegment>,
+    #[serde(default)]
+    pub closed: bool,
+}
+
+impl PathSegment {
+    pub fn end(&self) -> Point {
+        match *self {
+            PathSegment::Line { to } | PathSegment::Quadratic { to, .. } | PathSegment::Cubic { to, .. } => to,
+        }
+    }
+
+    // Control points of the same curve as a cubic starting at `from`
+    fn cubic(&self, from: Point) -> [Point; 4] {
+        let lerp = |a: Point, b: Point, t: f64| Point {
+            x: a.x + t * (b.x - a.x),
+            y: a.y + t * (b.y - a.y),
+        };
+        match *self {
+            PathSegment::Line { to } => [from, lerp(from, to, 1.0 / 3.0), lerp(from, to, 2.0 / 3.0), to],
+            PathSegment::Quadratic { control, to } => [from, lerp(from, control, 2.0 / 3.0), lerp(to, control, 2.0 / 3.0), to],
+            PathSegment::Cubic { control1, control2, to } => [from, control1, control2, to],
+        }
+    }
+
+    fn map(&self, f: &impl Fn(Point) -> Point) -> PathSegment {
+        match *self {
+            PathSegment::Line { to } => PathSegment::Line { to: f(to) },
+            PathSegment::Quadratic { control, to } => PathSegment::Quadratic { control: f(control), to: f(to) },
+            PathSegment::Cubic { control1, control2, to } => PathSegment::Cubic {
+                control1: f(control1),
+                control2: f(control2),
+                to: f(to),
+            },
+        }
+    }
+}
+
+impl Path {
+    pub fn validate(&self) -> Result<(), GeometryError> {
+        if self.segments.is_empty() {
+            return Err(GeometryError::TooFewPoints {
+                field: "segments".to_string(),
+                required: 1,
+                actual: 0,
+            });
+        }
+        self.start.check_finite("start")?;
+        for (i, segment) in self.segments.iter().enumerate() {
+            let check = |point: &Point, name: &str| point.check_finite(&format!("segments[{}].{}", i, name));
+            match segment {
+                PathSegment::Line { to } => check(to, "to")?,
+                PathSegment::Quadratic { control, to } => {
+                    check(control, "control")?;
+                    check(to, "to")?;
+                }
+                PathSegment::Cubic { control1, control2, to } => {
+                    check(control1, "control1")?;
+                    check(control2, "control2")?;
+                    check(to, "to")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Every segment as cubic control points, including the line that closes a closed path
+    fn cubics(&self) -> Vec<[Point; 4]> {
+        let mut from = self.start;
+        let mut cubics = Vec::with_capacity(self.segments.len() + 1);
+        for segment in &self.segments {
+            cubics.push(segment.cubic(from));
+            from = segment.end();
+        }
+        if self.closed {
+            cubics.push(PathSegment::Line { to: self.start }.cubic(from));
+        }
+        cubics
+    }
+
+    // Green's theorem over each cubic, relative to the start so distant paths keep their
+    // precision; positive for counter-clockwise paths (Y up)
+    pub fn signed_area(&self) -> f64 {
+        let origin = self.start;
+        let cross = |a: Point, b: Point| (a.x - origin.x) * (b.y - origin.y) - (b.x - origin.x) * (a.y - origin.y);
+        self.cubics()
+            .iter()
+            .map(|&[p0, p1, p2, p3]| {
+                (6.0 * (cross(p0, p1) + cross(p2, p3)) + 3.0 * (cross(p0, p2) + cross(p1, p2) + cross(p1, p3)) + cross(p0, p3))
+                    / 20.0
+            })
+            .sum()
+    }
+
+    pub fn area(&self) -> Result<f64, GeometryError> {
+        self.validate()?;
+        if !self.closed {
+            return Err(GeometryError::OpenShape { field: "closed".to_string() });
+        }
+        checked_area(self.signed_area().abs())
+    }
+
+    // Integral of the speed along each cubic, closing line included
+    pub fn length(&self) -> f64 {
+        self.cubics()
+            .iter()
+            .map(|&[p0, p1, p2, p3]| {
+                let speed = |t: f64| {
+                    let u = 1.0 - t;
+                    let dx = 3.0 * (u * u * (p1.x - p0.x) + 2.0 * u * t * (p2.x - p1.x) + t * t * (p3.x - p2.x));
+                    let dy = 3.0 * (u * u * (p1.y - p0.y) + 2.0 * u * t * (p2.y - p1.y) + t * t * (p3.y - p2.y));
+                    dx.hypot(dy)
+                };
+                let tolerance = LENGTH_TOLERANCE * (p0.distance(&p1) + p1.distance(&p2) + p2.distance(&p3));
+                let (a, m, b) = (speed(0.0), speed(0.5), speed(1.0));
+                simpson(&speed, 0.0, 1.0, a, m, b, (a + 4.0 * m + b) / 6.0, tolerance, 0)
+            })
+            .sum()
+    }
+
+    // Points along the path within `tolerance` of the curve, from the start to the last
+    // segment's end; closed paths do not repeat the start
+    pub fn points(&self, tolerance: f64) -> Vec<Point> {
+        let mut points = vec![self.start];
+        let mut from = self.start;
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::Line { to } => points.push(to),
+                PathSegment::Quadratic { control, to } => points.extend(flatten::quadratic(from, control, to, tolerance)),
+                PathSegment::Cubic { control1, control2, to } => {
+                    points.extend(flatten::cubic(from, control1, control2, to, tolerance))
+                }
+            }
+            from = segment.end();
+        }
+        if self.closed {
+            dedup_closing_point(&mut points);
+        }
+        points
+    }
+
+    // Area centroid of a closed path, or the length-weighted middle of an open one
+    pub fn centroid(&self) -> Point {
+        let points = self.points(CENTROID_TOLERANCE);
+        let area = if self.closed && points.len() >= 3 { ring_signed_area(&points) } else { 0.0 };
+        if area != 0.0 {
+            ring_centroid(&points, area)
+        } else {
+            Polyline { points }.centroid()
+        }
+    }
+
+    // Exact box: the ends of each cubic and the points where it turns in x or y
+    pub fn bounding_box(&self) -> Rectangle {
+        let mut extremes = vec![self.start];
+        for [p0, p1, p2, p3] in self.cubics() {
+            extremes.push(p3);
+            let mut turns = cubic_turns(p0.x, p1.x, p2.x, p3.x);
+            turns.extend(cubic_turns(p0.y, p1.y, p2.y, p3.y));
+            for t in turns {
+                let u = 1.0 - t;
+                let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                extremes.push(Point {
+                    x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+                    y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+                });
+            }
+        }
+        ring_bounding_box(&extremes)
+    }
+
+    // Whether `p` is inside a closed path; open paths enclose nothing
+    pub fn contains(&self, p: &Point) -> bool {
+        self.closed && ring_contains(&self.points(flatten::DEFAULT_TOLERANCE), p)
+    }
+
+    // The same path with every point, control points included, passed through `f`; exact
+    // for affine maps, which take Bezier curves to Bezier curves
+    pub fn map(&self, f: impl Fn(Point) -> Point) -> Path {
+        Path {
+            start: f(self.start),
+            segments: self.segments.iter().map(|segment| segment.map(&f)).collect(),
+            closed: self.closed,
+        }
+    }
+
+    // Reads SVG path data of a single subpath. Shorthand and relative commands are expanded;
+    // elliptical arcs are not supported.
+    pub fn parse(data: &str) -> Result<Path, String> {
+        let mut start: Option<Point> = None;
+        let mut segments = Vec::new();
+        let mut closed = false;
+        for command in parse_path_data(data)? {
+            if closed {
+                return Err("path data has more than one subpath".to_string());
+            }
+            let segment = match command {
+                PathCommand::MoveTo(to) if segments.is_empty() => {
+                    start = Some(to);
+                    continue;
+                }
+                PathCommand::MoveTo(_) => return Err("path data has more than one subpath".to_string()),
+                PathCommand::Close => {
+                    closed = true;
+                    continue;
+                }
+                PathCommand::LineTo(to) => PathSegment::Line { to },
+                PathCommand::QuadTo(control, to) => PathSegment::Quadratic { control, to },
+                PathCommand::CubicTo(control1, control2, to) => PathSegment::Cubic { control1, control2, to },
+                PathCommand::ArcTo { .. } => return Err("elliptical arc commands are not supported".to_string()),
+            };
+            segments.push(segment);
+        }
+        let start = start.ok_or("path data has no move-to command")?;
+        if segments.is_empty() {
+            return Err("path data has no segments".to_string());
+        }
+        Ok(Path { start, segments, closed })
+    }
+
+    // SVG path data with absolute commands, e.g. `M 0 0 C 10 0 20 10 20 20 Z`
+    pub fn to_data(&self) -> String {
+        let point = |p: &Point| format!("{} {}", format_number(p.x, DECIMALS), format_number(p.y, DECIMALS));
+        let mut data = format!("M {}", point(&self.start));
+        for segment in &self.segments {
+            data.push(' ');
+            data.push_str(&match segment {
+                PathSegment::Line { to } => format!("L {}", point(to)),
+                PathSegment::Quadratic { control, to } => format!("Q {} {}", point(control), point(to)),
+                PathSegment::Cubic { control1, control2, to } => {
+                    format!("C {} {} {}", point(control1), point(control2), point(to))
+                }
+            });
+        }
+        if self.closed {
+            data.push_str(" Z");
+        }
+        data
+    }
+}
+
+// Parameters in (0, 1) where a cubic with coordinates a..d stops rising or falling
+fn cubic_turns(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Derivative / 3 = (a' - 2b' + c') t² + 2(b' - a') t + a', with a' = b - a, b' = c - b, c' = d - c
+    let (p, q, r) = (b - a, c - b, d - c);
+    let (qa, qb, qc) = (p - 2.0 * q + r, 2.0 * (q - p), p);
+    let roots = if qa.abs() < 1e-12 {
+        if qb == 0.0 { Vec::new() } else { vec![-qc / qb] }
+    } else {
+        let discriminant = qb * qb - 4.0 * qa * qc;
+        if discriminant < 0.0 {
+            Vec::new()
+        } else {
+            let root = discriminant.sqrt();
+            vec![(-qb + root) / (2.0 * qa), (-qb - root) / (2.0 * qa)]
+        }
+    };
+    roots.into_iter().filter(|&t| t > 0.0 && t < 1.0).collect()
+}
+
+// Adaptive Simpson's rule over [a, b], given f at the ends and middle and the estimate `whole`
+#[allow(clippy::too_many_arguments)]
+fn simpson(f: &impl Fn(f64) -> f64, a: f64, b: f64, fa: f64, fm: f64, fb: f64, whole: f64, tolerance: f64, depth: u32) -> f64 {
+    let m = (a + b) / 2.0;
+    let (lm, rm) = ((a + m) / 2.0, (m + b) / 2.0);
+    let (flm, frm) = (f(lm), f(rm));
+    let left = (m - a) * (fa + 4.0 * flm + fm) / 6.0;
+    let right = (b - m) * (fm + 4.0 * frm + fb) / 6.0;
+    let delta = left + right - whole;
+    if depth >= MAX_LENGTH_DEPTH || (depth >= MIN_LENGTH_DEPTH && delta.abs() <= 15.0 * tolerance) {
+        return left + right + delta / 15.0;
+    }
+    simpson(f, a, m, fa, flm, fm, left, tolerance / 2.0, depth + 1)
+        + simpson(f, m, b, fm, frm, fb, right, tolerance / 2.0, depth + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    // A closed cubic y = 3t(1 - t), x = 3t² - 2t³ over the unit interval, traced clockwise
+    fn hump() -> Path {
+        Path {
+            start: point(0.0, 0.0),
+            segments: vec![PathSegment::Cubic {
+                control1: point(0.0, 1.0),
+                control2: point(1.0, 1.0),
+                to: point(1.0, 0.0),
+            }],
+            closed: true,
+        }
+    }
+
+    #[test]
+    fn absolute_path_data_round_trips() {
+        for data in [
+            "M 0 0 L 10 0 Q 20 0 20 10 C 20 20 10 20 0 20 Z",
+            "M -1.5 2.25 C 3 4 5 6 7.125 -8",
+            "M 0.000001 100 L 1000000 -0.5",
+        ] {
+            assert_eq!(Path::parse(data).unwrap().to_data(), data);
+        }
+    }
+
+    #[test]
+    fn relative_and_shorthand_commands_are_expanded() {
+        let path = Path::parse("m 1 2 h 3 v 4 q 1 1 2 0 t 2 0 z").unwrap();
+        assert_eq!(path.to_data(), "M 1 2 L 4 2 L 4 6 Q 5 7 6 6 Q 7 5 8 6 Z");
+        assert_eq!(Path::parse(&path.to_data()).unwrap().to_data(), path.to_data());
+    }
+
+    #[test]
+    fn rejects_unsupported_path_data() {
+        for data in ["L 1 1", "M 0 0", "M 0 0 L 1 1 M 2 2 L 3 3", "M 0 0 L 1 1 Z L 2 2", "M 0 0 A 1 1 0 0 1 2 0"] {
+            assert!(Path::parse(data).is_err(), "accepted {}", data);
+        }
+    }
+
+    #[test]
+    fn signed_area_is_exact_for_curves() {
+        // ∫ y dx = ∫ 3t(1 - t)(6t - 6t²) dt = 0.6, negative as the path runs clockwise
+        assert_close(hump().signed_area(), -0.6, 1e-12);
+        // A parabolic segment covers two thirds of its bounding box
+        let parabola = Path::parse("M 2 0 Q 1 2 0 0 Z").unwrap();
+        assert_close(parabola.signed_area(), 4.0 / 3.0, 1e-12);
+
+        let far = hump().map(|p| point(p.x + 1e6, p.y - 1e6));
+        assert_close(far.signed_area(), -0.6, 1e-9);
+        assert!(matches!(Path { closed: false, ..hump() }.area(), Err(GeometryError::OpenShape { .. })));
+    }
+
+    #[test]
+    fn length_matches_known_curves() {
+        let line = Path::parse("M 0 0 C 1 1.333333333333 2 2.666666666667 3 4").unwrap();
+        assert_close(line.length(), 5.0, 1e-9);
+        let parabola = Path::parse("M 0 0 Q 1 2 2 0").unwrap();
+        assert_close(parabola.length(), 5f64.sqrt() + 2f64.asinh() / 2.0, 1e-9);
+        // The closing line counts
+        assert_close(Path { closed: true, ..parabola }.length(), 2.0 + 5f64.sqrt() + 2f64.asinh() / 2.0, 1e-9);
+    }
+
+    #[test]
+    fn length_accuracy_does_not_depend_on_scale() {
+        let unit = hump().length();
+        for scale in [1e-6, 1e6] {
+            let scaled = hump().map(|p| point(p.x * scale, p.y * scale)).length();
+            assert_close(scaled / scale, unit, 1e-9 * unit);
+        }
+    }
+
+    #[test]
+    fn bounding_box_reaches_the_curve_extremes() {
+        let bounds = hump().bounding_box();
+        assert_close(bounds.top_left.x.min(bounds.bottom_right.x), 0.0, 1e-12);
+        assert_close(bounds.top_left.x.max(bounds.bottom_right.x), 1.0, 1e-12);
+        assert_close(bounds.top_left.y.max(bounds.bottom_right.y), 0.75, 1e-12);
+    }
+}
@@ -0,0 +1,295 @@
+use geo::{BooleanOps, LineString, MultiPolygon, OpType};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GeometryError;
+use crate::flatten;
+use crate::precision::Precision;
+use crate::svg_import::dedup_closing_point;
+use crate::units::MeasurementUnit;
+use crate::{ring_signed_area, Point, Polygon, Shape};
+
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BooleanOp {
+    Union,
+    Intersection,
+    // Subject minus clip
+    Difference,
+    Xor,
+}
+
+impl From<BooleanOp> for OpType {
+    fn from(op: BooleanOp) -> OpType {
+        match op {
+            BooleanOp::Union => OpType::Union,
+            BooleanOp::Intersection => OpType::Intersection,
+            BooleanOp::Difference => OpType::Difference,
+            BooleanOp::Xor => OpType::Xor,
+        }
+    }
+}
+
+#[derive(Serialize,Debug)]
+pub(crate) struct BooleanResult {
+    // Connected pieces with outer rings wound counter-clockwise and holes clockwise.
+    // Points are in internal pixels and rings are not closed.
+    pub polygons: Vec<Polygon>,
+    // Total area with holes subtracted, in `area_unit`
+    pub area: f64,
+    pub unit: MeasurementUnit,
+    pub area_unit: String,
+}
+
+fn line_string(ring: &[Point]) -> LineString<f64> {
+    ring.iter().map(|p| (p.x, p.y)).collect()
+}
+
+fn polygon_to_geo(polygon: &Polygon) -> geo::Polygon<f64> {
+    geo::Polygon::new(
+        line_string(&polygon.points),
+        polygon.holes.iter().map(|hole| line_string(hole)).collect(),
+    )
+}
+
+// Outline of a shape as geo polygons, with curves flattened to within `tolerance`
+fn to_geo(shape: &Shape, tolerance: f64) -> Result<MultiPolygon<f64>, GeometryError> {
+    let polygons = match shape {
+        Shape::Rectangle(rect) => {
+            rect.area(&Precision::default())?;
+            let (a, b) = (rect.top_left, rect.bottom_right);
+            vec![geo::Polygon::new(
+                line_string(&[a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }]),
+                Vec::new(),
+            )]
+        }
+        Shape::OrientedRectangle(rect) => {
+            rect.validate()?;
+            vec![geo::Polygon::new(line_string(&rect.corners()), Vec::new())]
+        }
+        Shape::Circle(circle) => {
+            circle.area(&Precision::default())?;
+            let ring = flatten::ellipse(circle.center, circle.radius, circle.radius, tolerance);
+            vec![geo::Polygon::new(line_string(&ring), Vec::new())]
+        }
+        Shape::Ellipse(ellipse) => {
+            ellipse.area(&Precision::default())?;
+            vec![geo::Polygon::new(line_string(&ellipse.outline(tolerance)), Vec::new())]
+        }
+        Shape::Arc(arc) => {
+            arc.area(&Precision::default())?;
+            vec![geo::Polygon::new(line_string(&arc.outline(tolerance)), Vec::new())]
+        }
+        Shape::Path(path) => {
+            path.area()?;
+            vec![geo::Polygon::new(line_string(&path.points(tolerance)), Vec::new())]
+        }
+        // Open shapes enclose no region; `area` reports why
+        Shape::Segment(_) | Shape::Polyline(_) => {
+            shape.area(&Precision::default())?;
+            Vec::new()
+        }
+        Shape::Polygon(polygon) => {
+            polygon.validate()?;
+            vec![polygon_to_geo(polygon)]
+        }
+        Shape::MultiPolygon(multi) => {
+            multi.validate()?;
+            multi.polygons.iter().map(polygon_to_geo).collect()
+        }
+    };
+    Ok(MultiPolygon::new(polygons))
+}
+
+// Union of all `shapes`, so overlapping members of one operand count once
+fn merge(shapes: &[Shape], name: &str, tolerance: f64) -> Result<MultiPolygon<f64>, GeometryError> {
+    let mut merged = MultiPolygon::new(Vec::new());
+    for (i, shape) in shapes.iter().enumerate() {
+        let polygons = to_geo(shape, tolerance).map_err(|err| err.within(&format!("{}[{}]", name, i)))?;
+        merged = merged.union(&polygons);
+    }
+    Ok(merged)
+}
+
+fn ring_points(ring: &LineString<f64>, precision: &Precision) -> Vec<Point> {
+    let mut points: Vec<Point> = ring
+        .coords()
+        .map(|c| Point {
+            x: precision.round(c.x),
+            y: precision.round(c.y),
+        })
+        .collect();
+    dedup_closing_point(&mut points);
+    points
+}
+
+// Combines the `subject` and `clip` shape sets with `op`. Self-intersecting polygons are
+// filled by the even-odd rule. The area is reported in `unit` via `area_factor`, the number
+// of square `unit`s per square internal pixel.
+pub(crate) fn apply(
+    op: BooleanOp,
+    subject: &[Shape],
+    clip: &[Shape],
+    tolerance: f64,
+    precision: &Precision,
+    unit: MeasurementUnit,
+    area_factor: f64,
+) -> Result<BooleanResult, GeometryError> {
+    let subject = merge(subject, "subject", tolerance)?;
+    let clip = merge(clip, "clip", tolerance)?;
+    let result = subject.boolean_op(&clip, op.into());
+
+    let mut area = 0.0;
+    let mut polygons = Vec::new();
+    for polygon in &result {
+        let mut region = Polygon {
+            points: ring_points(polygon.exterior(), precision),
+            holes: polygon.interiors().iter().map(|ring| ring_points(ring, precision)).collect(),
+        };
+        let outer = ring_signed_area(&region.points);
+        if region.points.len() < 3 || precision.is_zero(outer) {
+            continue;
+        }
+        if outer < 0.0 {
+            region.points.reverse();
+        }
+        area += outer.abs();
+        region.holes.retain(|hole| hole.len() >= 3);
+        for hole in &mut region.holes {
+            let signed_area = ring_signed_area(hole);
+            if signed_area > 0.0 {
+                hole.reverse();
+            }
+            area -= signed_area.abs();
+        }
+        polygons.push(region);
+    }
+    Ok(BooleanResult {
+        polygons,
+        area: precision.round(area * area_factor),
+        unit,
+        area_unit: unit.squared_symbol(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Circle;
+
+    fn square(x: f64, y: f64, side: f64) -> Shape {
+        Shape::Polygon(Polygon::new(vec![
+            Point { x, y },
+            Point { x: x + side, y },
+            Point { x: x + side, y: y + side },
+            Point { x, y: y + side },
+        ]))
+    }
+
+    fn apply_in_pixels(op: BooleanOp, subject: &[Shape], clip: &[Shape]) -> BooleanResult {
+        apply(
+            op,
+            subject,
+            clip,
+            flatten::DEFAULT_TOLERANCE,
+            &Precision::default(),
+            MeasurementUnit::Pixel,
+            1.0,
+        )
+        .unwrap()
+    }
+
+    // Outer rings counter-clockwise and holes clockwise, as `BooleanResult` promises
+    fn assert_wound(result: &BooleanResult) {
+        for polygon in &result.polygons {
+            assert!(ring_signed_area(&polygon.points) > 0.0, "{:?}", polygon.points);
+            for hole in &polygon.holes {
+                assert!(ring_signed_area(hole) < 0.0, "{:?}", hole);
+            }
+        }
+    }
+
+    #[test]
+    fn combines_overlapping_squares() {
+        let (a, b) = ([square(0.0, 0.0, 4.0)], [square(2.0, 2.0, 4.0)]);
+        let cases = [
+            (BooleanOp::Union, 28.0, 1),
+            (BooleanOp::Intersection, 4.0, 1),
+            (BooleanOp::Difference, 12.0, 1),
+            (BooleanOp::Xor, 24.0, 2),
+        ];
+        for (op, area, pieces) in cases {
+            let result = apply_in_pixels(op, &a, &b);
+            assert_eq!(result.area, area, "{:?}", op);
+            assert_eq!(result.polygons.len(), pieces, "{:?}", op);
+            assert_wound(&result);
+        }
+    }
+
+    #[test]
+    fn combines_disjoint_squares() {
+        let (a, b) = ([square(0.0, 0.0, 4.0)], [square(10.0, 0.0, 2.0)]);
+        let union = apply_in_pixels(BooleanOp::Union, &a, &b);
+        assert_eq!((union.area, union.polygons.len()), (20.0, 2));
+        let intersection = apply_in_pixels(BooleanOp::Intersection, &a, &b);
+        assert_eq!((intersection.area, intersection.polygons.len()), (0.0, 0));
+        let difference = apply_in_pixels(BooleanOp::Difference, &a, &b);
+        assert_eq!((difference.area, difference.polygons.len()), (16.0, 1));
+    }
+
+    #[test]
+    fn cutting_out_an_inner_square_leaves_a_hole() {
+        let result = apply_in_pixels(BooleanOp::Difference, &[square(0.0, 0.0, 10.0)], &[square(3.0, 3.0, 3.0)]);
+        assert_eq!(result.area, 91.0);
+        match result.polygons.as_slice() {
+            [polygon] => assert_eq!((polygon.points.len(), polygon.holes.len()), (4, 1)),
+            polygons => panic!("expected one polygon, got {:?}", polygons),
+        }
+        assert_wound(&result);
+
+        // Filling the hole back in gives the whole square
+        let filled = apply_in_pixels(
+            BooleanOp::Union,
+            &[Shape::Polygon(result.polygons[0].clone())],
+            &[square(3.0, 3.0, 3.0)],
+        );
+        assert_eq!(filled.area, 100.0);
+        assert!(filled.polygons[0].holes.is_empty());
+    }
+
+    #[test]
+    fn overlapping_members_of_one_operand_count_once() {
+        let result = apply_in_pixels(BooleanOp::Union, &[square(0.0, 0.0, 4.0), square(2.0, 2.0, 4.0)], &[]);
+        assert_eq!(result.area, 28.0);
+    }
+
+    #[test]
+    fn reports_areas_in_the_requested_unit() {
+        let result = apply(
+            BooleanOp::Union,
+            &[square(0.0, 0.0, 96.0)],
+            &[],
+            flatten::DEFAULT_TOLERANCE,
+            &Precision::default(),
+            MeasurementUnit::Inch,
+            1.0 / (96.0 * 96.0),
+        )
+        .unwrap();
+        assert_eq!((result.area, result.area_unit.as_str()), (1.0, "in²"));
+    }
+
+    #[test]
+    fn names_the_invalid_operand() {
+        let circle = Shape::Circle(Circle { center: Point { x: 0.0, y: 0.0 }, radius: -1.0 });
+        let err = apply(
+            BooleanOp::Union,
+            &[square(0.0, 0.0, 4.0)],
+            &[square(0.0, 0.0, 1.0), circle],
+            flatten::DEFAULT_TOLERANCE,
+            &Precision::default(),
+            MeasurementUnit::Pixel,
+            1.0,
+        )
+        .unwrap_err();
+        assert_eq!(err.field(), "clip[1].radius");
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::error::DocumentError;
+use crate::scene::Scene;
+use crate::units::MeasurementUnit;
+
+// Identifies drawing files so arbitrary JSON is not mistaken for one
+const FORMAT: &str = "hello-tauri-world/drawing";
+
+pub(crate) const CURRENT_VERSION: u32 = 5;
+
+// `MIGRATIONS[n]` upgrades a document from version `n + 1` to `n + 2`, so a file written by
+// any older release is walked step by step up to `CURRENT_VERSION` before deserializing
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[
+    // Version 2 added polygon holes and multi-polygons; version 1 documents are valid as-is
+    |_| {},
+    // Version 3 gave every shape a stable id, wrapping it as `{ id, shape }`
+    |fields| {
+        if let Some(Value::Array(shapes)) = fields.get_mut("shapes") {
+            for shape in shapes.iter_mut() {
+                let mut item = Map::new();
+                item.insert("id".to_string(), Value::from(Uuid::new_v4().to_string()));
+                item.insert("shape".to_string(), shape.take());
+                *shape = Value::Object(item);
+            }
+        }
+    },
+    // Version 4 added layers; earlier shapes all go on one
+    |fields| {
+        let layer = Uuid::new_v4().to_string();
+        if let Some(Value::Array(shapes)) = fields.get_mut("shapes") {
+            for shape in shapes.iter_mut().filter_map(Value::as_object_mut) {
+                shape.insert("layer".to_string(), Value::from(layer.clone()));
+            }
+        }
+        fields.insert(
+            "layers".to_string(),
+            json!([{ "id": layer, "name": "Layer 1", "color": "#000000", "visible": true, "locked": false }]),
+        );
+    },
+    // Version 5 added groups; earlier shapes are in none
+    |fields| {
+        if let Some(Value::Array(shapes)) = fields.get_mut("shapes") {
+            for shape in shapes.iter_mut().filter_map(Value::as_object_mut) {
+                shape.insert("group".to_string(), Value::Null);
+            }
+        }
+        fields.insert("groups".to_string(), json!([]));
+    },
+];
+
+// Mirrors `GridSettings` in src/types/shapes.ts
+#[derive(Deserialize,Serialize,Debug,Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GridSettings {
+    pub grid_size: f64,
+    pub unit: MeasurementUnit,
+    pub show_grid: bool,
+    pub snap_to_grid: bool,
+    pub conversion_factor: f64,
+}
+
+#[derive(Deserialize,Serialize,Debug,Clone,Default)]
+pub(crate) struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    // Seconds since the Unix epoch, filled in on save
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+}
+
+// Everything saved with a drawing besides its shapes, which live in the scene
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct DocumentProperties {
+    pub grid: GridSettings,
+    pub unit: MeasurementUnit,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+// A drawing as saved to disk; shape coordinates are in internal pixels
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct Document {
+    #[serde(flatten)]
+    pub scene: Scene,
+    #[serde(flatten)]
+    pub properties: DocumentProperties,
+}
+
+impl Document {
+    pub fn to_json(&self) -> Result<String, DocumentError> {
+        let mut fields = match serde_json::to_value(self)? {
+            Value::Object(fields) => fields,
+            _ => unreachable!("documents serialize to JSON objects"),
+        };
+        fields.insert("format".to_string(), Value::from(FORMAT));
+        fields.insert("version".to_string(), Value::from(CURRENT_VERSION));
+        Ok(serde_json::to_string_pretty(&fields)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Document, DocumentError> {
+        let mut fields = match serde_json::from_str(json)? {
+            Value::Object(fields) => fields,
+            _ => return Err(DocumentError::NotADocument),
+        };
+        if fields.remove("format").as_ref().and_then(Value::as_str) != Some(FORMAT) {
+            return Err(DocumentError::NotADocument);
+        }
+        let version = fields
+            .remove("version")
+            .as_ref()
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(DocumentError::NotADocument)?;
+        if version == 0 {
+            return Err(DocumentError::NotADocument);
+        }
+        if version > CURRENT_VERSION {
+            return Err(DocumentError::UnsupportedVersion {
+                found: version,
+                supported: CURRENT_VERSION,
+            });
+        }
+        for migrate in &MIGRATIONS[(version - 1) as usize..] {
+            migrate(&mut fields);
+        }
+        let document: Document = serde_json::from_value(Value::Object(fields))?;
+        if let Some(reason) = document.scene.problem() {
+            return Err(DocumentError::Malformed { format: "drawing", reason });
+        }
+        Ok(document)
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), DocumentError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .ok();
+        let metadata = &mut self.properties.metadata;
+        metadata.created = metadata.created.or(now);
+        metadata.modified = now;
+
+        // Write next to the target and rename so a failed save never truncates the old file
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, self.to_json()?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn open(path: &Path) -> Result<Document, DocumentError> {
+        Document::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::SceneShape;
+    use crate::Shape;
+
+    const GRID: &str =
+        r#""grid": { "gridSize": 20, "unit": "cm", "showGrid": true, "snapToGrid": false, "conversionFactor": 1 }"#;
+    const RECTANGLE: &str =
+        r#"{ "type": "rectangle", "top_left": { "x": 0, "y": 0 }, "bottom_right": { "x": 40, "y": 20 } }"#;
+    const TRIANGLE: &str =
+        r#"{ "type": "polygon", "points": [{ "x": 0, "y": 0 }, { "x": 30, "y": 0 }, { "x": 0, "y": 30 }] }"#;
+    const FIRST_ID: &str = "6f1c1a52-8a39-4a4e-9a5e-4d0f3b1f2c11";
+    const SECOND_ID: &str = "0d9b8c1e-3b1a-4f55-8e5d-7f2a6c9e4b22";
+    const LAYER_ID: &str = "a3e2f6d1-5c4b-4e7a-9f8d-1b2c3d4e5f66";
+
+    fn document(version: u64, body: &str) -> String {
+        format!(r#"{{ "format": "{}", "version": {}, "unit": "cm", {}, {} }}"#, FORMAT, version, GRID, body)
+    }
+
+    // Files as written by each older release, with the same two shapes
+    fn fixtures() -> [(u32, String); 4] {
+        let shapes = format!(r#""shapes": [{}, {}]"#, RECTANGLE, TRIANGLE);
+        let with_ids = format!(
+            r#""shapes": [{{ "id": "{}", "shape": {} }}, {{ "id": "{}", "shape": {} }}]"#,
+            FIRST_ID, RECTANGLE, SECOND_ID, TRIANGLE
+        );
+        let with_layers = format!(
+            r##""layers": [{{ "id": "{layer}", "name": "Plan", "color": "#336699", "visible": true, "locked": false }}],
+            "shapes": [
+                {{ "id": "{}", "layer": "{layer}", "shape": {} }},
+                {{ "id": "{}", "layer": "{layer}", "shape": {} }}
+            ]"##,
+            FIRST_ID,
+            RECTANGLE,
+            SECOND_ID,
+            TRIANGLE,
+            layer = LAYER_ID
+        );
+        [
+            (1, document(1, &shapes)),
+            (2, document(2, &shapes)),
+            (3, document(3, &with_ids)),
+            (4, document(4, &with_layers)),
+        ]
+    }
+
+    #[test]
+    fn migrates_every_older_version() {
+        for (version, json) in fixtures() {
+            let document = Document::from_json(&json).unwrap_or_else(|err| panic!("version {}: {}", version, err));
+            let scene = &document.scene;
+            assert_eq!(document.properties.unit, MeasurementUnit::Centimeter);
+            assert_eq!(scene.layers().len(), 1, "version {}", version);
+            assert!(scene.groups().is_empty());
+
+            let shapes = scene.shapes();
+            assert_eq!(shapes.len(), 2, "version {}", version);
+            assert!(matches!(shapes[0].shape, Shape::Rectangle(_)));
+            assert!(matches!(&shapes[1].shape, Shape::Polygon(polygon) if polygon.points.len() == 3));
+            assert!(shapes.iter().all(|item| item.layer == scene.layers()[0].id && item.group.is_none()));
+            assert_ne!(shapes[0].id, shapes[1].id);
+            if version >= 3 {
+                let ids: Vec<String> = shapes.iter().map(|item| item.id.to_string()).collect();
+                assert_eq!(ids, [FIRST_ID, SECOND_ID]);
+            }
+            let layer = &scene.layers()[0];
+            if version >= 4 {
+                assert_eq!((layer.id.to_string().as_str(), layer.name.as_str()), (LAYER_ID, "Plan"));
+            } else {
+                assert_eq!((layer.name.as_str(), layer.visible, layer.locked), ("Layer 1", true, false));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let json = document(u64::from(CURRENT_VERSION) + 1, r#""shapes": []"#);
+        match Document::from_json(&json) {
+            Err(DocumentError::UnsupportedVersion { found, supported }) => {
+                assert_eq!((found, supported), (CURRENT_VERSION + 1, CURRENT_VERSION));
+            }
+            result => panic!("expected an unsupported version, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rejects_versions_that_are_not_positive_integers() {
+        // 2^32 + 1 would read as version 1 if truncated
+        for version in ["0", "-1", "1.5", "\"1\"", "4294967297"] {
+            let json = format!(r#"{{ "format": "{}", "version": {}, "shapes": [] }}"#, FORMAT, version);
+            assert!(
+                matches!(Document::from_json(&json), Err(DocumentError::NotADocument)),
+                "accepted version {}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_json() {
+        assert!(matches!(Document::from_json("[1, 2]"), Err(DocumentError::NotADocument)));
+        let json = r#"{ "format": "something-else", "version": 1 }"#;
+        assert!(matches!(Document::from_json(json), Err(DocumentError::NotADocument)));
+        assert!(matches!(Document::from_json("{"), Err(DocumentError::Parse(_))));
+    }
+
+    #[test]
+    fn save_and_open_round_trip() {
+        let (_, json) = fixtures().into_iter().last().unwrap();
+        let mut document = Document::from_json(&json).unwrap();
+        document.properties.metadata.title = Some("Ground floor".to_string());
+        let layer = document.scene.layers()[0].id;
+        let circle = r#"{ "type": "circle", "center": { "x": 5, "y": 5 }, "radius": 2.5 }"#;
+        let circle: Shape = serde_json::from_str(circle).unwrap();
+        document.scene.insert(2, SceneShape::new(circle, layer));
+
+        let path = std::env::temp_dir().join(format!("drawing-{}.json", Uuid::new_v4()));
+        document.save(&path).unwrap();
+        let opened = Document::open(&path);
+        fs::remove_file(&path).unwrap();
+        let opened = opened.unwrap();
+
+        assert!(!path.with_extension("tmp").exists());
+        let metadata = &opened.properties.metadata;
+        assert_eq!(metadata.title.as_deref(), Some("Ground floor"));
+        assert!(metadata.created.is_some() && metadata.modified.is_some());
+        assert_eq!(serde_json::to_value(&opened).unwrap(), serde_json::to_value(&document).unwrap());
+    }
+}
@@ -0,0 +1,824 @@
+use serde::Deserialize;
+
+use crate::document::Document;
+use crate::error::GeometryError;
+use crate::flatten;
+use crate::units::{MeasurementUnit, UnitSettings};
+use crate::{ArcClosure, Point, Polygon, Rectangle, Shape};
+
+// Grids denser than this are thinned to major lines only, then dropped altogether
+const MAX_GRID_LINES: usize = 2000;
+
+// 2^53; grid lines further from the origin than this many steps cannot all be told apart
+const MAX_GRID_INDEX: f64 = 9_007_199_254_740_992.0;
+
+// Half-size of the area shown for a drawing without shapes
+const EMPTY_EXTENT: f64 = 100.0;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f64,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: f64) -> Color {
+        Color { r, g, b, a }
+    }
+
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+// Colors and widths below match what GraphGrid.vue and DrawingArea.vue paint
+const MINOR_GRID: Style = Style::stroke(Color::rgb(0xdd, 0xdd, 0xdd), 0.5);
+const MAJOR_GRID: Style = Style::stroke(Color::rgb(0xaa, 0xaa, 0xaa), 0.8);
+const AXES: Style = Style::stroke(Color::rgb(0x66, 0x66, 0x66), 1.5);
+const AXIS_LABEL: Color = Color::rgb(0x66, 0x66, 0x66);
+const AXIS_LABEL_SIZE: f64 = 10.0;
+const TICK_LENGTH: f64 = 5.0;
+const SHAPE_STROKE_WIDTH: f64 = 2.0;
+const VERTEX_MARKER: Color = Color::rgb(0xff, 0x57, 0x22);
+const VERTEX_MARKER_RADIUS: f64 = 4.0;
+const LABEL: Color = Color::rgb(0x00, 0x00, 0x00);
+
+// Stroke widths and text sizes are in screen pixels, independent of the drawing's scale
+#[derive(Debug,Clone,Copy)]
+pub(crate) struct Style {
+    pub stroke: Option<Color>,
+    pub fill: Option<Color>,
+    pub stroke_width: f64,
+}
+
+impl Style {
+    const fn stroke(color: Color, width: f64) -> Style {
+        Style {
+            stroke: Some(color),
+            fill: None,
+            stroke_width: width,
+        }
+    }
+
+    // Outlined in the color of the shape's layer and filled with a tint of it, as on the canvas
+    fn shape(stroke: Color) -> Style {
+        Style {
+            stroke: Some(stroke),
+            fill: Some(Color::rgba(stroke.r, stroke.g, stroke.b, 0.3)),
+            stroke_width: SHAPE_STROKE_WIDTH,
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) enum Baseline {
+    Top,
+    Middle,
+    Bottom,
+}
+
+// Output-independent drawing operations; positions are in internal pixels, Y up
+#[derive(Debug,Clone)]
+pub(crate) enum Primitive {
+    Line {
+        from: Point,
+        to: Point,
+        style: Style,
+    },
+    // Closed outline; `holes` are cut out of the fill by the even-odd rule
+    Polygon {
+        points: Vec<Point>,
+        holes: Vec<Vec<Point>>,
+        style: Style,
+    },
+    Circle {
+        center: Point,
+        radius: f64,
+        style: Style,
+    },
+    Text {
+        position: Point,
+        text: String,
+        size: f64,
+        color: Color,
+        anchor: Anchor,
+        baseline: Baseline,
+    },
+}
+
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(default)]
+pub(crate) struct DrawingOptions {
+    // Defaults to the document's own grid setting
+    pub show_grid: Option<bool>,
+    pub show_axes: bool,
+    // Vertex markers and coordinate labels, as shown while drawing
+    pub show_labels: bool,
+    // Space around the shapes, in internal pixels
+    pub margin: f64,
+}
+
+impl Default for DrawingOptions {
+    fn default() -> Self {
+        DrawingOptions {
+            show_grid: None,
+            show_axes: true,
+            show_labels: false,
+            margin: 20.0,
+        }
+    }
+}
+
+// A document laid out for output: everything inside `bounds` (normalized, internal pixels)
+pub(crate) struct Drawing {
+    pub bounds: Rectangle,
+    // Grid and axes, painted below the shapes
+    pub guides: Vec<Primitive>,
+    pub shapes: Vec<Primitive>,
+    pub unit: MeasurementUnit,
+    // Internal pixels per `unit`
+    pub pixels_per_unit: f64,
+}
+
+impl Drawing {
+    pub fn new(
+        document: &Document,
+        settings: &UnitSettings,
+        options: &DrawingOptions,
+    ) -> Result<Drawing, GeometryError> {
+        if !(options.margin.is_finite() && options.margin >= 0.0) {
+            return Err(GeometryError::OutOfRange {
+                field: "margin".to_string(),
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+        let show_grid = options.show_grid.unwrap_or(document.properties.grid.show_grid);
+        let shapes = document.scene.visible();
+        let mut bounds = shapes
+            .iter()
+            .filter_map(|item| item.shape.bounding_box())
+            .reduce(|a, b| a.union(&b));
+        if options.show_axes {
+            let origin = Rectangle {
+                top_left: Point { x: 0.0, y: 0.0 },
+                bottom_right: Point { x: 0.0, y: 0.0 },
+            };
+            bounds = Some(bounds.map_or(origin, |b| b.union(&origin)));
+        }
+        let bounds = match bounds {
+            Some(b) => expand(&b, options.margin),
+            None => expand(
+                &Rectangle {
+                    top_left: Point { x: 0.0, y: 0.0 },
+                    bottom_right: Point { x: 0.0, y: 0.0 },
+                },
+                EMPTY_EXTENT,
+            ),
+        };
+
+        let mut drawing = Drawing {
+            bounds,
+            guides: Vec::new(),
+            shapes: Vec::new(),
+            unit: document.properties.unit,
+            pixels_per_unit: document.properties.unit.pixels_per_unit(settings.dpi),
+        };
+        if show_grid {
+            drawing.add_grid(document.properties.grid.grid_size);
+        }
+        if options.show_axes {
+            drawing.add_axes(document.properties.grid.grid_size);
+        }
+        for item in &shapes {
+            let (r, g, b) = document.scene.layer(item.layer).map_or((0, 0, 0), |layer| layer.rgb());
+            drawing.add_shape(&item.shape, Color::rgb(r, g, b));
+        }
+        if options.show_labels {
+            for item in &shapes {
+                drawing.add_labels(&item.shape);
+            }
+        }
+        Ok(drawing)
+    }
+
+    pub fn width(&self) -> f64 {
+        self.bounds.bottom_right.x - self.bounds.top_left.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bounds.bottom_right.y - self.bounds.top_left.y
+    }
+
+    fn add_shape(&mut self, shape: &Shape, color: Color) {
+        let style = Style::shape(color);
+        match shape {
+            Shape::Rectangle(rect) => {
+                let rect = rect.normalized();
+                let (min, max) = (rect.top_left, rect.bottom_right);
+                self.shapes.push(Primitive::Polygon {
+                    points: vec![
+                        min,
+                        Point { x: max.x, y: min.y },
+                        max,
+                        Point { x: min.x, y: max.y },
+                    ],
+                    holes: Vec::new(),
+                    style,
+                });
+            }
+            Shape::OrientedRectangle(rect) => self.shapes.push(Primitive::Polygon {
+                points: rect.corners().to_vec(),
+                holes: Vec::new(),
+                style,
+            }),
+            Shape::Circle(circle) => self.shapes.push(Primitive::Circle {
+                center: circle.center,
+                radius: circle.radius.abs(),
+                style,
+            }),
+            Shape::Ellipse(ellipse) => self.shapes.push(Primitive::Polygon {
+                points: ellipse.outline(flatten::DEFAULT_TOLERANCE),
+                holes: Vec::new(),
+                style,
+            }),
+            // Open arcs are stroked segment by segment, without a fill
+            Shape::Arc(arc) if arc.closure == ArcClosure::Open => {
+                let style = Style { fill: None, ..style };
+                for pair in arc.points(flatten::DEFAULT_TOLERANCE).windows(2) {
+                    self.shapes.push(Primitive::Line {
+                        from: pair[0],
+                        to: pair[1],
+                        style,
+                    });
+                }
+            }
+            Shape::Arc(arc) => self.shapes.push(Primitive::Polygon {
+                points: arc.outline(flatten::DEFAULT_TOLERANCE),
+                holes: Vec::new(),
+                style,
+            }),
+            Shape::Segment(segment) => self.shapes.push(Primitive::Line {
+                from: segment.start,
+                to: segment.end,
+                style: Style { fill: None, ..style },
+            }),
+            Shape::Polyline(polyline) => {
+                let style = Style { fill: None, ..style };
+                for pair in polyline.points.windows(2) {
+                    self.shapes.push(Primitive::Line {
+                        from: pair[0],
+                        to: pair[1],
+                        style,
+                    });
+                }
+            }
+            Shape::Path(path) if path.closed => self.shapes.push(Primitive::Polygon {
+                points: path.points(flatten::DEFAULT_TOLERANCE),
+                holes: Vec::new(),
+                style,
+            }),
+            Shape::Path(path) => {
+                let style = Style { fill: None, ..style };
+                for pair in path.points(flatten::DEFAULT_TOLERANCE).windows(2) {
+                    self.shapes.push(Primitive::Line {
+                        from: pair[0],
+                        to: pair[1],
+                        style,
+                    });
+                }
+            }
+            Shape::Polygon(polygon) => self.shapes.push(Primitive::Polygon {
+                points: polygon.points.clone(),
+                holes: polygon.holes.clone(),
+                style,
+            }),
+            Shape::MultiPolygon(multi) => {
+                for polygon in &multi.polygons {
+                    self.shapes.push(Primitive::Polygon {
+                        points: polygon.points.clone(),
+                        holes: polygon.holes.clone(),
+                        style,
+                    });
+                }
+            }
+        }
+    }
+
+    // Mirrors the markers and `formatPoint` labels DrawingArea.vue paints for the current shape
+    fn add_labels(&mut self, shape: &Shape) {
+        let label = |position: Point, text: String, size: f64, anchor: Anchor, baseline: Baseline| {
+            Primitive::Text {
+                position,
+                text,
+                size,
+                color: LABEL,
+                anchor,
+                baseline,
+            }
+        };
+        match shape {
+            Shape::Rectangle(rect) => {
+                for corner in [rect.top_left, rect.bottom_right] {
+                    self.add_marker(corner);
+                }
+                self.shapes.push(label(
+                    Point { x: rect.top_left.x, y: rect.top_left.y + 5.0 },
+                    self.format_point(&rect.top_left),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: rect.bottom_right.x, y: rect.bottom_right.y - 5.0 },
+                    self.format_point(&rect.bottom_right),
+                    12.0,
+                    Anchor::End,
+                    Baseline::Top,
+                ));
+            }
+            Shape::OrientedRectangle(rect) => {
+                for corner in rect.corners() {
+                    self.add_marker(corner);
+                }
+                let center = rect.center;
+                self.add_marker(center);
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y },
+                    format!("Center: {}", self.format_point(&center)),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y - 15.0 },
+                    format!(
+                        "{} × {} {}, {}°",
+                        self.format_length(rect.width),
+                        self.format_length(rect.height),
+                        self.unit,
+                        format_number(rect.angle, 1)
+                    ),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+            }
+            Shape::Ellipse(ellipse) => {
+                let center = ellipse.center;
+                self.add_marker(center);
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y },
+                    format!("Center: {}", self.format_point(&center)),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y - 15.0 },
+                    format!(
+                        "Radii: {} × {} {}, {}°",
+                        self.format_length(ellipse.rx),
+                        self.format_length(ellipse.ry),
+                        self.unit,
+                        format_number(ellipse.angle, 1)
+                    ),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+            }
+            Shape::Arc(arc) => {
+                for point in [arc.center, arc.start(), arc.end()] {
+                    self.add_marker(point);
+                }
+                let center = arc.center;
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y },
+                    format!("Center: {}", self.format_point(&center)),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y - 15.0 },
+                    format!(
+                        "Radius: {} {}, {}° from {}°",
+                        self.format_length(arc.radius),
+                        self.unit,
+                        format_number(arc.sweep_angle, 1),
+                        format_number(arc.start_angle, 1)
+                    ),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+            }
+            Shape::Circle(circle) => {
+                let center = circle.center;
+                self.add_marker(center);
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y },
+                    format!("Center: {}", self.format_point(&center)),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: center.x + 10.0, y: center.y - 15.0 },
+                    format!("Radius: {} {}", self.format_length(circle.radius.abs()), self.unit),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+            }
+            Shape::Segment(segment) => self.add_path_labels(&[segment.start, segment.end]),
+            Shape::Polyline(polyline) => self.add_path_labels(&polyline.points),
+            Shape::Path(path) => {
+                // Markers on the curve only, not at control points
+                self.add_marker(path.start);
+                for segment in &path.segments {
+                    self.add_marker(segment.end());
+                }
+                let start = path.start;
+                self.shapes.push(label(
+                    Point { x: start.x + 10.0, y: start.y },
+                    format!("Start: {}", self.format_point(&start)),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+                self.shapes.push(label(
+                    Point { x: start.x + 10.0, y: start.y - 15.0 },
+                    format!("Length: {} {}", self.format_length(path.length()), self.unit),
+                    12.0,
+                    Anchor::Start,
+                    Baseline::Bottom,
+                ));
+            }
+            Shape::Polygon(polygon) => self.add_polygon_labels(polygon, ""),
+            Shape::MultiPolygon(multi) => {
+                for (index, polygon) in multi.polygons.iter().enumerate() {
+                    self.add_polygon_labels(polygon, &format!("{}.", index + 1));
+                }
+            }
+        }
+    }
+
+    // Numbered outer vertices; hole vertices get markers only. `prefix` tells multi-polygon members apart.
+    fn add_polygon_labels(&mut self, polygon: &Polygon, prefix: &str) {
+        for (index, point) in polygon.points.iter().enumerate() {
+            self.add_marker(*point);
+            self.shapes.push(Primitive::Text {
+                position: Point { x: point.x + 5.0, y: point.y + 5.0 },
+                text: format!("{}{}: {}", prefix, index + 1, self.format_point(point)),
+                size: 10.0,
+                color: LABEL,
+                anchor: Anchor::Start,
+                baseline: Baseline::Bottom,
+            });
+        }
+        for point in polygon.holes.iter().flatten() {
+            self.add_marker(*point);
+        }
+    }
+
+    // Numbered vertices, and the length of each segment at its midpoint
+    fn add_path_labels(&mut self, points: &[Point]) {
+        for (index, point) in points.iter().enumerate() {
+            self.add_marker(*point);
+            self.shapes.push(Primitive::Text {
+                position: Point { x: point.x + 5.0, y: point.y + 5.0 },
+                text: format!("{}: {}", index + 1, self.format_point(point)),
+                size: 10.0,
+                color: LABEL,
+                anchor: Anchor::Start,
+                baseline: Baseline::Bottom,
+            });
+        }
+        for pair in points.windows(2) {
+            self.shapes.push(Primitive::Text {
+                position: Point { x: (pair[0].x + pair[1].x) / 2.0, y: (pair[0].y + pair[1].y) / 2.0 - 5.0 },
+                text: format!("{} {}", self.format_length(pair[0].distance(&pair[1])), self.unit),
+                size: 10.0,
+                color: LABEL,
+                anchor: Anchor::Middle,
+                baseline: Baseline::Top,
+            });
+        }
+    }
+
+    fn add_marker(&mut self, at: Point) {
+        self.shapes.push(Primitive::Circle {
+            center: at,
+            radius: VERTEX_MARKER_RADIUS,
+            style: Style {
+                stroke: None,
+                fill: Some(VERTEX_MARKER),
+                stroke_width: 0.0,
+            },
+        });
+    }
+
+    fn format_length(&self, pixels: f64) -> String {
+        format!("{:.2}", pixels / self.pixels_per_unit)
+    }
+
+    // Same layout as `formatPoint` in coordinates.ts
+    fn format_point(&self, point: &Point) -> String {
+        format!(
+            "({} {unit}, {} {unit})",
+            self.format_length(point.x),
+            self.format_length(point.y),
+            unit = self.unit
+        )
+    }
+
+    // Indices of the multiples of `step` that fall within `min..=max` and how many there are,
+    // counted in f64 so huge extents cannot overflow; `None` past `MAX_GRID_INDEX`
+    fn grid_indices(min: f64, max: f64, step: f64) -> Option<(std::ops::RangeInclusive<i64>, f64)> {
+        let (first, last) = ((min / step).ceil(), (max / step).floor());
+        if !(first.abs() <= MAX_GRID_INDEX && last.abs() <= MAX_GRID_INDEX) {
+            return None;
+        }
+        Some((first as i64..=last as i64, (last - first + 1.0).max(0.0)))
+    }
+
+    fn add_grid(&mut self, grid_size: f64) {
+        if !(grid_size.is_finite() && grid_size > 0.0) {
+            return;
+        }
+        let Rectangle { top_left: min, bottom_right: max } = self.bounds;
+        let major = self.unit.major_grid_interval() as i64;
+        let indices = (
+            Drawing::grid_indices(min.x, max.x, grid_size),
+            Drawing::grid_indices(min.y, max.y, grid_size),
+        );
+        let (Some((columns, column_count)), Some((rows, row_count))) = indices else {
+            return;
+        };
+        let line_count = column_count + row_count;
+        if line_count / major as f64 > MAX_GRID_LINES as f64 {
+            return;
+        }
+        let draw_minor = line_count <= MAX_GRID_LINES as f64;
+
+        // Minor lines first so the major ones are painted over them
+        for pass_major in [false, true] {
+            if !pass_major && !draw_minor {
+                continue;
+            }
+            let style = if pass_major { MAJOR_GRID } else { MINOR_GRID };
+            for i in columns.clone().filter(|i| (i % major == 0) == pass_major) {
+                let x = i as f64 * grid_size;
+                self.guides.push(Primitive::Line {
+                    from: Point { x, y: min.y },
+                    to: Point { x, y: max.y },
+                    style,
+                });
+            }
+            for i in rows.clone().filter(|i| (i % major == 0) == pass_major) {
+                let y = i as f64 * grid_size;
+                self.guides.push(Primitive::Line {
+                    from: Point { x: min.x, y },
+                    to: Point { x: max.x, y },
+                    style,
+                });
+            }
+        }
+    }
+
+    fn add_axes(&mut self, grid_size: f64) {
+        let Rectangle { top_left: min, bottom_right: max } = self.bounds;
+        self.guides.push(Primitive::Line {
+            from: Point { x: min.x, y: 0.0 },
+            to: Point { x: max.x, y: 0.0 },
+            style: AXES,
+        });
+        self.guides.push(Primitive::Line {
+            from: Point { x: 0.0, y: min.y },
+            to: Point { x: 0.0, y: max.y },
+            style: AXES,
+        });
+
+        if grid_size.is_finite() && grid_size > 0.0 {
+            let step = grid_size * self.unit.major_grid_interval() as f64;
+            let indices = (Drawing::grid_indices(min.x, max.x, step), Drawing::grid_indices(min.y, max.y, step));
+            match indices {
+                (Some((columns, column_count)), Some((rows, row_count)))
+                    if column_count + row_count <= MAX_GRID_LINES as f64 =>
+                {
+                    for i in columns.filter(|&i| i != 0) {
+                        let x = i as f64 * step;
+                        self.add_tick(Point { x, y: 0.0 }, true);
+                    }
+                    for i in rows.filter(|&i| i != 0) {
+                        let y = i as f64 * step;
+                        self.add_tick(Point { x: 0.0, y }, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.guides.push(Primitive::Text {
+            position: Point { x: TICK_LENGTH + 1.0, y: -(TICK_LENGTH + 1.0) },
+            text: "0".to_string(),
+            size: AXIS_LABEL_SIZE,
+            color: AXIS_LABEL,
+            anchor: Anchor::Start,
+            baseline: Baseline::Top,
+        });
+        self.guides.push(Primitive::Text {
+            position: Point { x: max.x - 10.0, y: min.y + 10.0 },
+            text: self.unit.symbol().to_string(),
+            size: AXIS_LABEL_SIZE,
+            color: AXIS_LABEL,
+            anchor: Anchor::End,
+            baseline: Baseline::Bottom,
+        });
+    }
+
+    // Tick mark and value label for a point on the X axis (`horizontal`) or the Y axis
+    fn add_tick(&mut self, at: Point, horizontal: bool) {
+        let (offset, value) = if horizontal {
+            (Point { x: 0.0, y: TICK_LENGTH }, at.x)
+        } else {
+            (Point { x: TICK_LENGTH, y: 0.0 }, at.y)
+        };
+        self.guides.push(Primitive::Line {
+            from: Point { x: at.x - offset.x, y: at.y - offset.y },
+            to: Point { x: at.x + offset.x, y: at.y + offset.y },
+            style: AXES,
+        });
+        let text = format_number(value / self.pixels_per_unit, 2);
+        let label = if horizontal {
+            Primitive::Text {
+                position: Point { x: at.x, y: at.y - TICK_LENGTH - 1.0 },
+                text,
+                size: AXIS_LABEL_SIZE,
+                color: AXIS_LABEL,
+                anchor: Anchor::Middle,
+                baseline: Baseline::Top,
+            }
+        } else {
+            Primitive::Text {
+                position: Point { x: at.x - TICK_LENGTH - 1.0, y: at.y },
+                text,
+                size: AXIS_LABEL_SIZE,
+                color: AXIS_LABEL,
+                anchor: Anchor::End,
+                baseline: Baseline::Middle,
+            }
+        };
+        self.guides.push(label);
+    }
+}
+
+fn expand(rect: &Rectangle, margin: f64) -> Rectangle {
+    Rectangle {
+        top_left: Point {
+            x: rect.top_left.x - margin,
+            y: rect.top_left.y - margin,
+        },
+        bottom_right: Point {
+            x: rect.bottom_right.x + margin,
+            y: rect.bottom_right.y + margin,
+        },
+    }
+}
+
+// Fixed-point formatting without trailing zeros, e.g. `2.5` rather than `2.50`
+pub(crate) fn format_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &text
+    };
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{DocumentProperties, GridSettings, Metadata};
+    use crate::scene::{Scene, SceneShape};
+
+    // A pixel drawing of one rectangle from `min` to `max` with a grid every `grid_size`
+    fn document(min: f64, max: f64, grid_size: f64) -> Document {
+        let mut scene = Scene::default();
+        let layer = scene.layers()[0].id;
+        let rect = Rectangle {
+            top_left: Point { x: min, y: min },
+            bottom_right: Point { x: max, y: max },
+        };
+        scene.insert(0, SceneShape::new(Shape::Rectangle(rect), layer));
+        Document {
+            scene,
+            properties: DocumentProperties {
+                grid: GridSettings {
+                    grid_size,
+                    unit: MeasurementUnit::Pixel,
+                    show_grid: true,
+                    snap_to_grid: false,
+                    conversion_factor: 1.0,
+                },
+                unit: MeasurementUnit::Pixel,
+                metadata: Metadata::default(),
+            },
+        }
+    }
+
+    fn options(margin: f64) -> DrawingOptions {
+        DrawingOptions {
+            show_grid: Some(true),
+            show_axes: false,
+            show_labels: false,
+            margin,
+        }
+    }
+
+    fn grid_lines(drawing: &Drawing) -> usize {
+        drawing.guides.iter().filter(|guide| matches!(guide, Primitive::Line { .. })).count()
+    }
+
+    #[test]
+    fn draws_a_line_at_every_grid_step() {
+        let drawing = Drawing::new(&document(0.0, 100.0, 20.0), &UnitSettings::default(), &options(10.0)).unwrap();
+        assert_eq!((drawing.width(), drawing.height()), (120.0, 120.0));
+        // 0, 20, ..., 100 across and down
+        assert_eq!(grid_lines(&drawing), 12);
+    }
+
+    #[test]
+    fn thins_then_drops_dense_grids() {
+        let settings = UnitSettings::default();
+        // 2 * 2001 lines are too many for minor lines, but every fifth one is a major line
+        let drawing = Drawing::new(&document(0.0, 2000.0, 1.0), &settings, &options(0.0)).unwrap();
+        assert_eq!(grid_lines(&drawing), 2 * 401);
+        let drawing = Drawing::new(&document(0.0, 20_000.0, 1.0), &settings, &options(0.0)).unwrap();
+        assert_eq!(grid_lines(&drawing), 0);
+    }
+
+    #[test]
+    fn skips_grids_too_large_to_count() {
+        let settings = UnitSettings::default();
+        let cases = [
+            // More lines than fit in an i64
+            (-1e300, 1e300, 1e-300),
+            // Few lines, but too far out for their indices to be exact
+            (1e20, 1e20 + 1e5, 1e4),
+            (-1e20 - 1e5, -1e20, 1e4),
+        ];
+        for (min, max, grid_size) in cases {
+            let with_axes = DrawingOptions { show_axes: true, ..options(0.0) };
+            let drawing = Drawing::new(&document(min, max, grid_size), &settings, &with_axes).unwrap();
+            // Only the two axes remain
+            assert_eq!(grid_lines(&drawing), 2, "{} to {} every {}", min, max, grid_size);
+        }
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_margins() {
+        let document = document(0.0, 100.0, 20.0);
+        for margin in [-1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                Drawing::new(&document, &UnitSettings::default(), &options(margin)).err(),
+                Some(GeometryError::OutOfRange {
+                    field: "margin".to_string(),
+                    min: 0.0,
+                    max: f64::INFINITY,
+                }),
+                "margin {}",
+                margin
+            );
+        }
+        assert!(Drawing::new(&document, &UnitSettings::default(), &options(0.0)).is_ok());
+    }
+
+    #[test]
+    fn formats_numbers_without_trailing_zeros() {
+        assert_eq!(format_number(1.5, 6), "1.5");
+        assert_eq!(format_number(2.0, 6), "2");
+        assert_eq!(format_number(-0.0000001, 6), "0");
+        assert_eq!(format_number(1234.5678, 2), "1234.57");
+    }
+}
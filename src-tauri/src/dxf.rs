@@ -0,0 +1,1202 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::document::Document;
+use crate::drawing::format_number;
+use crate::error::DocumentError;
+use crate::flatten;
+use crate::precision::Precision;
+use crate::scene::Layer;
+use crate::svg_import::{dedup_closing_point, ImportIssue};
+use crate::units::{MeasurementUnit, UnitSettings};
+use crate::{
+    ring_contains, ring_signed_area, Arc, ArcClosure, Circle, Ellipse, OrientedRectangle, Point, Polygon, Polyline, Rectangle,
+    Segment, Shape,
+};
+
+// Coordinates are written in the document's unit; this keeps sub-micron precision in all of them
+const DECIMALS: usize = 6;
+
+// Rotated rectangles still read back as rectangles after rounding to `DECIMALS`
+const RECTANGLE_PRECISION: Precision = Precision {
+    epsilon: 1e-6,
+    decimals: None,
+};
+
+// Endpoints of LINE entities closer than this, in file units, are joined into one outline
+const JOIN_TOLERANCE: f64 = 1e-6;
+
+// DXF has no pixel unit, so pixel drawings are written as unitless with this comment, which
+// tells `import` to read them back in pixels rather than the current unit
+const PIXEL_COMMENT: &str = "hello-tauri-world: unitless coordinates are pixels";
+
+// Layer name and AutoCAD color index for polygon holes. Closed outlines on this layer are cut
+// out of the polygon around them.
+const HOLE_LAYER: (&str, u8) = ("Holes", 8);
+
+// AutoCAD color indices of the standard colors, which every CAD program shows the same way.
+// Index 7 is black or white, whichever contrasts with the background.
+const STANDARD_COLORS: [(u8, (u8, u8, u8)); 10] = [
+    (1, (255, 0, 0)),
+    (2, (255, 255, 0)),
+    (3, (0, 255, 0)),
+    (4, (0, 255, 255)),
+    (5, (0, 0, 255)),
+    (6, (255, 0, 255)),
+    (7, (0, 0, 0)),
+    (7, (255, 255, 255)),
+    (8, (128, 128, 128)),
+    (9, (192, 192, 192)),
+];
+
+#[derive(Serialize,Debug)]
+pub(crate) struct DxfImport {
+    pub shapes: Vec<Shape>,
+    // Layer of each shape, by index
+    pub layers: Vec<String>,
+    pub unit: MeasurementUnit,
+    // Entities that were imported only approximately, e.g. arc segments of polylines
+    pub warnings: Vec<ImportIssue>,
+    // Entities that could not be converted and were left out
+    pub unsupported: Vec<ImportIssue>,
+}
+
+// `$INSUNITS` code for a unit; pixels have no CAD equivalent and are written as unitless, see
+// `PIXEL_COMMENT`
+fn insunits(unit: MeasurementUnit) -> u8 {
+    match unit {
+        MeasurementUnit::Pixel => 0,
+        MeasurementUnit::Inch => 1,
+        MeasurementUnit::Millimeter => 4,
+        MeasurementUnit::Centimeter => 5,
+    }
+}
+
+// Unit and multiplier for a `$INSUNITS` code, e.g. feet are 12 inches
+fn from_insunits(code: i64) -> Option<(MeasurementUnit, f64)> {
+    match code {
+        1 => Some((MeasurementUnit::Inch, 1.0)),
+        2 => Some((MeasurementUnit::Inch, 12.0)),
+        4 => Some((MeasurementUnit::Millimeter, 1.0)),
+        5 => Some((MeasurementUnit::Centimeter, 1.0)),
+        6 => Some((MeasurementUnit::Centimeter, 100.0)),
+        _ => None,
+    }
+}
+
+// Writes the document as an ASCII DXF (AutoCAD 2000) file in the document's unit.
+// Rectangles and polygons become closed LWPOLYLINEs and circles CIRCLEs. Each scene layer becomes
+// a DXF layer with the nearest standard color, turned off when hidden; polygon holes go on a layer
+// of their own.
+pub(crate) fn export(document: &Document, settings: &UnitSettings) -> String {
+    let unit = document.properties.unit;
+    let scale = 1.0 / unit.pixels_per_unit(settings.dpi);
+    let num = |value: f64| format_number(value * scale, DECIMALS);
+    let mut writer = DxfWriter::default();
+
+    if unit == MeasurementUnit::Pixel {
+        writer.pair(999, PIXEL_COMMENT);
+    }
+    writer.pair(0, "SECTION");
+    writer.pair(2, "HEADER");
+    writer.pair(9, "$ACADVER");
+    writer.pair(1, "AC1015");
+    writer.pair(9, "$INSUNITS");
+    writer.pair(70, insunits(unit));
+    // Metric drawing flag: 0 imperial, 1 metric
+    writer.pair(9, "$MEASUREMENT");
+    writer.pair(70, if unit == MeasurementUnit::Inch { 0 } else { 1 });
+    writer.pair(0, "ENDSEC");
+
+    writer.pair(0, "SECTION");
+    writer.pair(2, "TABLES");
+    writer.pair(0, "TABLE");
+    writer.pair(2, "LTYPE");
+    writer.pair(70, 1);
+    writer.pair(0, "LTYPE");
+    writer.handle();
+    writer.pair(100, "AcDbSymbolTableRecord");
+    writer.pair(100, "AcDbLinetypeTableRecord");
+    writer.pair(2, "CONTINUOUS");
+    writer.pair(70, 0);
+    writer.pair(3, "Solid line");
+    writer.pair(72, 65);
+    writer.pair(73, 0);
+    writer.pair(40, 0.0);
+    writer.pair(0, "ENDTAB");
+    writer.pair(0, "TABLE");
+    writer.pair(2, "LAYER");
+    let layers = document.scene.layers();
+    let names = layer_names(layers);
+    writer.pair(70, layers.len() + 2);
+    writer.layer("0", 7, true, false);
+    writer.layer(HOLE_LAYER.0, HOLE_LAYER.1, true, false);
+    for layer in layers {
+        writer.layer(&names[&layer.id], standard_color(layer.rgb()), layer.visible, layer.locked);
+    }
+    writer.pair(0, "ENDTAB");
+    writer.pair(0, "ENDSEC");
+
+    writer.pair(0, "SECTION");
+    writer.pair(2, "ENTITIES");
+    for item in document.scene.ordered() {
+        let layer = names[&item.layer].as_str();
+        match &item.shape {
+            Shape::Rectangle(rect) => writer.polyline(layer, &rectangle_ring(rect), &num),
+            Shape::OrientedRectangle(rect) => writer.polyline(layer, &rect.corners(), &num),
+            Shape::Circle(circle) => {
+                writer.pair(0, "CIRCLE");
+                writer.handle();
+                writer.pair(100, "AcDbEntity");
+                writer.pair(8, layer);
+                writer.pair(100, "AcDbCircle");
+                writer.pair(10, num(circle.center.x));
+                writer.pair(20, num(circle.center.y));
+                writer.pair(30, 0.0);
+                writer.pair(40, num(circle.radius));
+            }
+            Shape::Ellipse(ellipse) => {
+                // DXF describes the major axis by its endpoint and the minor one by their ratio
+                let (major, minor, angle) = if ellipse.rx >= ellipse.ry {
+                    (ellipse.rx, ellipse.ry, ellipse.angle)
+                } else {
+                    (ellipse.ry, ellipse.rx, ellipse.angle + 90.0)
+                };
+                let (sin, cos) = angle.to_radians().sin_cos();
+                writer.pair(0, "ELLIPSE");
+                writer.handle();
+                writer.pair(100, "AcDbEntity");
+                writer.pair(8, layer);
+                writer.pair(100, "AcDbEllipse");
+                writer.pair(10, num(ellipse.center.x));
+                writer.pair(20, num(ellipse.center.y));
+                writer.pair(30, 0.0);
+                writer.pair(11, num(major * cos));
+                writer.pair(21, num(major * sin));
+                writer.pair(31, 0.0);
+                writer.pair(40, format_number(if major > 0.0 { minor / major } else { 1.0 }, DECIMALS));
+                writer.pair(41, 0.0);
+                writer.pair(42, format_number(2.0 * PI, DECIMALS));
+            }
+            Shape::Arc(arc) if arc.closure == ArcClosure::Open => {
+                // Always counter-clockwise, so clockwise arcs start at their end
+                let start = arc.start_angle + arc.sweep_angle.min(0.0);
+                writer.pair(0, "ARC");
+                writer.handle();
+                writer.pair(100, "AcDbEntity");
+                writer.pair(8, layer);
+                writer.pair(100, "AcDbCircle");
+                writer.pair(10, num(arc.center.x));
+                writer.pair(20, num(arc.center.y));
+                writer.pair(30, 0.0);
+                writer.pair(40, num(arc.radius));
+                writer.pair(100, "AcDbArc");
+                writer.pair(50, format_number(start, DECIMALS));
+                writer.pair(51, format_number(start + arc.sweep_angle.abs(), DECIMALS));
+            }
+            Shape::Arc(arc) => {
+                // A bulged segment for the curve, back through the center for sectors
+                let bulge = (arc.sweep_angle.to_radians() / 4.0).tan();
+                let mut vertices = vec![(arc.start(), bulge), (arc.end(), 0.0)];
+                if arc.is_full_circle() {
+                    // One segment cannot close on itself, so go round in two halves
+                    let half = (arc.sweep_angle.to_radians() / 8.0).tan();
+                    vertices = vec![(arc.start(), half), (arc.point_at(arc.start_angle + arc.sweep_angle / 2.0), half)];
+                } else if arc.closure == ArcClosure::Sector {
+                    vertices.push((arc.center, 0.0));
+                }
+                writer.bulged_polyline(layer, &vertices, true, &num);
+            }
+            Shape::Segment(segment) => {
+                writer.pair(0, "LINE");
+                writer.handle();
+                writer.pair(100, "AcDbEntity");
+                writer.pair(8, layer);
+                writer.pair(100, "AcDbLine");
+                writer.pair(10, num(segment.start.x));
+                writer.pair(20, num(segment.start.y));
+                writer.pair(30, 0.0);
+                writer.pair(11, num(segment.end.x));
+                writer.pair(21, num(segment.end.y));
+                writer.pair(31, 0.0);
+            }
+            Shape::Polyline(polyline) => {
+                let vertices: Vec<(Point, f64)> = polyline.points.iter().map(|&p| (p, 0.0)).collect();
+                writer.bulged_polyline(layer, &vertices, false, &num);
+            }
+            // Curves are written flattened
+            Shape::Path(path) if path.closed => {
+                writer.polyline(layer, &path.points(flatten::DEFAULT_TOLERANCE), &num)
+            }
+            Shape::Path(path) => {
+                let vertices: Vec<(Point, f64)> =
+                    path.points(flatten::DEFAULT_TOLERANCE).into_iter().map(|p| (p, 0.0)).collect();
+                writer.bulged_polyline(layer, &vertices, false, &num);
+            }
+            Shape::Polygon(polygon) => writer.polygon(layer, polygon, &num),
+            Shape::MultiPolygon(multi) => {
+                for polygon in &multi.polygons {
+                    writer.polygon(layer, polygon, &num);
+                }
+            }
+        }
+    }
+    writer.pair(0, "ENDSEC");
+    writer.pair(0, "EOF");
+    writer.text
+}
+
+#[derive(Default)]
+struct DxfWriter {
+    text: String,
+    next_handle: u32,
+}
+
+impl DxfWriter {
+    // DXF is a flat list of group code and value lines
+    fn pair(&mut self, code: u16, value: impl std::fmt::Display) {
+        let _ = write!(self.text, "{:>3}\n{}\n", code, value);
+    }
+
+    fn handle(&mut self) {
+        self.next_handle += 1;
+        let handle = format!("{:X}", self.next_handle + 0x100);
+        self.pair(5, handle);
+    }
+
+    // Negative colors mark layers that are off; flag 4 locks a layer
+    fn layer(&mut self, name: &str, color: u8, visible: bool, locked: bool) {
+        self.pair(0, "LAYER");
+        self.handle();
+        self.pair(100, "AcDbSymbolTableRecord");
+        self.pair(100, "AcDbLayerTableRecord");
+        self.pair(2, name);
+        self.pair(70, if locked { 4 } else { 0 });
+        self.pair(62, if visible { i16::from(color) } else { -i16::from(color) });
+        self.pair(6, "CONTINUOUS");
+    }
+
+    fn polygon(&mut self, layer: &str, polygon: &Polygon, num: &dyn Fn(f64) -> String) {
+        self.polyline(layer, &polygon.points, num);
+        for hole in &polygon.holes {
+            self.polyline(HOLE_LAYER.0, hole, num);
+        }
+    }
+
+    fn polyline(&mut self, layer: &str, points: &[Point], num: &dyn Fn(f64) -> String) {
+        let vertices: Vec<(Point, f64)> = points.iter().map(|&p| (p, 0.0)).collect();
+        self.bulged_polyline(layer, &vertices, true, num);
+    }
+
+    // Each vertex's bulge curves the segment that starts there
+    fn bulged_polyline(&mut self, layer: &str, vertices: &[(Point, f64)], closed: bool, num: &dyn Fn(f64) -> String) {
+        self.pair(0, "LWPOLYLINE");
+        self.handle();
+        self.pair(100, "AcDbEntity");
+        self.pair(8, layer);
+        self.pair(100, "AcDbPolyline");
+        self.pair(90, vertices.len());
+        self.pair(70, u8::from(closed));
+        for &(p, bulge) in vertices {
+            self.pair(10, num(p.x));
+            self.pair(20, num(p.y));
+            if bulge != 0.0 {
+                // The arc's center depends sensitively on the bulge, so keep all of it
+                self.pair(42, bulge);
+            }
+        }
+    }
+}
+
+// The standard AutoCAD color closest to `rgb`
+fn standard_color((r, g, b): (u8, u8, u8)) -> u8 {
+    let distance = |(sr, sg, sb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, sr) + d(g, sg) + d(b, sb)
+    };
+    STANDARD_COLORS
+        .iter()
+        .min_by_key(|(_, color)| distance(*color))
+        .map_or(7, |(index, _)| *index)
+}
+
+// DXF layer names for the scene's layers, with characters DXF does not allow replaced and
+// numbers added to tell apart layers of the same name
+fn layer_names(layers: &[Layer]) -> HashMap<Uuid, String> {
+    let mut taken: HashSet<String> = ["0", HOLE_LAYER.0].iter().map(|name| name.to_lowercase()).collect();
+    let mut names = HashMap::new();
+    for layer in layers {
+        let base: String = layer
+            .name
+            .trim()
+            .chars()
+            .map(|c| if c.is_control() || "<>/\\\":;?*|=`".contains(c) { '_' } else { c })
+            .collect();
+        let base = if base.is_empty() { "Layer".to_string() } else { base };
+        let mut name = base.clone();
+        let mut count = 1;
+        while !taken.insert(name.to_lowercase()) {
+            count += 1;
+            name = format!("{} ({})", base, count);
+        }
+        names.insert(layer.id, name);
+    }
+    names
+}
+
+// Reads LINE, LWPOLYLINE, CIRCLE, ELLIPSE and ARC entities into shapes in internal pixels. Closed chains of
+// LINEs become polygons and open ones polylines, or segments when alone; outlines on the holes
+// layer become holes, and polyline arc segments are flattened to within `tolerance` pixels.
+// Drawings without a supported `$INSUNITS` are taken to be in the current unit, except unitless
+// ones this app wrote from pixel drawings.
+pub(crate) fn import(text: &str, settings: &UnitSettings, tolerance: f64) -> Result<DxfImport, DocumentError> {
+    let pairs = parse_pairs(text)?;
+    let mut result = DxfImport {
+        shapes: Vec::new(),
+        layers: Vec::new(),
+        unit: settings.unit,
+        warnings: Vec::new(),
+        unsupported: Vec::new(),
+    };
+
+    let (unit, multiplier) = match header_value(&pairs, "$INSUNITS").map(|value| value.parse::<i64>()) {
+        Some(Ok(0)) if pairs.contains(&(999, PIXEL_COMMENT)) => (MeasurementUnit::Pixel, 1.0),
+        Some(Ok(code)) => match from_insunits(code) {
+            Some(unit) => unit,
+            None => {
+                if code != 0 {
+                    result.warnings.push(ImportIssue {
+                        element: "HEADER".to_string(),
+                        id: None,
+                        reason: format!("unsupported $INSUNITS {}, using {}", code, settings.unit),
+                    });
+                }
+                (settings.unit, 1.0)
+            }
+        },
+        _ => (settings.unit, 1.0),
+    };
+    result.unit = unit;
+    let scale = unit.pixels_per_unit(settings.dpi) * multiplier;
+
+    let mut lines = Vec::new();
+    for entity in entities(&pairs) {
+        let layer = entity.value(8).unwrap_or("0").to_string();
+        let converted = match entity.kind {
+            "LINE" => entity.point(10, 20).and_then(|from| {
+                let to = entity.point(11, 21)?;
+                lines.push(Line { from, to, layer: layer.clone() });
+                Ok(None)
+            }),
+            "CIRCLE" => circle(&entity, scale).map(Some),
+            "ELLIPSE" => ellipse(&entity, scale).map(Some),
+            "ARC" => arc(&entity, scale).map(Some),
+            "LWPOLYLINE" => lwpolyline(&entity, scale, tolerance).map(|(shape, approximate)| {
+                if approximate {
+                    result.warnings.push(entity.issue("arc segments were flattened into straight lines"));
+                }
+                Some(shape)
+            }),
+            // Parts of old-style POLYLINEs, which are reported once below
+            "VERTEX" | "SEQEND" => Ok(None),
+            kind => Err(format!("{} entities are not supported", kind)),
+        };
+        match converted {
+            Ok(Some(shape)) => {
+                result.shapes.push(shape);
+                result.layers.push(layer);
+            }
+            Ok(None) => {}
+            Err(reason) => result.unsupported.push(entity.issue(&reason)),
+        }
+    }
+
+    for chain in join_lines(lines) {
+        let first = &chain[0];
+        if chain.len() >= 3 && close_enough(&first.from, &chain[chain.len() - 1].to) {
+            result.shapes.push(Shape::Polygon(Polygon::new(
+                chain.iter().map(|line| line.from.scaled(scale)).collect(),
+            )));
+            result.layers.push(first.layer.clone());
+        } else if chain.len() == 1 {
+            result.shapes.push(Shape::Segment(Segment {
+                start: first.from.scaled(scale),
+                end: first.to.scaled(scale),
+            }));
+            result.layers.push(first.layer.clone());
+        } else {
+            let mut points: Vec<Point> = chain.iter().map(|line| line.from.scaled(scale)).collect();
+            points.push(chain[chain.len() - 1].to.scaled(scale));
+            result.shapes.push(Shape::Polyline(Polyline { points }));
+            result.layers.push(first.layer.clone());
+        }
+    }
+    attach_holes(&mut result, tolerance);
+    Ok(result)
+}
+
+// Moves outlines on the holes layer into the smallest polygon or rectangle around them.
+// Holes without a surrounding shape stay shapes of their own.
+fn attach_holes(result: &mut DxfImport, tolerance: f64) {
+    let is_hole = |layer: &String| layer.eq_ignore_ascii_case(HOLE_LAYER.0);
+    let mut holes = Vec::new();
+    let mut kept = Vec::new();
+    for (shape, layer) in result.shapes.drain(..).zip(result.layers.drain(..)) {
+        if is_hole(&layer) {
+            holes.push((shape, layer));
+        } else {
+            kept.push((shape, layer));
+        }
+    }
+
+    for (shape, layer) in holes {
+        let ring = match &shape {
+            Shape::Rectangle(rect) => rectangle_ring(rect),
+            Shape::OrientedRectangle(rect) => rect.corners().to_vec(),
+            Shape::Circle(circle) => flatten::ellipse(circle.center, circle.radius, circle.radius, tolerance),
+            Shape::Ellipse(ellipse) => ellipse.outline(tolerance),
+            Shape::Arc(arc) if arc.closure != ArcClosure::Open => arc.outline(tolerance),
+            Shape::Arc(_) | Shape::Segment(_) | Shape::Polyline(_) => {
+                kept.push((shape, layer));
+                continue;
+            }
+            Shape::Polygon(polygon) => polygon.points.clone(),
+            Shape::MultiPolygon(_) | Shape::Path(_) => unreachable!("DXF entities import as lines, arcs and single outlines"),
+        };
+        let container = kept
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (candidate, _))| {
+                let outline = match candidate {
+                    Shape::Rectangle(rect) => rectangle_ring(rect),
+                    Shape::OrientedRectangle(rect) => rect.corners().to_vec(),
+                    Shape::Polygon(polygon) => polygon.points.clone(),
+                    _ => return None,
+                };
+                ring_contains(&outline, &ring[0]).then(|| (i, ring_signed_area(&outline).abs()))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        match container {
+            Some(i) => {
+                let mut polygon = match &kept[i].0 {
+                    Shape::Rectangle(rect) => Polygon::new(rectangle_ring(rect)),
+                    Shape::OrientedRectangle(rect) => rect.outline(),
+                    Shape::Polygon(polygon) => polygon.clone(),
+                    _ => unreachable!("containers are rectangles or polygons"),
+                };
+                polygon.holes.push(ring);
+                kept[i].0 = Shape::Polygon(polygon);
+            }
+            None => {
+                result.warnings.push(ImportIssue {
+                    element: HOLE_LAYER.0.to_string(),
+                    id: None,
+                    reason: "an outline on the holes layer is not inside any shape and was kept as a shape".to_string(),
+                });
+                kept.push((shape, layer));
+            }
+        }
+    }
+    (result.shapes, result.layers) = kept.into_iter().unzip();
+}
+
+fn rectangle_ring(rect: &Rectangle) -> Vec<Point> {
+    let (a, b) = (rect.top_left, rect.bottom_right);
+    vec![a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }]
+}
+
+// Group code and value pairs, with values trimmed
+fn parse_pairs(text: &str) -> Result<Vec<(i32, &str)>, DocumentError> {
+    let lines: Vec<&str> = text.lines().collect();
+    if !lines.len().is_multiple_of(2) && lines.last().is_some_and(|line| !line.trim().is_empty()) {
+        return Err(malformed("the file ends in the middle of a group"));
+    }
+    let mut pairs = Vec::with_capacity(lines.len() / 2);
+    for (i, pair) in lines.chunks_exact(2).enumerate() {
+        let code = pair[0]
+            .trim()
+            .parse()
+            .map_err(|_| malformed(&format!("invalid group code '{}' on line {}", pair[0].trim(), 2 * i + 1)))?;
+        pairs.push((code, pair[1].trim()));
+    }
+    if !pairs.iter().any(|&(code, value)| code == 0 && value == "SECTION") {
+        return Err(malformed("the file has no sections"));
+    }
+    Ok(pairs)
+}
+
+fn malformed(reason: &str) -> DocumentError {
+    DocumentError::Malformed {
+        format: "DXF",
+        reason: reason.to_string(),
+    }
+}
+
+// Pairs of the section named `name`, without the section markers
+fn section<'a>(pairs: &'a [(i32, &'a str)], name: &str) -> &'a [(i32, &'a str)] {
+    let start = pairs
+        .windows(2)
+        .position(|w| w[0] == (0, "SECTION") && w[1] == (2, name))
+        .map(|i| i + 2);
+    match start {
+        Some(start) => {
+            let end = pairs[start..]
+                .iter()
+                .position(|&pair| pair == (0, "ENDSEC"))
+                .map_or(pairs.len(), |i| start + i);
+            &pairs[start..end]
+        }
+        None => &[],
+    }
+}
+
+fn header_value<'a>(pairs: &'a [(i32, &'a str)], variable: &str) -> Option<&'a str> {
+    let header = section(pairs, "HEADER");
+    let i = header.iter().position(|&pair| pair == (9, variable))?;
+    header.get(i + 1).map(|&(_, value)| value)
+}
+
+struct Entity<'a> {
+    kind: &'a str,
+    pairs: &'a [(i32, &'a str)],
+}
+
+fn entities<'a>(pairs: &'a [(i32, &'a str)]) -> Vec<Entity<'a>> {
+    let section = section(pairs, "ENTITIES");
+    let mut entities = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, &(code, _)) in section.iter().enumerate() {
+        if code == 0 {
+            if let Some(start) = start {
+                entities.push(Entity { kind: section[start].1, pairs: &section[start + 1..i] });
+            }
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        entities.push(Entity { kind: section[start].1, pairs: &section[start + 1..] });
+    }
+    entities
+}
+
+impl Entity<'_> {
+    fn value(&self, code: i32) -> Option<&str> {
+        self.pairs.iter().find(|&&(c, _)| c == code).map(|&(_, value)| value)
+    }
+
+    fn number(&self, code: i32) -> Result<f64, String> {
+        let text = self.value(code).ok_or_else(|| format!("missing group {}", code))?;
+        parse_number(text, code)
+    }
+
+    fn point(&self, x: i32, y: i32) -> Result<Point, String> {
+        Ok(Point { x: self.number(x)?, y: self.number(y)? })
+    }
+
+    fn issue(&self, reason: &str) -> ImportIssue {
+        ImportIssue {
+            element: self.kind.to_string(),
+            id: self.value(5).map(str::to_string),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+fn parse_number(text: &str, code: i32) -> Result<f64, String> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("invalid number '{}' in group {}", text, code))
+}
+
+fn circle(entity: &Entity, scale: f64) -> Result<Shape, String> {
+    let radius = entity.number(40)?;
+    if radius <= 0.0 {
+        return Err("radius must be positive".to_string());
+    }
+    Ok(Shape::Circle(Circle {
+        center: entity.point(10, 20)?.scaled(scale),
+        radius: radius * scale,
+    }))
+}
+
+// Full ellipses only; elliptical arcs have no shape to become
+fn ellipse(entity: &Entity, scale: f64) -> Result<Shape, String> {
+    let major = entity.point(11, 21)?;
+    let ratio = entity.number(40)?;
+    let start = entity.number(41).unwrap_or(0.0);
+    let end = entity.number(42).unwrap_or(2.0 * PI);
+    // Allow for the rounding of 2π to a few decimals
+    if ((end - start).abs() - 2.0 * PI).abs() > 1e-6 {
+        return Err("elliptical arcs are not supported".to_string());
+    }
+    let rx = major.x.hypot(major.y);
+    if rx <= 0.0 || ratio <= 0.0 {
+        return Err("axes must be positive".to_string());
+    }
+    Ok(Shape::Ellipse(Ellipse {
+        center: entity.point(10, 20)?.scaled(scale),
+        rx: rx * scale,
+        ry: rx * ratio * scale,
+        angle: major.y.atan2(major.x).to_degrees(),
+    }))
+}
+
+// ARCs run counter-clockwise from the start to the end angle, in degrees
+fn arc(entity: &Entity, scale: f64) -> Result<Shape, String> {
+    let radius = entity.number(40)?;
+    if radius <= 0.0 {
+        return Err("radius must be positive".to_string());
+    }
+    let start = entity.number(50)?;
+    let sweep = (entity.number(51)? - start).rem_euclid(360.0);
+    Ok(Shape::Arc(Arc {
+        center: entity.point(10, 20)?.scaled(scale),
+        radius: radius * scale,
+        start_angle: start,
+        sweep_angle: if sweep == 0.0 { 360.0 } else { sweep },
+        closure: ArcClosure::Open,
+    }))
+}
+
+// The shape and whether arc segments had to be flattened
+fn lwpolyline(entity: &Entity, scale: f64, tolerance: f64) -> Result<(Shape, bool), String> {
+    // Vertices are repeated 10/20 groups, each optionally followed by a 42 bulge
+    let mut vertices: Vec<(Point, f64)> = Vec::new();
+    let mut x = None;
+    for &(code, value) in entity.pairs {
+        match code {
+            10 => x = Some(parse_number(value, code)?),
+            20 => {
+                let x = x.take().ok_or("y coordinate without x")?;
+                vertices.push((Point { x, y: parse_number(value, code)? }.scaled(scale), 0.0));
+            }
+            42 => {
+                if let Some(last) = vertices.last_mut() {
+                    last.1 = parse_number(value, code)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    let flags: i64 = entity.value(70).and_then(|flags| flags.parse().ok()).unwrap_or(0);
+    let closed = flags & 1 == 1
+        || (vertices.len() > 2 && close_enough(&vertices[0].0, &vertices[vertices.len() - 1].0));
+    // A bulge on a zero-length segment traces no arc and has no center, so the segment is
+    // taken as straight
+    let n = vertices.len();
+    for i in 0..n {
+        let next = if closed { (i + 1) % n } else { i + 1 };
+        if next < n && vertices[i].0.distance(&vertices[next].0) == 0.0 {
+            vertices[i].1 = 0.0;
+        }
+    }
+    if !closed {
+        return open_polyline(&vertices, tolerance);
+    }
+
+    let approximate = vertices.iter().any(|&(_, bulge)| bulge != 0.0);
+    if !approximate && vertices.len() == 4 {
+        if let Some(rect) = axis_aligned_rectangle(&vertices) {
+            return Ok((Shape::Rectangle(rect), false));
+        }
+    }
+    if let Some(arc) = closed_arc(&vertices) {
+        return Ok((Shape::Arc(arc), false));
+    }
+    let mut points = Vec::new();
+    for (i, &(from, bulge)) in vertices.iter().enumerate() {
+        points.push(from);
+        if bulge != 0.0 {
+            let to = vertices[(i + 1) % vertices.len()].0;
+            let arc = bulge_arc(from, to, bulge).points(tolerance);
+            // Both ends are vertices of the polyline already
+            points.extend_from_slice(&arc[1..arc.len() - 1]);
+        }
+    }
+    dedup_closing_point(&mut points);
+    if points.len() < 3 {
+        return Err("fewer than 3 points".to_string());
+    }
+    let polygon = Polygon::new(points);
+    if !approximate {
+        if let Some(rect) = OrientedRectangle::from_polygon(&polygon, &RECTANGLE_PRECISION) {
+            return Ok((Shape::OrientedRectangle(rect), false));
+        }
+    }
+    Ok((Shape::Polygon(polygon), approximate))
+}
+
+// An open polyline, with arc segments flattened; a single arc segment reads back as an open arc
+fn open_polyline(vertices: &[(Point, f64)], tolerance: f64) -> Result<(Shape, bool), String> {
+    if vertices.len() < 2 {
+        return Err("fewer than 2 points".to_string());
+    }
+    // The last vertex starts no segment, so its bulge means nothing
+    let segments = &vertices[..vertices.len() - 1];
+    if let [(from, bulge)] = *segments {
+        if bulge != 0.0 {
+            return Ok((Shape::Arc(bulge_arc(from, vertices[1].0, bulge)), false));
+        }
+    }
+    let mut points = Vec::new();
+    for (i, &(from, bulge)) in segments.iter().enumerate() {
+        points.push(from);
+        if bulge != 0.0 {
+            let arc = bulge_arc(from, vertices[i + 1].0, bulge).points(tolerance);
+            points.extend_from_slice(&arc[1..arc.len() - 1]);
+        }
+    }
+    points.push(vertices[vertices.len() - 1].0);
+    let approximate = segments.iter().any(|&(_, bulge)| bulge != 0.0);
+    Ok((Shape::Polyline(Polyline { points }), approximate))
+}
+
+// A closed polyline of one arc segment is a chord arc; with a third vertex at the arc's center
+// it is a sector, and two halves of the same circle make a full-circle arc, as `export` writes them
+fn closed_arc(vertices: &[(Point, f64)]) -> Option<Arc> {
+    let bulged: Vec<usize> = (0..vertices.len()).filter(|&i| vertices[i].1 != 0.0).collect();
+    if let [(from, bulge), (to, other)] = *vertices {
+        if bulged.len() == 2 {
+            let mut arc = bulge_arc(from, to, bulge);
+            let second = bulge_arc(to, from, other);
+            if (bulge - other).abs() > 1e-9 * bulge.abs() || second.center.distance(&arc.center) > 1e-6 * arc.radius {
+                return None;
+            }
+            arc.sweep_angle *= 2.0;
+            arc.closure = ArcClosure::Chord;
+            return Some(arc);
+        }
+    }
+    let &[i] = bulged.as_slice() else {
+        return None;
+    };
+    let n = vertices.len();
+    let (from, bulge) = vertices[i];
+    let mut arc = bulge_arc(from, vertices[(i + 1) % n].0, bulge);
+    match n {
+        2 => arc.closure = ArcClosure::Chord,
+        3 if vertices[(i + 2) % n].0.distance(&arc.center) <= 1e-6 * arc.radius => arc.closure = ArcClosure::Sector,
+        _ => return None,
+    }
+    Some(arc)
+}
+
+fn axis_aligned_rectangle(vertices: &[(Point, f64)]) -> Option<Rectangle> {
+    let p: Vec<Point> = vertices.iter().map(|&(p, _)| p).collect();
+    let horizontal_first = p[0].y == p[1].y && p[1].x == p[2].x && p[2].y == p[3].y && p[3].x == p[0].x;
+    let vertical_first = p[0].x == p[1].x && p[1].y == p[2].y && p[2].x == p[3].x && p[3].y == p[0].y;
+    if !(horizontal_first || vertical_first) || p[0].x == p[2].x || p[0].y == p[2].y {
+        return None;
+    }
+    // Top-left is the visual top-left corner, with Y pointing up
+    Some(Rectangle {
+        top_left: Point { x: p[0].x.min(p[2].x), y: p[0].y.max(p[2].y) },
+        bottom_right: Point { x: p[0].x.max(p[2].x), y: p[0].y.min(p[2].y) },
+    })
+}
+
+// The open arc a polyline segment with `bulge` traces from `from` to `to`; the bulge is the
+// tangent of a quarter of the sweep, positive for counter-clockwise. `from` and `to` must differ.
+fn bulge_arc(from: Point, to: Point, bulge: f64) -> Arc {
+    let sweep = 4.0 * bulge.atan();
+    let chord = from.distance(&to);
+    let offset = chord / 2.0 / (sweep / 2.0).tan();
+    // Center lies on the chord's perpendicular bisector, left of the chord for positive offsets
+    let center = Point {
+        x: (from.x + to.x) / 2.0 - (to.y - from.y) / chord * offset,
+        y: (from.y + to.y) / 2.0 + (to.x - from.x) / chord * offset,
+    };
+    Arc {
+        center,
+        radius: center.distance(&from),
+        start_angle: (from.y - center.y).atan2(from.x - center.x).to_degrees(),
+        sweep_angle: sweep.to_degrees(),
+        closure: ArcClosure::Open,
+    }
+}
+
+struct Line {
+    from: Point,
+    to: Point,
+    layer: String,
+}
+
+fn close_enough(a: &Point, b: &Point) -> bool {
+    (a.x - b.x).abs() <= JOIN_TOLERANCE && (a.y - b.y).abs() <= JOIN_TOLERANCE
+}
+
+// Chains lines that share endpoints, reversing lines drawn in the opposite direction
+fn join_lines(mut lines: Vec<Line>) -> Vec<Vec<Line>> {
+    let mut chains = Vec::new();
+    while !lines.is_empty() {
+        let mut chain = vec![lines.remove(0)];
+        loop {
+            let end = chain[chain.len() - 1].to;
+            if close_enough(&end, &chain[0].from) && chain.len() > 1 {
+                break;
+            }
+            let next = lines
+                .iter()
+                .position(|line| close_enough(&line.from, &end) || close_enough(&line.to, &end));
+            match next {
+                Some(i) => {
+                    let mut line = lines.remove(i);
+                    if !close_enough(&line.from, &end) {
+                        std::mem::swap(&mut line.from, &mut line.to);
+                    }
+                    chain.push(line);
+                }
+                None => break,
+            }
+        }
+        // An open chain may also continue before the line it started from
+        if !close_enough(&chain[chain.len() - 1].to, &chain[0].from) {
+            while let Some(i) = lines
+                .iter()
+                .position(|line| close_enough(&line.from, &chain[0].from) || close_enough(&line.to, &chain[0].from))
+            {
+                let mut line = lines.remove(i);
+                if !close_enough(&line.to, &chain[0].from) {
+                    std::mem::swap(&mut line.from, &mut line.to);
+                }
+                chain.insert(0, line);
+            }
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{DocumentProperties, GridSettings, Metadata};
+    use crate::scene::{Scene, SceneShape};
+    use crate::precision::Precision;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    // Exporting rounds to `DECIMALS` places of the document's unit
+    fn assert_close(actual: Point, expected: Point, settings: &UnitSettings, unit: MeasurementUnit) {
+        let tolerance = 10f64.powi(-(DECIMALS as i32)) * unit.pixels_per_unit(settings.dpi);
+        assert!(
+            actual.distance(&expected) <= tolerance,
+            "{:?} is not within {} of {:?}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    // One shape of each type the first DXF export supported
+    fn shapes() -> Vec<Shape> {
+        vec![
+            Shape::Rectangle(Rectangle { top_left: point(0.0, 100.0), bottom_right: point(200.0, 0.0) }),
+            Shape::Polygon(Polygon::new(vec![point(0.0, 0.0), point(200.0, 0.0), point(100.0, 150.0)])),
+            Shape::Circle(Circle { center: point(50.0, 60.0), radius: 40.0 }),
+        ]
+    }
+
+    // A centimeter drawing of `shapes` on the default layer
+    fn document(shapes: Vec<Shape>) -> Document {
+        let mut scene = Scene::default();
+        let layer = scene.layers()[0].id;
+        for (index, shape) in shapes.into_iter().enumerate() {
+            scene.insert(index, SceneShape::new(shape, layer));
+        }
+        Document {
+            scene,
+            properties: DocumentProperties {
+                grid: GridSettings {
+                    grid_size: 20.0,
+                    unit: MeasurementUnit::Centimeter,
+                    show_grid: true,
+                    snap_to_grid: true,
+                    conversion_factor: 1.0,
+                },
+                unit: MeasurementUnit::Centimeter,
+                metadata: Metadata::default(),
+            },
+        }
+    }
+
+    fn dxf(pairs: &[(i32, &str)]) -> String {
+        pairs.iter().map(|(code, value)| format!("{:>3}\n{}\n", code, value)).collect()
+    }
+
+    #[test]
+    fn round_trip_keeps_shapes_and_unit() {
+        let settings = UnitSettings::default();
+        let unit = MeasurementUnit::Centimeter;
+        let text = export(&document(shapes()), &settings);
+        let imported = import(&text, &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert_eq!(imported.unit, unit);
+        assert!(imported.unsupported.is_empty(), "{:?}", imported.unsupported);
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.layers, ["Layer 1"; 3]);
+
+        match &imported.shapes[0] {
+            Shape::Rectangle(rect) => {
+                assert_close(rect.top_left, point(0.0, 100.0), &settings, unit);
+                assert_close(rect.bottom_right, point(200.0, 0.0), &settings, unit);
+            }
+            shape => panic!("expected a rectangle, got {:?}", shape),
+        }
+        match &imported.shapes[1] {
+            Shape::Polygon(polygon) => {
+                assert_eq!(polygon.points.len(), 3);
+                for (actual, expected) in polygon.points.iter().zip([point(0.0, 0.0), point(200.0, 0.0), point(100.0, 150.0)]) {
+                    assert_close(*actual, expected, &settings, unit);
+                }
+            }
+            shape => panic!("expected a polygon, got {:?}", shape),
+        }
+        match &imported.shapes[2] {
+            Shape::Circle(circle) => {
+                assert_close(circle.center, point(50.0, 60.0), &settings, unit);
+                assert_close(point(circle.radius, 0.0), point(40.0, 0.0), &settings, unit);
+            }
+            shape => panic!("expected a circle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn header_records_the_document_unit() {
+        let text = export(&document(shapes()), &UnitSettings::default());
+        let pairs = parse_pairs(&text).unwrap();
+        assert_eq!(header_value(&pairs, "$INSUNITS"), Some("5"));
+        assert_eq!(header_value(&pairs, "$MEASUREMENT"), Some("1"));
+    }
+
+    #[test]
+    fn line_chains_become_polygons_and_segments() {
+        // A millimeter triangle drawn as three LINEs, one of them backwards, and a stray LINE
+        let text = dxf(&[
+            (0, "SECTION"), (2, "HEADER"), (9, "$INSUNITS"), (70, "4"), (0, "ENDSEC"),
+            (0, "SECTION"), (2, "ENTITIES"),
+            (0, "LINE"), (8, "Walls"), (10, "0"), (20, "0"), (11, "10"), (21, "0"),
+            (0, "LINE"), (8, "Walls"), (10, "0"), (20, "10"), (11, "10"), (21, "0"),
+            (0, "LINE"), (8, "Walls"), (10, "0"), (20, "10"), (11, "0"), (21, "0"),
+            (0, "LINE"), (5, "1F"), (10, "50"), (20, "50"), (11, "60"), (21, "60"),
+            (0, "ENDSEC"), (0, "EOF"),
+        ]);
+        let settings = UnitSettings::default();
+        let imported = import(&text, &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert_eq!(imported.unit, MeasurementUnit::Millimeter);
+        assert!(imported.unsupported.is_empty(), "{:?}", imported.unsupported);
+        assert_eq!(imported.layers, ["Walls", "0"]);
+        let scale = MeasurementUnit::Millimeter.pixels_per_unit(settings.dpi);
+        let area = imported.shapes[0].area(&Precision::default()).unwrap();
+        assert!((area - 50.0 * scale * scale).abs() < 1e-6, "area {}", area);
+        match &imported.shapes[1] {
+            Shape::Segment(segment) => assert!((segment.end.x - 60.0 * scale).abs() < 1e-9),
+            shape => panic!("expected a segment, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn open_shapes_round_trip() {
+        let settings = UnitSettings::default();
+        let unit = MeasurementUnit::Centimeter;
+        let segment = Segment { start: point(0.0, 0.0), end: point(100.0, 50.0) };
+        let points = vec![point(0.0, 0.0), point(80.0, 0.0), point(80.0, 40.0)];
+        let document = document(vec![Shape::Segment(segment), Shape::Polyline(Polyline { points: points.clone() })]);
+        let imported = import(&export(&document, &settings), &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        // LINEs are chained after the other entities are read
+        assert_eq!(imported.layers, ["Layer 1"; 2]);
+        match imported.shapes.as_slice() {
+            [Shape::Polyline(polyline), Shape::Segment(read)] => {
+                assert_close(read.start, segment.start, &settings, unit);
+                assert_close(read.end, segment.end, &settings, unit);
+                assert_eq!(polyline.points.len(), points.len());
+                for (actual, expected) in polyline.points.iter().zip(points) {
+                    assert_close(*actual, expected, &settings, unit);
+                }
+            }
+            shapes => panic!("expected a polyline and a segment, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn pixel_drawings_read_back_in_pixels() {
+        let settings = UnitSettings { unit: MeasurementUnit::Centimeter, ..UnitSettings::default() };
+        let mut document = document(vec![Shape::Circle(Circle { center: point(50.0, 60.0), radius: 40.0 })]);
+        document.properties.unit = MeasurementUnit::Pixel;
+        let text = export(&document, &settings);
+        let imported = import(&text, &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert_eq!(header_value(&parse_pairs(&text).unwrap(), "$INSUNITS"), Some("0"));
+        assert_eq!(imported.unit, MeasurementUnit::Pixel);
+        match imported.shapes.as_slice() {
+            [Shape::Circle(circle)] => {
+                assert_close(circle.center, point(50.0, 60.0), &settings, MeasurementUnit::Pixel);
+                assert!((circle.radius - 40.0).abs() < 1e-6, "radius {}", circle.radius);
+            }
+            shapes => panic!("expected one circle, got {:?}", shapes),
+        }
+
+        // Unitless drawings from elsewhere are still taken to be in the current unit
+        let text = text.replacen(&format!("999\n{}\n", PIXEL_COMMENT), "", 1);
+        let imported = import(&text, &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+        assert_eq!(imported.unit, MeasurementUnit::Centimeter);
+    }
+
+    #[test]
+    fn bulges_on_zero_length_segments_are_ignored() {
+        let polyline = |closed: &'static str| {
+            dxf(&[
+                (0, "SECTION"), (2, "ENTITIES"),
+                (0, "LWPOLYLINE"), (70, closed),
+                (10, "0"), (20, "0"), (42, "0.5"),
+                (10, "0"), (20, "0"), (42, "1"),
+                (10, "10"), (20, "0"),
+                (10, "10"), (20, "10"),
+                (0, "ENDSEC"), (0, "EOF"),
+            ])
+        };
+        let settings = UnitSettings::default();
+        for closed in ["0", "1"] {
+            let imported = import(&polyline(closed), &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+            assert!(imported.unsupported.is_empty(), "{:?}", imported.unsupported);
+            let points = match imported.shapes.as_slice() {
+                [Shape::Polyline(Polyline { points })] | [Shape::Polygon(Polygon { points, .. })] => points,
+                shapes => panic!("expected one polyline or polygon, got {:?}", shapes),
+            };
+            assert!(points.len() > 4, "the bulged segment is flattened");
+            assert!(points.iter().all(|p| p.x.is_finite() && p.y.is_finite()), "{:?}", points);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let settings = UnitSettings::default();
+        assert!(import("  0\nSECTION\n  2\n", &settings, 0.25).is_err());
+        assert!(import("abc\nLINE\n", &settings, 0.25).is_err());
+        assert!(import("  0\nEOF\n", &settings, 0.25).is_err());
+    }
+
+    #[test]
+    fn holes_round_trip_into_their_polygon() {
+        let settings = UnitSettings::default();
+        let outer = vec![point(0.0, 0.0), point(400.0, 0.0), point(400.0, 400.0), point(0.0, 400.0)];
+        let hole = vec![point(100.0, 100.0), point(200.0, 100.0), point(200.0, 200.0), point(100.0, 200.0)];
+        let document = document(vec![Shape::Polygon(Polygon { points: outer, holes: vec![hole] })]);
+        let imported = import(&export(&document, &settings), &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert_eq!(imported.layers, ["Layer 1"]);
+        match imported.shapes.as_slice() {
+            [shape @ Shape::Polygon(polygon)] => {
+                assert_eq!(polygon.holes.len(), 1);
+                let area = shape.area(&Precision::default()).unwrap();
+                assert!((area - 150_000.0).abs() < 1.0, "area {}", area);
+            }
+            shapes => panic!("expected one polygon, got {:?}", shapes),
+        }
+    }
+
+    // The default layer with a circle, a hidden layer with a segment and a locked layer with
+    // a triangle
+    fn layered_document() -> Document {
+        let mut document = document(vec![Shape::Circle(Circle { center: point(50.0, 60.0), radius: 40.0 })]);
+        let mut walls = Layer::new("Walls".to_string(), "#ff0000".to_string());
+        walls.visible = false;
+        let mut notes = Layer::new("Notes".to_string(), "#0000ff".to_string());
+        notes.locked = true;
+        let shapes = [
+            (Shape::Segment(Segment { start: point(0.0, 0.0), end: point(100.0, 50.0) }), walls.id),
+            (
+                Shape::Polygon(Polygon::new(vec![point(0.0, 0.0), point(200.0, 0.0), point(100.0, 150.0)])),
+                notes.id,
+            ),
+        ];
+        document.scene.insert_layer(1, walls);
+        document.scene.insert_layer(2, notes);
+        for (index, (shape, layer)) in shapes.into_iter().enumerate() {
+            document.scene.insert(index + 1, SceneShape::new(shape, layer));
+        }
+        document
+    }
+
+    #[test]
+    fn round_trip_keeps_layers() {
+        let settings = UnitSettings::default();
+        let unit = MeasurementUnit::Centimeter;
+        let text = export(&layered_document(), &settings);
+        let imported = import(&text, &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert!(imported.unsupported.is_empty(), "{:?}", imported.unsupported);
+        assert_eq!(imported.shapes.len(), 3);
+        let on_layer = |name: &str| {
+            let index = imported.layers.iter().position(|layer| layer == name).unwrap();
+            &imported.shapes[index]
+        };
+        match on_layer("Walls") {
+            Shape::Segment(segment) => assert_close(segment.end, point(100.0, 50.0), &settings, unit),
+            shape => panic!("expected a segment, got {:?}", shape),
+        }
+        assert!(matches!(on_layer("Notes"), Shape::Polygon(_)));
+        assert!(matches!(on_layer("Layer 1"), Shape::Circle(_)));
+    }
+
+    #[test]
+    fn full_circle_arc_reads_back_as_one_arc() {
+        let settings = UnitSettings::default();
+        let unit = MeasurementUnit::Centimeter;
+        let arc = Arc {
+            center: point(10.0, -20.0),
+            radius: 30.0,
+            start_angle: 45.0,
+            sweep_angle: -360.0,
+            closure: ArcClosure::Chord,
+        };
+        let document = document(vec![Shape::Arc(arc)]);
+        let imported = import(&export(&document, &settings), &settings, flatten::DEFAULT_TOLERANCE).unwrap();
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        match imported.shapes.as_slice() {
+            [Shape::Arc(read)] => {
+                assert_close(read.center, arc.center, &settings, unit);
+                assert_close(read.start(), arc.start(), &settings, unit);
+                assert!((read.radius - arc.radius).abs() < 1e-4);
+                assert!((read.sweep_angle - arc.sweep_angle).abs() < 1e-6);
+                assert_eq!(read.closure, ArcClosure::Chord);
+            }
+            shapes => panic!("expected one arc, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn layer_table_marks_hidden_and_locked_layers() {
+        let text = export(&layered_document(), &UnitSettings::default());
+        let pairs = parse_pairs(&text).unwrap();
+        let table: Vec<(&str, i64, i64)> = pairs
+            .split(|&pair| pair == (0, "LAYER"))
+            .skip(1)
+            .map(|entry| {
+                let value = |wanted: i32| entry.iter().find(|(code, _)| *code == wanted).map(|&(_, value)| value);
+                (
+                    value(2).unwrap(),
+                    value(70).unwrap().trim().parse().unwrap(),
+                    value(62).unwrap().trim().parse().unwrap(),
+                )
+            })
+            .collect();
+        let layer = |name: &str| *table.iter().find(|(layer, _, _)| *layer == name).unwrap();
+        assert!(layer("Walls").2 < 0, "hidden layers have a negative color");
+        assert_eq!(layer("Walls").1 & 4, 0);
+        assert_eq!(layer("Walls").2, -1, "red is standard color 1");
+        assert!(layer("Notes").2 > 0);
+        assert_eq!(layer("Notes").1 & 4, 4, "locked layers set flag 4");
+    }
+}
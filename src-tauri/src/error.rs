@@ -0,0 +1,271 @@
+use std::fmt;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use uuid::Uuid;
+
+// Errors returned by the geometry commands. Each one serializes as
+// `{ code, field, message }` so the frontend can highlight the offending input
+// by its field path (e.g. `points[2].x`) and localize the message by code.
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) enum GeometryError {
+    NonFinite { field: String },
+    NegativeExtent { field: String },
+    TooFewPoints { field: String, required: usize, actual: usize },
+    DegeneratePolygon { field: String },
+    SelfIntersecting { field: String },
+    HoleOutside { field: String },
+    DegenerateTransform { field: String },
+    NotStraight { field: String },
+    OpenShape { field: String },
+    OutOfRange { field: String, min: f64, max: f64 },
+    Overflow { field: String },
+    InvalidUnit { field: String, value: String },
+    InvalidDpi { field: String },
+}
+
+impl GeometryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GeometryError::NonFinite { .. } => "non_finite",
+            GeometryError::NegativeExtent { .. } => "negative_extent",
+            GeometryError::TooFewPoints { .. } => "too_few_points",
+            GeometryError::DegeneratePolygon { .. } => "degenerate_polygon",
+            GeometryError::SelfIntersecting { .. } => "self_intersecting",
+            GeometryError::HoleOutside { .. } => "hole_outside",
+            GeometryError::DegenerateTransform { .. } => "degenerate_transform",
+            GeometryError::NotStraight { .. } => "not_straight",
+            GeometryError::OpenShape { .. } => "open_shape",
+            GeometryError::OutOfRange { .. } => "out_of_range",
+            GeometryError::Overflow { .. } => "overflow",
+            GeometryError::InvalidUnit { .. } => "invalid_unit",
+            GeometryError::InvalidDpi { .. } => "invalid_dpi",
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        match self {
+            GeometryError::NonFinite { field }
+            | GeometryError::NegativeExtent { field }
+            | GeometryError::TooFewPoints { field, .. }
+            | GeometryError::DegeneratePolygon { field }
+            | GeometryError::SelfIntersecting { field }
+            | GeometryError::HoleOutside { field }
+            | GeometryError::DegenerateTransform { field }
+            | GeometryError::NotStraight { field }
+            | GeometryError::OpenShape { field }
+            | GeometryError::OutOfRange { field, .. }
+            | GeometryError::Overflow { field }
+            | GeometryError::InvalidUnit { field, .. }
+            | GeometryError::InvalidDpi { field } => field,
+        }
+    }
+
+    // Nests the field path under `parent`, e.g. `points[2].x` under `clip[1]`
+    pub fn within(mut self, parent: &str) -> GeometryError {
+        match &mut self {
+            GeometryError::NonFinite { field }
+            | GeometryError::NegativeExtent { field }
+            | GeometryError::TooFewPoints { field, .. }
+            | GeometryError::DegeneratePolygon { field }
+            | GeometryError::SelfIntersecting { field }
+            | GeometryError::HoleOutside { field }
+            | GeometryError::DegenerateTransform { field }
+            | GeometryError::NotStraight { field }
+            | GeometryError::OpenShape { field }
+            | GeometryError::OutOfRange { field, .. }
+            | GeometryError::Overflow { field }
+            | GeometryError::InvalidUnit { field, .. }
+            | GeometryError::InvalidDpi { field } => *field = format!("{}.{}", parent, field),
+        }
+        self
+    }
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::NonFinite { field } => write!(f, "{} must be a finite number", field),
+            GeometryError::NegativeExtent { field } => write!(f, "{} cannot be negative", field),
+            GeometryError::TooFewPoints { field, required, actual } => write!(
+                f,
+                "{} needs at least {} points, got {}",
+                field, required, actual
+            ),
+            GeometryError::DegeneratePolygon { .. } => write!(f, "Polygon has zero area"),
+            GeometryError::SelfIntersecting { .. } => write!(f, "Polygon edges cross each other"),
+            GeometryError::HoleOutside { .. } => write!(f, "A hole must lie inside its polygon and apart from other holes"),
+            GeometryError::DegenerateTransform { .. } => write!(f, "The transform flattens shapes to a line or point"),
+            GeometryError::NotStraight { .. } => write!(f, "Only straight-edged shapes can be measured segment by segment"),
+            GeometryError::OpenShape { .. } => write!(f, "An open shape has no area"),
+            GeometryError::OutOfRange { field, min, max } if max.is_infinite() => {
+                write!(f, "{} must be greater than {}", field, min)
+            }
+            GeometryError::OutOfRange { field, min, max } => {
+                write!(f, "{} must be between {} and {}", field, min, max)
+            }
+            GeometryError::Overflow { field } => write!(f, "{} is too large to represent", field),
+            GeometryError::InvalidUnit { value, .. } => write!(
+                f,
+                "'{}' is not a supported measurement unit (px, cm, mm, in)",
+                value
+            ),
+            GeometryError::InvalidDpi { .. } => write!(f, "DPI must be a positive number"),
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+impl Serialize for GeometryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("GeometryError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("field", self.field())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+// Errors returned by the document commands. Invalid numeric options keep the geometry
+// `{ code, field, message }` shape; the rest serialize as `{ code, message }`.
+#[derive(Debug)]
+pub(crate) enum DocumentError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    NotADocument,
+    UnsupportedVersion { found: u32, supported: u32 },
+    // An imported file in another format could not be read
+    Malformed { format: &'static str, reason: String },
+    // An export could not be produced with the requested options
+    Render { reason: String },
+    // An option such as the flattening tolerance is out of range
+    Geometry(GeometryError),
+}
+
+impl DocumentError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DocumentError::Io(_) => "io",
+            DocumentError::Parse(_) => "parse",
+            DocumentError::NotADocument => "not_a_document",
+            DocumentError::UnsupportedVersion { .. } => "unsupported_version",
+            DocumentError::Malformed { .. } => "malformed",
+            DocumentError::Render { .. } => "render",
+            DocumentError::Geometry(err) => err.code(),
+        }
+    }
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Io(err) => write!(f, "Could not access the file: {}", err),
+            DocumentError::Parse(err) => write!(f, "The file is not valid drawing JSON: {}", err),
+            DocumentError::NotADocument => write!(f, "The file is not a drawing document"),
+            DocumentError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Document version {} is newer than the supported version {}",
+                found, supported
+            ),
+            DocumentError::Malformed { format, reason } => write!(f, "Invalid {} file: {}", format, reason),
+            DocumentError::Render { reason } => write!(f, "Could not render the drawing: {}", reason),
+            DocumentError::Geometry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl From<std::io::Error> for DocumentError {
+    fn from(err: std::io::Error) -> Self {
+        DocumentError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(err: serde_json::Error) -> Self {
+        DocumentError::Parse(err)
+    }
+}
+
+impl From<GeometryError> for DocumentError {
+    fn from(err: GeometryError) -> Self {
+        DocumentError::Geometry(err)
+    }
+}
+
+impl Serialize for DocumentError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let DocumentError::Geometry(err) = self {
+            return err.serialize(serializer);
+        }
+        let mut state = serializer.serialize_struct("DocumentError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+// Errors returned by the scene commands. Geometry errors keep their own `{ code, field, message }`
+// shape; the rest serialize as `{ code, message }`.
+#[derive(Debug)]
+pub(crate) enum SceneError {
+    NotFound { id: Uuid },
+    LayerNotFound { id: Uuid },
+    LayerLocked { name: String },
+    InvalidColor { value: String },
+    MergeIntoSelf,
+    GroupNotFound { id: Uuid },
+    EmptyGroup,
+    Geometry(GeometryError),
+}
+
+impl SceneError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SceneError::NotFound { .. } => "not_found",
+            SceneError::LayerNotFound { .. } => "layer_not_found",
+            SceneError::LayerLocked { .. } => "layer_locked",
+            SceneError::InvalidColor { .. } => "invalid_color",
+            SceneError::MergeIntoSelf => "merge_into_self",
+            SceneError::GroupNotFound { .. } => "group_not_found",
+            SceneError::EmptyGroup => "empty_group",
+            SceneError::Geometry(err) => err.code(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::NotFound { id } => write!(f, "No shape with id {} in the drawing", id),
+            SceneError::LayerNotFound { id } => write!(f, "No layer with id {} in the drawing", id),
+            SceneError::LayerLocked { name } => write!(f, "Layer '{}' is locked", name),
+            SceneError::InvalidColor { value } => write!(f, "'{}' is not a #rrggbb color", value),
+            SceneError::MergeIntoSelf => write!(f, "A layer cannot be merged into itself"),
+            SceneError::GroupNotFound { id } => write!(f, "No group with id {} in the drawing", id),
+            SceneError::EmptyGroup => write!(f, "A group needs at least one shape"),
+            SceneError::Geometry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<GeometryError> for SceneError {
+    fn from(err: GeometryError) -> Self {
+        SceneError::Geometry(err)
+    }
+}
+
+impl Serialize for SceneError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let SceneError::Geometry(err) = self {
+            return err.serialize(serializer);
+        }
+        let mut state = serializer.serialize_struct("SceneError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
@@ -0,0 +1,224 @@
+// Approximation of curves by straight segments. `tolerance` is the largest allowed distance
+// between the curve and its approximation, in the same units as the points.
+use std::f64::consts::PI;
+
+use crate::Point;
+
+// Subdivision depth limit; 2^16 segments is far beyond any useful tolerance
+const MAX_DEPTH: u32 = 16;
+
+// Default tolerance in internal pixels, a quarter pixel is invisible on screen
+pub(crate) const DEFAULT_TOLERANCE: f64 = 0.25;
+
+// Number of segments needed so a chord of a circle of `radius` stays within `tolerance`
+pub(crate) fn arc_segments(radius: f64, sweep: f64, tolerance: f64) -> usize {
+    let radius = radius.abs();
+    if radius <= tolerance || tolerance <= 0.0 {
+        return 8.max((sweep.abs() / (PI / 4.0)).ceil() as usize);
+    }
+    let max_step = 2.0 * (1.0 - tolerance / radius).acos();
+    ((sweep.abs() / max_step).ceil() as usize).clamp(1, 1 << MAX_DEPTH)
+}
+
+// Points along an axis-aligned ellipse from `start` to `start + sweep` radians, both ends included
+pub(crate) fn ellipse_arc(
+    center: Point,
+    rx: f64,
+    ry: f64,
+    start: f64,
+    sweep: f64,
+    tolerance: f64,
+) -> Vec<Point> {
+    let segments = arc_segments(rx.abs().max(ry.abs()), sweep, tolerance);
+    (0..=segments)
+        .map(|i| {
+            let angle = start + sweep * i as f64 / segments as f64;
+            Point {
+                x: center.x + rx * angle.cos(),
+                y: center.y + ry * angle.sin(),
+            }
+        })
+        .collect()
+}
+
+// Closed ellipse outline, without repeating the first point at the end
+pub(crate) fn ellipse(center: Point, rx: f64, ry: f64, tolerance: f64) -> Vec<Point> {
+    let mut points = ellipse_arc(center, rx, ry, 0.0, 2.0 * PI, tolerance);
+    points.pop();
+    points
+}
+
+// Points after `p0` along the quadratic Bezier p0-p1-p2, ending with `p2`
+pub(crate) fn quadratic(p0: Point, p1: Point, p2: Point, tolerance: f64) -> Vec<Point> {
+    // Degree elevation gives the identical curve as a cubic
+    let c1 = Point {
+        x: p0.x + 2.0 / 3.0 * (p1.x - p0.x),
+        y: p0.y + 2.0 / 3.0 * (p1.y - p0.y),
+    };
+    let c2 = Point {
+        x: p2.x + 2.0 / 3.0 * (p1.x - p2.x),
+        y: p2.y + 2.0 / 3.0 * (p1.y - p2.y),
+    };
+    cubic(p0, c1, c2, p2, tolerance)
+}
+
+// Points after `p0` along the cubic Bezier p0-p1-p2-p3, ending with `p3`
+pub(crate) fn cubic(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f64) -> Vec<Point> {
+    let mut points = Vec::new();
+    subdivide_cubic(p0, p1, p2, p3, tolerance, 0, &mut points);
+    points
+}
+
+fn subdivide_cubic(
+    p0: Point,
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    tolerance: f64,
+    depth: u32,
+    out: &mut Vec<Point>,
+) {
+    // The curve stays within the hull of its control points, so it is flat enough
+    // once both inner control points are within tolerance of the chord
+    let flat = distance_to_segment(&p1, &p0, &p3) <= tolerance
+        && distance_to_segment(&p2, &p0, &p3) <= tolerance;
+    if flat || depth >= MAX_DEPTH {
+        out.push(p3);
+        return;
+    }
+    // de Casteljau split at t = 0.5
+    let mid = |a: Point, b: Point| Point {
+        x: (a.x + b.x) / 2.0,
+        y: (a.y + b.y) / 2.0,
+    };
+    let p01 = mid(p0, p1);
+    let p12 = mid(p1, p2);
+    let p23 = mid(p2, p3);
+    let p012 = mid(p01, p12);
+    let p123 = mid(p12, p23);
+    let split = mid(p012, p123);
+    subdivide_cubic(p0, p01, p012, split, tolerance, depth + 1, out);
+    subdivide_cubic(split, p123, p23, p3, tolerance, depth + 1, out);
+}
+
+pub(crate) fn distance_to_segment(p: &Point, a: &Point, b: &Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return p.distance(a);
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_squared).clamp(0.0, 1.0);
+    p.distance(&Point {
+        x: a.x + t * dx,
+        y: a.y + t * dy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    // Largest distance from points along `curve` to the polyline through `points`
+    fn deviation(points: &[Point], curve: impl Fn(f64) -> Point) -> f64 {
+        (0..=1000)
+            .map(|i| {
+                let p = curve(i as f64 / 1000.0);
+                points
+                    .windows(2)
+                    .map(|pair| distance_to_segment(&p, &pair[0], &pair[1]))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn cubic_at(p: [Point; 4], t: f64) -> Point {
+        let s = 1.0 - t;
+        let weights = [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t];
+        Point {
+            x: p.iter().zip(weights).map(|(p, w)| p.x * w).sum(),
+            y: p.iter().zip(weights).map(|(p, w)| p.y * w).sum(),
+        }
+    }
+
+    #[test]
+    fn arcs_get_enough_segments_for_the_tolerance() {
+        for (radius, tolerance) in [(100.0, 0.25), (1e4, 0.01), (3.0, 1.0)] {
+            let segments = arc_segments(radius, 2.0 * PI, tolerance);
+            // The sagitta of each chord is the farthest it strays from the circle
+            let step = 2.0 * PI / segments as f64;
+            assert!(radius * (1.0 - (step / 2.0).cos()) <= tolerance + 1e-12, "{} {}", radius, tolerance);
+            assert!(arc_segments(radius, PI, tolerance) <= segments.div_ceil(2) + 1);
+        }
+        // Tiny circles and unusable tolerances still get a recognizable outline
+        assert_eq!(arc_segments(0.1, 2.0 * PI, 0.25), 8);
+        assert_eq!(arc_segments(100.0, 2.0 * PI, 0.0), 8);
+        assert_eq!(arc_segments(1e30, 2.0 * PI, 1e-9), 1 << MAX_DEPTH);
+    }
+
+    #[test]
+    fn ellipse_outlines_stay_within_tolerance() {
+        let center = point(5.0, -3.0);
+        let points = ellipse(center, 40.0, 20.0, 0.25);
+        assert_ne!(points.first().unwrap().x, points.last().unwrap().x);
+        for p in &points {
+            let (x, y) = ((p.x - center.x) / 40.0, (p.y - center.y) / 20.0);
+            assert!((x * x + y * y - 1.0).abs() < 1e-9);
+        }
+        let mut closed = points.clone();
+        closed.push(points[0]);
+        let curve = |t: f64| point(center.x + 40.0 * (2.0 * PI * t).cos(), center.y + 20.0 * (2.0 * PI * t).sin());
+        assert!(deviation(&closed, curve) <= 0.25);
+    }
+
+    #[test]
+    fn elliptical_arcs_include_both_ends() {
+        let points = ellipse_arc(point(0.0, 0.0), 2.0, 1.0, PI / 2.0, -PI, 0.01);
+        let (first, last) = (points[0], *points.last().unwrap());
+        assert!(first.distance(&point(0.0, 1.0)) < 1e-12);
+        assert!(last.distance(&point(0.0, -1.0)) < 1e-12);
+        assert!(points.iter().all(|p| p.x >= -1e-12));
+    }
+
+    #[test]
+    fn curves_stay_within_tolerance() {
+        let control = [point(0.0, 0.0), point(30.0, 80.0), point(70.0, -40.0), point(100.0, 0.0)];
+        for tolerance in [1.0, 0.25, 0.01] {
+            let mut points = vec![control[0]];
+            points.extend(cubic(control[0], control[1], control[2], control[3], tolerance));
+            assert_eq!(points.last().unwrap().distance(&control[3]), 0.0);
+            assert!(deviation(&points, |t| cubic_at(control, t)) <= tolerance);
+        }
+
+        let (p0, p1, p2) = (point(0.0, 0.0), point(50.0, 100.0), point(100.0, 0.0));
+        let mut points = vec![p0];
+        points.extend(quadratic(p0, p1, p2, 0.25));
+        let curve = |t: f64| {
+            let s = 1.0 - t;
+            point(s * s * p0.x + 2.0 * s * t * p1.x + t * t * p2.x, s * s * p0.y + 2.0 * s * t * p1.y + t * t * p2.y)
+        };
+        assert!(deviation(&points, curve) <= 0.25);
+    }
+
+    #[test]
+    fn straight_curves_need_one_segment() {
+        let (p0, p3) = (point(0.0, 0.0), point(9.0, 0.0));
+        let cubic = cubic(p0, point(3.0, 0.0), point(6.0, 0.0), p3, 0.25);
+        for points in [cubic, quadratic(p0, point(4.5, 0.0), p3, 0.25)] {
+            assert_eq!(points.len(), 1);
+            assert_eq!(points[0].distance(&p3), 0.0);
+        }
+    }
+
+    #[test]
+    fn measures_distances_to_segments() {
+        let (a, b) = (point(0.0, 0.0), point(10.0, 0.0));
+        assert_eq!(distance_to_segment(&point(5.0, 3.0), &a, &b), 3.0);
+        assert_eq!(distance_to_segment(&point(13.0, 4.0), &a, &b), 5.0);
+        assert_eq!(distance_to_segment(&point(-3.0, -4.0), &a, &b), 5.0);
+        assert_eq!(distance_to_segment(&point(3.0, 4.0), &a, &a), 5.0);
+    }
+}
@@ -0,0 +1,928 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::bezier::Path;
+use crate::drawing::format_number;
+use crate::error::DocumentError;
+use crate::flatten;
+use crate::svg_import::{dedup_closing_point, ImportIssue};
+use crate::units::{MeasurementUnit, UnitSettings};
+use crate::{
+    ring_bounding_box, ring_signed_area, Arc, ArcClosure, Circle, Ellipse, MultiPolygon, OrientedRectangle, Point, Polygon,
+    Polyline, Rectangle, Segment, Shape,
+};
+
+// Decimal places kept for coordinates, matching the SVG and DXF exports
+const DECIMALS: usize = 6;
+
+// A shape recorded in the feature properties is used only when its outline, flattened as `format`
+// flattens it, lies this close to the geometry, in internal pixels. Both outlines are within the
+// flattening tolerance of the curve, so the app's own files always agree.
+const HINT_TOLERANCE: f64 = 2.0 * flatten::DEFAULT_TOLERANCE;
+
+#[derive(Deserialize,Serialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GeometryFormat {
+    GeoJson,
+    Wkt,
+}
+
+#[derive(Serialize,Debug)]
+pub(crate) struct GeometryImport {
+    pub shapes: Vec<Shape>,
+    pub format: GeometryFormat,
+    // Geometries that were imported only partly, e.g. polygons with degenerate holes
+    pub warnings: Vec<ImportIssue>,
+    // Geometries that could not be converted and were left out
+    pub unsupported: Vec<ImportIssue>,
+}
+
+// Reads polygons from GeoJSON or WKT text, telling them apart by the leading `{` of JSON.
+// Coordinates are taken to be in `unit`; shapes are returned in internal pixels.
+pub(crate) fn parse(text: &str, unit: MeasurementUnit, settings: &UnitSettings) -> Result<GeometryImport, DocumentError> {
+    let scale = unit.pixels_per_unit(settings.dpi);
+    let text = text.trim_start_matches('\u{feff}').trim();
+    let mut importer = Importer {
+        scale,
+        result: GeometryImport {
+            shapes: Vec::new(),
+            format: GeometryFormat::GeoJson,
+            warnings: Vec::new(),
+            unsupported: Vec::new(),
+        },
+    };
+    if text.starts_with('{') {
+        let value: Value = serde_json::from_str(text).map_err(|err| malformed("GeoJSON", err.to_string()))?;
+        importer.geojson(&value, None, None).map_err(|reason| malformed("GeoJSON", reason))?;
+    } else {
+        importer.result.format = GeometryFormat::Wkt;
+        let mut parser = WktParser { text: text.as_bytes(), pos: 0 };
+        let geometries = parser.geometries().map_err(|reason| malformed("WKT", reason))?;
+        for geometry in geometries {
+            importer.wkt(geometry);
+        }
+    }
+    Ok(importer.result)
+}
+
+fn malformed(format: &'static str, reason: String) -> DocumentError {
+    DocumentError::Malformed { format, reason }
+}
+
+// Polygon rings in file coordinates, exterior first
+type Rings = Vec<Vec<Point>>;
+
+struct Importer {
+    scale: f64,
+    result: GeometryImport,
+}
+
+impl Importer {
+    fn issue(kind: &str, id: Option<String>, reason: &str) -> ImportIssue {
+        ImportIssue {
+            element: kind.to_string(),
+            id,
+            reason: reason.to_string(),
+        }
+    }
+
+    // `properties` and `id` come from the enclosing feature, if any
+    fn geojson(&mut self, value: &Value, properties: Option<&Map<String, Value>>, id: Option<String>) -> Result<(), String> {
+        let kind = value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or("object without a \"type\" member")?;
+        match kind {
+            "FeatureCollection" => {
+                let features = value
+                    .get("features")
+                    .and_then(Value::as_array)
+                    .ok_or("FeatureCollection without a \"features\" array")?;
+                for feature in features {
+                    self.geojson(feature, None, None)?;
+                }
+            }
+            "Feature" => {
+                let id = value.get("id").map(|id| match id {
+                    Value::String(id) => id.clone(),
+                    other => other.to_string(),
+                });
+                match value.get("geometry") {
+                    Some(Value::Null) | None => {}
+                    Some(geometry) => {
+                        self.geojson(geometry, value.get("properties").and_then(Value::as_object), id)?;
+                    }
+                }
+            }
+            "GeometryCollection" => {
+                let geometries = value
+                    .get("geometries")
+                    .and_then(Value::as_array)
+                    .ok_or("GeometryCollection without a \"geometries\" array")?;
+                for geometry in geometries {
+                    self.geojson(geometry, None, id.clone())?;
+                }
+            }
+            "Polygon" | "MultiPolygon" => {
+                let coordinates = value.get("coordinates").ok_or("geometry without coordinates")?;
+                let polygons = if kind == "Polygon" {
+                    vec![geojson_rings(coordinates)?]
+                } else {
+                    coordinates
+                        .as_array()
+                        .ok_or("MultiPolygon coordinates are not an array")?
+                        .iter()
+                        .map(geojson_rings)
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                // The app's own export records the exact shape in the feature properties
+                let hint = properties.and_then(|p| p.get("shape")).and_then(Value::as_str);
+                if polygons.len() == 1 {
+                    let exact = hint.zip(properties).and_then(|(hint, p)| self.shape_from_properties(hint, p));
+                    if let Some(shape) = exact.filter(|shape| self.agrees(shape, &polygons[0])) {
+                        self.result.shapes.push(shape);
+                        return Ok(());
+                    }
+                }
+                self.polygons(kind, id, polygons, hint == Some("rectangle"));
+            }
+            "LineString" | "MultiLineString" => {
+                let coordinates = value.get("coordinates").ok_or("geometry without coordinates")?;
+                let lines = if kind == "LineString" {
+                    vec![geojson_positions(coordinates)?]
+                } else {
+                    coordinates
+                        .as_array()
+                        .ok_or("MultiLineString coordinates are not an array")?
+                        .iter()
+                        .map(geojson_positions)
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                let hint = properties.and_then(|p| p.get("shape")).and_then(Value::as_str);
+                if lines.len() == 1 {
+                    let exact = hint.zip(properties).and_then(|(hint, p)| self.shape_from_properties(hint, p));
+                    if let Some(shape) = exact.filter(|shape| self.agrees(shape, &lines)) {
+                        self.result.shapes.push(shape);
+                        return Ok(());
+                    }
+                }
+                for line in lines {
+                    self.line(kind, &id, line, hint == Some("polyline"));
+                }
+            }
+            "Point" | "MultiPoint" => {
+                self.result
+                    .unsupported
+                    .push(Importer::issue(kind, id, "only polygons and lines can be imported"));
+            }
+            other => return Err(format!("unknown GeoJSON type '{}'", other)),
+        }
+        Ok(())
+    }
+
+    // Curved and rotated shapes as recorded by `format`, which the outline only approximates
+    fn shape_from_properties(&self, hint: &str, properties: &Map<String, Value>) -> Option<Shape> {
+        if hint == "path" {
+            let path = Path::parse(properties.get("data")?.as_str()?).ok()?;
+            return Some(Shape::Path(path.map(|p| p.scaled(self.scale))));
+        }
+        let number = |key: &str| properties.get(key).and_then(Value::as_f64);
+        let length = |key: &str| number(key).filter(|&value| value > 0.0).map(|value| value * self.scale);
+        let center = properties.get("center")?.as_array()?;
+        let center = Point {
+            x: center.first()?.as_f64()?,
+            y: center.get(1)?.as_f64()?,
+        }
+        .scaled(self.scale);
+        Some(match hint {
+            "circle" => Shape::Circle(Circle {
+                center,
+                radius: length("radius")?,
+            }),
+            "oriented_rectangle" => Shape::OrientedRectangle(OrientedRectangle {
+                center,
+                width: length("width")?,
+                height: length("height")?,
+                angle: number("angle")?,
+            }),
+            "ellipse" => Shape::Ellipse(Ellipse {
+                center,
+                rx: length("rx")?,
+                ry: length("ry")?,
+                angle: number("angle")?,
+            }),
+            "arc" => Shape::Arc(Arc {
+                center,
+                radius: length("radius")?,
+                start_angle: number("start_angle")?,
+                sweep_angle: number("sweep_angle").filter(|sweep| sweep.abs() <= 360.0)?,
+                closure: serde_json::from_value(properties.get("closure")?.clone()).ok()?,
+            }),
+            _ => return None,
+        })
+    }
+
+    // Whether a shape read from the properties matches the rings or line of its geometry, so
+    // properties left stale by editing the coordinates are not trusted
+    fn agrees(&self, shape: &Shape, geometry: &[Vec<Point>]) -> bool {
+        let outline = match shape_line(shape, 0, 1.0, flatten::DEFAULT_TOLERANCE) {
+            Ok(Some(line)) => line,
+            Ok(None) => match shape_polygons(shape, 0, 1.0, flatten::DEFAULT_TOLERANCE).as_deref() {
+                Ok([rings]) => rings[0].clone(),
+                _ => return false,
+            },
+            Err(_) => return false,
+        };
+        let [points] = geometry else {
+            return false;
+        };
+        let points: Vec<Point> = points.iter().map(|p| p.scaled(self.scale)).collect();
+        points.len() >= 2 && near_line(&points, &outline) && near_line(&outline, &points)
+    }
+
+    // Two points read as a segment unless recorded as a polyline, more as a polyline. Empty lines
+    // add nothing, like empty polygons.
+    fn line(&mut self, kind: &str, id: &Option<String>, points: Vec<Point>, polyline_hint: bool) {
+        let mut points: Vec<Point> = points.iter().map(|p| p.scaled(self.scale)).collect();
+        match points.len() {
+            0 => {}
+            1 => self
+                .result
+                .unsupported
+                .push(Importer::issue(kind, id.clone(), "line has fewer than 2 points")),
+            2 if !polyline_hint => {
+                let end = points.remove(1);
+                self.result.shapes.push(Shape::Segment(Segment { start: points[0], end }));
+            }
+            _ => self.result.shapes.push(Shape::Polyline(Polyline { points })),
+        }
+    }
+
+    // Several polygons read as one multi-polygon shape, a single one as a polygon
+    fn polygons(&mut self, kind: &str, id: Option<String>, polygons: Vec<Rings>, rectangle_hint: bool) {
+        let mut members: Vec<Polygon> = polygons
+            .into_iter()
+            .filter_map(|rings| self.polygon(kind, &id, rings))
+            .collect();
+        match members.len() {
+            0 => {}
+            1 => {
+                let polygon = members.remove(0);
+                let rectangle = (rectangle_hint && polygon.holes.is_empty())
+                    .then(|| rectangle(&polygon.points))
+                    .flatten();
+                self.result.shapes.push(match rectangle {
+                    Some(rect) => Shape::Rectangle(rect),
+                    None => Shape::Polygon(polygon),
+                });
+            }
+            _ => self.result.shapes.push(Shape::MultiPolygon(MultiPolygon { polygons: members })),
+        }
+    }
+
+    // Exterior ring first; rings with fewer than 3 points are reported and left out
+    fn polygon(&mut self, kind: &str, id: &Option<String>, rings: Rings) -> Option<Polygon> {
+        let mut rings = rings.into_iter().map(|mut ring| {
+            dedup_closing_point(&mut ring);
+            ring.iter().map(|p| p.scaled(self.scale)).collect::<Vec<Point>>()
+        });
+        let exterior = rings.next()?;
+        if exterior.len() < 3 {
+            self.result
+                .unsupported
+                .push(Importer::issue(kind, id.clone(), "ring has fewer than 3 points"));
+            return None;
+        }
+        let mut polygon = Polygon::new(exterior);
+        for hole in rings {
+            if hole.len() < 3 {
+                self.result
+                    .warnings
+                    .push(Importer::issue(kind, id.clone(), "a hole with fewer than 3 points was dropped"));
+            } else {
+                polygon.holes.push(hole);
+            }
+        }
+        Some(polygon)
+    }
+
+    fn wkt(&mut self, geometry: Wkt) {
+        match geometry {
+            Wkt::Polygon(rings) => self.polygons("POLYGON", None, vec![rings], false),
+            Wkt::MultiPolygon(polygons) => self.polygons("MULTIPOLYGON", None, polygons, false),
+            Wkt::LineString(points) => self.line("LINESTRING", &None, points, false),
+            Wkt::MultiLineString(lines) => {
+                for points in lines {
+                    self.line("MULTILINESTRING", &None, points, false);
+                }
+            }
+            Wkt::Collection(geometries) => {
+                for geometry in geometries {
+                    self.wkt(geometry);
+                }
+            }
+            Wkt::Other(kind) => self
+                .result
+                .unsupported
+                .push(Importer::issue(&kind, None, "only polygons and lines can be imported")),
+        }
+    }
+}
+
+// An axis-aligned rectangle from its four corners in any order of traversal
+fn rectangle(points: &[Point]) -> Option<Rectangle> {
+    if points.len() != 4 {
+        return None;
+    }
+    let bbox = ring_bounding_box(points);
+    let on_corner = |p: &Point| {
+        (p.x == bbox.top_left.x || p.x == bbox.bottom_right.x)
+            && (p.y == bbox.top_left.y || p.y == bbox.bottom_right.y)
+    };
+    let distinct_edges = (0..4).all(|i| {
+        let (a, b) = (points[i], points[(i + 1) % 4]);
+        (a.x == b.x) != (a.y == b.y)
+    });
+    (points.iter().all(on_corner) && distinct_edges).then_some(Rectangle {
+        top_left: Point { x: bbox.top_left.x, y: bbox.bottom_right.y },
+        bottom_right: Point { x: bbox.bottom_right.x, y: bbox.top_left.y },
+    })
+}
+
+// Whether every point lies within `HINT_TOLERANCE` of the chain of segments through `line`
+fn near_line(points: &[Point], line: &[Point]) -> bool {
+    points.iter().all(|p| {
+        line.windows(2)
+            .any(|edge| flatten::distance_to_segment(p, &edge[0], &edge[1]) <= HINT_TOLERANCE)
+    })
+}
+
+fn geojson_rings(value: &Value) -> Result<Rings, String> {
+    let rings = value.as_array().ok_or("polygon coordinates are not an array of rings")?;
+    rings.iter().map(geojson_positions).collect()
+}
+
+fn geojson_positions(value: &Value) -> Result<Vec<Point>, String> {
+    value
+        .as_array()
+        .ok_or("ring or line is not an array of positions")?
+        .iter()
+        .map(|position| {
+            let position = position.as_array().ok_or("position is not an array")?;
+            match (position.first().and_then(Value::as_f64), position.get(1).and_then(Value::as_f64)) {
+                (Some(x), Some(y)) => Ok(Point { x, y }),
+                _ => Err("position has no x and y numbers".to_string()),
+            }
+        })
+        .collect()
+}
+
+enum Wkt {
+    Polygon(Rings),
+    MultiPolygon(Vec<Rings>),
+    LineString(Vec<Point>),
+    MultiLineString(Vec<Vec<Point>>),
+    Collection(Vec<Wkt>),
+    // Geometry types other than polygons and lines, by name
+    Other(String),
+}
+
+struct WktParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl WktParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.text.len() && (self.text[self.pos].is_ascii_whitespace() || self.text[self.pos] == b';') {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at offset {}", byte as char, self.pos))
+        }
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.pos < self.text.len() && (self.text[self.pos].is_ascii_alphanumeric() || self.text[self.pos] == b'=') {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..self.pos]).to_ascii_uppercase()
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.pos < self.text.len() && matches!(self.text[self.pos], b'0'..=b'9' | b'.' | b'-' | b'+' | b'e' | b'E') {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()
+            .and_then(|number| number.parse::<f64>().ok())
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| format!("expected a number at offset {}", start))
+    }
+
+    // Geometries one after another, as when several lines of WKT are pasted
+    fn geometries(&mut self) -> Result<Vec<Wkt>, String> {
+        let mut geometries = Vec::new();
+        while self.peek().is_some() {
+            geometries.push(self.geometry()?);
+        }
+        if geometries.is_empty() {
+            return Err("no geometry found".to_string());
+        }
+        Ok(geometries)
+    }
+
+    fn geometry(&mut self) -> Result<Wkt, String> {
+        let mut kind = self.word();
+        // EWKT prefix, e.g. `SRID=4326;POLYGON(...)`
+        if kind.starts_with("SRID=") {
+            kind = self.word();
+        }
+        if kind.is_empty() {
+            return Err(format!("expected a geometry type at offset {}", self.pos));
+        }
+        let save = self.pos;
+        match self.word().as_str() {
+            "Z" | "M" | "ZM" => {}
+            _ => self.pos = save,
+        }
+        let save = self.pos;
+        if self.word() == "EMPTY" {
+            return Ok(match kind.as_str() {
+                "POLYGON" => Wkt::Polygon(Vec::new()),
+                "MULTIPOLYGON" => Wkt::MultiPolygon(Vec::new()),
+                "LINESTRING" => Wkt::LineString(Vec::new()),
+                "MULTILINESTRING" => Wkt::MultiLineString(Vec::new()),
+                "GEOMETRYCOLLECTION" => Wkt::Collection(Vec::new()),
+                _ => Wkt::Other(kind),
+            });
+        }
+        self.pos = save;
+        match kind.as_str() {
+            "POLYGON" => Ok(Wkt::Polygon(self.rings()?)),
+            "MULTIPOLYGON" => Ok(Wkt::MultiPolygon(self.list(|parser| parser.rings())?)),
+            "LINESTRING" => Ok(Wkt::LineString(self.list(|parser| parser.coordinate())?)),
+            "MULTILINESTRING" => Ok(Wkt::MultiLineString(
+                self.list(|parser| parser.list(|parser| parser.coordinate()))?,
+            )),
+            "GEOMETRYCOLLECTION" => Ok(Wkt::Collection(self.list(|parser| parser.geometry())?)),
+            _ => {
+                self.skip_group()?;
+                Ok(Wkt::Other(kind))
+            }
+        }
+    }
+
+    // `( item, item, ... )`
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        self.expect(b'(')?;
+        let mut items = vec![item(self)?];
+        while self.peek() == Some(b',') {
+            self.pos += 1;
+            items.push(item(self)?);
+        }
+        self.expect(b')')?;
+        Ok(items)
+    }
+
+    fn rings(&mut self) -> Result<Rings, String> {
+        self.list(|parser| parser.list(|parser| parser.coordinate()))
+    }
+
+    // Extra Z and M ordinates are ignored
+    fn coordinate(&mut self) -> Result<Point, String> {
+        let x = self.number()?;
+        let y = self.number()?;
+        while matches!(self.peek(), Some(b'0'..=b'9' | b'.' | b'-' | b'+')) {
+            self.number()?;
+        }
+        Ok(Point { x, y })
+    }
+
+    // Skips a balanced parenthesized group of an unsupported geometry
+    fn skip_group(&mut self) -> Result<(), String> {
+        self.expect(b'(')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.text.get(self.pos) {
+                Some(b'(') => depth += 1,
+                Some(b')') => depth -= 1,
+                Some(_) => {}
+                None => return Err("unbalanced parentheses".to_string()),
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+}
+
+// Points of an open shape in `unit`, with open arcs flattened to within `tolerance` internal
+// pixels; `None` for shapes with an outline
+fn shape_line(shape: &Shape, index: usize, scale: f64, tolerance: f64) -> Result<Option<Vec<Point>>, DocumentError> {
+    let points = match shape {
+        Shape::Segment(segment) => vec![segment.start, segment.end],
+        Shape::Polyline(polyline) => polyline.points.clone(),
+        Shape::Arc(arc) if arc.closure == ArcClosure::Open => arc.points(tolerance),
+        Shape::Path(path) if !path.closed => path.points(tolerance),
+        _ => return Ok(None),
+    };
+    if points.len() < 2 {
+        return Err(DocumentError::Render {
+            reason: format!("shape {} has fewer than 2 points", index + 1),
+        });
+    }
+    Ok(Some(points.iter().map(|p| p.scaled(scale)).collect()))
+}
+
+// Polygons of a shape in `unit`, exterior rings counter-clockwise and holes clockwise, all
+// closed, as GeoJSON requires. Curves are flattened to within `tolerance` internal pixels.
+// Open shapes have none; `shape_line` covers them.
+fn shape_polygons(shape: &Shape, index: usize, scale: f64, tolerance: f64) -> Result<Vec<Rings>, DocumentError> {
+    let polygons = match shape {
+        Shape::Segment(_) | Shape::Polyline(_) => Vec::new(),
+        Shape::Arc(arc) if arc.closure == ArcClosure::Open => Vec::new(),
+        Shape::Path(path) if !path.closed => Vec::new(),
+        Shape::Path(path) => vec![Polygon::new(path.points(tolerance))],
+        Shape::Rectangle(rect) => {
+            let (a, b) = (rect.top_left, rect.bottom_right);
+            vec![Polygon::new(vec![a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }])]
+        }
+        Shape::OrientedRectangle(rect) => vec![rect.outline()],
+        Shape::Ellipse(ellipse) => vec![Polygon::new(ellipse.outline(tolerance))],
+        Shape::Arc(arc) => vec![Polygon::new(arc.outline(tolerance))],
+        Shape::Circle(circle) => vec![Polygon::new(flatten::ellipse(
+            circle.center,
+            circle.radius,
+            circle.radius,
+            tolerance,
+        ))],
+        Shape::Polygon(polygon) => vec![polygon.clone()],
+        Shape::MultiPolygon(multi) => multi.polygons.clone(),
+    };
+    polygons
+        .iter()
+        .map(|polygon| {
+            polygon
+                .rings()
+                .enumerate()
+                .map(|(i, ring)| {
+                    let mut ring = ring.clone();
+                    dedup_closing_point(&mut ring);
+                    if ring.len() < 3 {
+                        return Err(DocumentError::Render {
+                            reason: format!("shape {} has a ring with fewer than 3 points", index + 1),
+                        });
+                    }
+                    // Exterior rings counter-clockwise, holes clockwise
+                    if (ring_signed_area(&ring) < 0.0) == (i == 0) {
+                        ring.reverse();
+                    }
+                    ring.push(ring[0]);
+                    Ok(ring.iter().map(|p| p.scaled(scale)).collect())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn round(value: f64) -> f64 {
+    format_number(value, DECIMALS).parse().unwrap_or(value)
+}
+
+// Writes shapes as a GeoJSON FeatureCollection or as WKT, with coordinates in `unit`. Open
+// shapes become line strings, and several shapes a WKT GEOMETRYCOLLECTION. GeoJSON features record the original shape type so
+// rectangles and circles read back exactly.
+pub(crate) fn format(
+    shapes: &[Shape],
+    format: GeometryFormat,
+    unit: MeasurementUnit,
+    settings: &UnitSettings,
+) -> Result<String, DocumentError> {
+    let scale = 1.0 / unit.pixels_per_unit(settings.dpi);
+    let polygons = shapes
+        .iter()
+        .enumerate()
+        .map(|(i, shape)| shape_polygons(shape, i, scale, flatten::DEFAULT_TOLERANCE))
+        .collect::<Result<Vec<_>, _>>()?;
+    let lines = shapes
+        .iter()
+        .enumerate()
+        .map(|(i, shape)| shape_line(shape, i, scale, flatten::DEFAULT_TOLERANCE))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match format {
+        GeometryFormat::GeoJson => {
+            let features: Vec<Value> = shapes
+                .iter()
+                .zip(polygons.iter().zip(&lines))
+                .map(|(shape, (members, line))| {
+                    let coordinates: Vec<Vec<Vec<[f64; 2]>>> = members
+                        .iter()
+                        .map(|rings| {
+                            rings
+                                .iter()
+                                .map(|ring| ring.iter().map(|p| [round(p.x), round(p.y)]).collect())
+                                .collect()
+                        })
+                        .collect();
+                    let properties = match shape {
+                        Shape::Rectangle(_) => json!({ "shape": "rectangle" }),
+                        Shape::OrientedRectangle(rect) => json!({
+                            "shape": "oriented_rectangle",
+                            "center": [round(rect.center.x * scale), round(rect.center.y * scale)],
+                            "width": round(rect.width * scale),
+                            "height": round(rect.height * scale),
+                            "angle": round(rect.angle),
+                        }),
+                        Shape::Circle(circle) => json!({
+                            "shape": "circle",
+                            "center": [round(circle.center.x * scale), round(circle.center.y * scale)],
+                            "radius": round(circle.radius * scale),
+                        }),
+                        Shape::Ellipse(ellipse) => json!({
+                            "shape": "ellipse",
+                            "center": [round(ellipse.center.x * scale), round(ellipse.center.y * scale)],
+                            "rx": round(ellipse.rx * scale),
+                            "ry": round(ellipse.ry * scale),
+                            "angle": round(ellipse.angle),
+                        }),
+                        Shape::Arc(arc) => json!({
+                            "shape": "arc",
+                            "center": [round(arc.center.x * scale), round(arc.center.y * scale)],
+                            "radius": round(arc.radius * scale),
+                            "start_angle": round(arc.start_angle),
+                            "sweep_angle": round(arc.sweep_angle),
+                            "closure": arc.closure,
+                        }),
+                        Shape::Polygon(_) => json!({ "shape": "polygon" }),
+                        Shape::MultiPolygon(_) => json!({ "shape": "multi_polygon" }),
+                        Shape::Segment(_) => json!({ "shape": "segment" }),
+                        Shape::Polyline(_) => json!({ "shape": "polyline" }),
+                        Shape::Path(path) => json!({
+                            "shape": "path",
+                            "data": path.map(|p| p.scaled(scale)).to_data(),
+                        }),
+                    };
+                    let geometry = match (shape, line) {
+                        (_, Some(line)) => {
+                            let positions: Vec<[f64; 2]> = line.iter().map(|p| [round(p.x), round(p.y)]).collect();
+                            json!({ "type": "LineString", "coordinates": positions })
+                        }
+                        (Shape::MultiPolygon(_), None) => json!({ "type": "MultiPolygon", "coordinates": coordinates }),
+                        _ => json!({ "type": "Polygon", "coordinates": coordinates[0] }),
+                    };
+                    json!({
+                        "type": "Feature",
+                        "properties": properties,
+                        "geometry": geometry,
+                    })
+                })
+                .collect();
+            Ok(serde_json::to_string_pretty(&json!({
+                "type": "FeatureCollection",
+                "features": features,
+            }))?)
+        }
+        GeometryFormat::Wkt => {
+            let geometries: Vec<String> = shapes
+                .iter()
+                .zip(polygons.iter().zip(&lines))
+                .map(|(shape, (members, line))| match (shape, line) {
+                    (_, Some(line)) => format!("LINESTRING {}", wkt_points(line)),
+                    (Shape::MultiPolygon(_), None) => {
+                        let bodies: Vec<String> = members.iter().map(wkt_rings).collect();
+                        format!("MULTIPOLYGON ({})", bodies.join(", "))
+                    }
+                    _ => format!("POLYGON {}", wkt_rings(&members[0])),
+                })
+                .collect();
+            Ok(match geometries.len() {
+                0 => "GEOMETRYCOLLECTION EMPTY".to_string(),
+                1 => geometries[0].clone(),
+                _ => format!("GEOMETRYCOLLECTION ({})", geometries.join(", ")),
+            })
+        }
+    }
+}
+
+fn wkt_rings(rings: &Rings) -> String {
+    let bodies: Vec<String> = rings.iter().map(|ring| wkt_points(ring)).collect();
+    format!("({})", bodies.join(", "))
+}
+
+fn wkt_points(points: &[Point]) -> String {
+    let mut text = String::from("(");
+    for (i, p) in points.iter().enumerate() {
+        let separator = if i > 0 { ", " } else { "" };
+        let _ = write!(
+            text,
+            "{}{} {}",
+            separator,
+            format_number(p.x, DECIMALS),
+            format_number(p.y, DECIMALS)
+        );
+    }
+    text.push(')');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bezier::PathSegment;
+    use crate::precision::Precision;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn square(x: f64, y: f64, side: f64) -> Vec<Point> {
+        vec![point(x, y), point(x + side, y), point(x + side, y + side), point(x, y + side)]
+    }
+
+    fn area(shape: &Shape) -> f64 {
+        shape.area(&Precision::default()).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    fn round_trip(shapes: &[Shape], format: GeometryFormat) -> GeometryImport {
+        let settings = UnitSettings::default();
+        let text = super::format(shapes, format, MeasurementUnit::Centimeter, &settings).unwrap();
+        let imported = parse(&text, MeasurementUnit::Centimeter, &settings).unwrap();
+        assert_eq!(imported.format, format);
+        assert!(imported.unsupported.is_empty(), "{:?}", imported.unsupported);
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        imported
+    }
+
+    fn polygons() -> Vec<Shape> {
+        let mut holed = Polygon::new(square(0.0, 0.0, 300.0));
+        holed.holes.push(square(100.0, 100.0, 100.0));
+        vec![
+            Shape::Polygon(holed),
+            Shape::MultiPolygon(MultiPolygon {
+                polygons: vec![Polygon::new(square(400.0, 0.0, 50.0)), Polygon::new(square(500.0, 0.0, 80.0))],
+            }),
+        ]
+    }
+
+    #[test]
+    fn geojson_round_trips_every_shape() {
+        let arc = Arc {
+            center: point(10.0, 20.0),
+            radius: 30.0,
+            start_angle: 15.0,
+            sweep_angle: 120.0,
+            closure: ArcClosure::Sector,
+        };
+        let path = Path {
+            start: point(0.0, 0.0),
+            segments: vec![PathSegment::Quadratic { control: point(50.0, 100.0), to: point(100.0, 0.0) }],
+            closed: true,
+        };
+        let mut shapes = vec![
+            Shape::Rectangle(Rectangle { top_left: point(0.0, 0.0), bottom_right: point(40.0, 20.0) }),
+            Shape::OrientedRectangle(OrientedRectangle {
+                center: point(5.0, 5.0),
+                width: 40.0,
+                height: 20.0,
+                angle: 30.0,
+            }),
+            Shape::Circle(Circle { center: point(100.0, 100.0), radius: 50.0 }),
+            Shape::Ellipse(Ellipse { center: point(0.0, 0.0), rx: 60.0, ry: 30.0, angle: 45.0 }),
+            Shape::Arc(arc),
+            Shape::Path(path),
+            Shape::Segment(Segment { start: point(0.0, 0.0), end: point(30.0, 40.0) }),
+            Shape::Polyline(Polyline { points: vec![point(0.0, 0.0), point(30.0, 0.0)] }),
+        ];
+        shapes.extend(polygons());
+        let imported = round_trip(&shapes, GeometryFormat::GeoJson);
+
+        let kinds: Vec<String> = imported
+            .shapes
+            .iter()
+            .map(|shape| serde_json::to_value(shape).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        let expected: Vec<String> = shapes
+            .iter()
+            .map(|shape| serde_json::to_value(shape).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, expected);
+        for (actual, expected) in imported.shapes.iter().zip(&shapes) {
+            if let Ok(expected) = expected.area(&Precision::default()) {
+                assert_close(area(actual), expected, 1e-6 * expected);
+            }
+        }
+        match &imported.shapes[8] {
+            Shape::Polygon(polygon) => assert_eq!(polygon.holes.len(), 1),
+            shape => panic!("expected a polygon, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn wkt_round_trips_holes_multi_polygons_and_lines() {
+        let mut shapes = polygons();
+        shapes.push(Shape::Polyline(Polyline { points: vec![point(0.0, 0.0), point(30.0, 0.0), point(30.0, 40.0)] }));
+        let imported = round_trip(&shapes, GeometryFormat::Wkt);
+
+        match imported.shapes.as_slice() {
+            [holed @ Shape::Polygon(polygon), multi @ Shape::MultiPolygon(members), Shape::Polyline(line)] => {
+                assert_eq!(polygon.holes.len(), 1);
+                assert_close(area(holed), 80_000.0, 1e-6 * 80_000.0);
+                assert_eq!(members.polygons.len(), 2);
+                assert_close(area(multi), 8_900.0, 1e-6 * 8_900.0);
+                assert_eq!(line.points.len(), 3);
+            }
+            shapes => panic!("expected a polygon, a multi-polygon and a polyline, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn stale_shape_properties_are_ignored() {
+        let feature = |radius: f64| {
+            let circle = Shape::Circle(Circle { center: point(0.0, 0.0), radius: 100.0 });
+            let settings = UnitSettings::default();
+            let text = format(&[circle], GeometryFormat::GeoJson, MeasurementUnit::Pixel, &settings).unwrap();
+            let mut value: Value = serde_json::from_str(&text).unwrap();
+            value["features"][0]["properties"]["radius"] = json!(radius);
+            parse(&value.to_string(), MeasurementUnit::Pixel, &settings).unwrap()
+        };
+        assert!(matches!(feature(100.0).shapes.as_slice(), [Shape::Circle(_)]));
+        // Someone edited the radius but not the outline, which wins
+        assert!(matches!(feature(150.0).shapes.as_slice(), [Shape::Polygon(_)]));
+    }
+
+    #[test]
+    fn reads_ewkt_and_extra_ordinates() {
+        let settings = UnitSettings::default();
+        for text in [
+            "SRID=4326;POLYGON((0 0, 10 0, 10 10, 0 10, 0 0))",
+            "POLYGON Z ((0 0 1, 10 0 1, 10 10 2, 0 10 2, 0 0 1))",
+            "POLYGON M ((0 0 5, 10 0 6, 10 10 7, 0 10 8, 0 0 5))",
+            "polygon zm ((0 0 1 5, 10 0 1 6, 10 10 2 7, 0 10 2 8, 0 0 1 5))",
+        ] {
+            let imported = parse(text, MeasurementUnit::Pixel, &settings).unwrap();
+            match imported.shapes.as_slice() {
+                [shape @ Shape::Polygon(polygon)] => {
+                    assert_eq!(polygon.points.len(), 4, "{}", text);
+                    assert_close(area(shape), 100.0, 1e-9);
+                }
+                shapes => panic!("expected one polygon from {}, got {:?}", text, shapes),
+            }
+        }
+        let imported = parse("LINESTRING Z (0 0 1, 3 4 1)", MeasurementUnit::Pixel, &settings).unwrap();
+        assert!(matches!(imported.shapes.as_slice(), [Shape::Segment(_)]));
+    }
+
+    #[test]
+    fn empty_geometries_add_nothing() {
+        let settings = UnitSettings::default();
+        for text in [
+            "POLYGON EMPTY",
+            "MULTIPOLYGON EMPTY",
+            "LINESTRING Z EMPTY",
+            r#"{ "type": "LineString", "coordinates": [] }"#,
+            "GEOMETRYCOLLECTION EMPTY",
+            r#"{ "type": "Polygon", "coordinates": [] }"#,
+            r#"{ "type": "Feature", "geometry": null, "properties": {} }"#,
+        ] {
+            let imported = parse(text, MeasurementUnit::Pixel, &settings).unwrap();
+            assert!(imported.shapes.is_empty(), "{}", text);
+            assert!(imported.unsupported.is_empty(), "{}", text);
+        }
+        let imported = parse("POINT EMPTY", MeasurementUnit::Pixel, &settings).unwrap();
+        assert_eq!(imported.unsupported.len(), 1);
+        assert_eq!(
+            format(&[], GeometryFormat::Wkt, MeasurementUnit::Pixel, &settings).unwrap(),
+            "GEOMETRYCOLLECTION EMPTY"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        let settings = UnitSettings::default();
+        for text in ["", "POLYGON((0 0, 1 0", "POLYGON((0 0, x 1))", "{ \"type\": \"Blob\" }", "{"] {
+            assert!(parse(text, MeasurementUnit::Pixel, &settings).is_err(), "accepted {:?}", text);
+        }
+    }
+}
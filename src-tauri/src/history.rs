@@ -0,0 +1,1068 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{GeometryError, SceneError};
+use crate::scene::{parse_color, Group, Layer, Scene, SceneShape};
+use crate::Shape;
+
+// Undo steps kept; older ones are dropped
+const MAX_DEPTH: usize = 100;
+
+// Emitted with a `SceneChange` after an undo or redo
+pub(crate) const SCENE_CHANGED: &str = "scene-changed";
+// Emitted with a `HistoryState` whenever what can be undone or redone changes
+pub(crate) const HISTORY_CHANGED: &str = "history-changed";
+
+// What an edit of an existing shape did, so the frontend can label its undo step
+#[derive(Deserialize,Serialize,Debug,Clone,Copy,PartialEq,Eq,Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EditKind {
+    Move,
+    Transform,
+    #[default]
+    EditProperty,
+}
+
+#[derive(Serialize,Debug,Clone,Copy,PartialEq,Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OperationKind {
+    Add,
+    Delete,
+    Clear,
+    Move,
+    Transform,
+    EditProperty,
+    AddLayer,
+    EditLayer,
+    ReorderLayer,
+    MoveToLayer,
+    MergeLayers,
+    Group,
+    Ungroup,
+}
+
+// One undoable change to the scene, holding everything needed to apply and revert it
+#[derive(Debug,Clone)]
+pub(crate) enum Operation {
+    Add { index: usize, item: SceneShape },
+    // `groups` are those the deletion leaves without shapes, with where each stood among all
+    // groups, in order
+    Delete { index: usize, item: SceneShape, groups: Vec<(usize, Group)> },
+    // Shapes on unlocked layers, with where each stood among all shapes, in order, and likewise
+    // the groups left without shapes
+    Clear { items: Vec<(usize, SceneShape)>, groups: Vec<(usize, Group)> },
+    Edit { kind: EditKind, id: Uuid, before: Shape, after: Shape },
+    // Every shape in group `id` and its nested groups, with its geometry before and after
+    EditGroup { kind: EditKind, id: Uuid, edits: Vec<(Uuid, Shape, Shape)> },
+    AddLayer { index: usize, layer: Layer },
+    EditLayer { before: Layer, after: Layer },
+    ReorderLayer { id: Uuid, from: usize, to: usize },
+    // Each moved shape with the layer it came from
+    MoveToLayer { moves: Vec<(Uuid, Uuid)>, to: Uuid },
+    // `layer` is removed from `index` in the stack after its shapes move onto `into`
+    MergeLayers { index: usize, layer: Layer, into: Uuid, shapes: Vec<Uuid> },
+    // `group` is added after the other groups and takes in each member shape and group, which
+    // are listed with the group they came from
+    Group { group: Group, shapes: Vec<(Uuid, Option<Uuid>)>, groups: Vec<(Uuid, Option<Uuid>)> },
+    // `group` is removed from `index` among groups after its members move up to its parent
+    Ungroup { index: usize, group: Group, shapes: Vec<Uuid>, groups: Vec<Uuid> },
+}
+
+// How a new operation combines with the previous one of the same gesture
+enum Merge {
+    Merged,
+    // The two undo each other, e.g. adding a polygon and cancelling it
+    Cancelled,
+    Separate(Operation),
+}
+
+impl Operation {
+    // Adds `item` on top of the scene
+    pub fn add(scene: &Scene, item: SceneShape) -> Result<Operation, SceneError> {
+        scene.unlocked_layer(item.layer)?;
+        Ok(Operation::Add {
+            index: scene.shapes().len(),
+            item,
+        })
+    }
+
+    pub fn delete(scene: &Scene, id: Uuid) -> Result<Operation, SceneError> {
+        let index = scene.index(id)?;
+        let item = scene.shapes()[index].clone();
+        scene.unlocked_layer(item.layer)?;
+        // The outermost group holding no other shape goes, along with everything nested in it
+        let emptied = std::iter::successors(item.group, |&id| scene.group(id).ok().and_then(|group| group.parent))
+            .take_while(|&id| scene.descendants(id).is_ok_and(|items| items.len() == 1))
+            .last();
+        let groups = match emptied {
+            Some(top) => indexed_groups(scene, |group| scene.is_within(group.id, top)),
+            None => Vec::new(),
+        };
+        Ok(Operation::Delete { index, item, groups })
+    }
+
+    // Removes every shape that is not on a locked layer, and the groups that leaves empty
+    pub fn clear(scene: &Scene) -> Operation {
+        let (items, kept): (Vec<_>, Vec<_>) = scene
+            .shapes()
+            .iter()
+            .enumerate()
+            .partition(|(_, item)| scene.unlocked_layer(item.layer).is_ok());
+        Operation::Clear {
+            items: items.into_iter().map(|(index, item)| (index, item.clone())).collect(),
+            groups: indexed_groups(scene, |group| {
+                !kept
+                    .iter()
+                    .any(|(_, item)| item.group.is_some_and(|id| scene.is_within(id, group.id)))
+            }),
+        }
+    }
+
+    pub fn edit(scene: &Scene, kind: EditKind, id: Uuid, shape: Shape) -> Result<Operation, SceneError> {
+        let item = scene.get(id)?;
+        scene.unlocked_layer(item.layer)?;
+        Ok(Operation::Edit {
+            kind,
+            id,
+            before: item.shape.clone(),
+            after: shape,
+        })
+    }
+
+    // Replaces the geometry of every shape in group `id` and its nested groups with `map` of it,
+    // e.g. to move or transform the group as one
+    pub fn edit_group(
+        scene: &Scene,
+        kind: EditKind,
+        id: Uuid,
+        map: impl Fn(&Shape) -> Result<Shape, GeometryError>,
+    ) -> Result<Operation, SceneError> {
+        let mut edits = Vec::new();
+        for item in scene.descendants(id)? {
+            scene.unlocked_layer(item.layer)?;
+            edits.push((item.id, item.shape.clone(), map(&item.shape)?));
+        }
+        if edits.is_empty() {
+            return Err(SceneError::EmptyGroup);
+        }
+        Ok(Operation::EditGroup { kind, id, edits })
+    }
+
+    // Adds `layer` on top of the stack
+    pub fn add_layer(scene: &Scene, layer: Layer) -> Result<Operation, SceneError> {
+        check_color(&layer.color)?;
+        Ok(Operation::AddLayer {
+            index: scene.layers().len(),
+            layer,
+        })
+    }
+
+    // Replaces the properties of the layer with the same id as `layer`. Locked layers can
+    // still be renamed, hidden or unlocked.
+    pub fn edit_layer(scene: &Scene, layer: Layer) -> Result<Operation, SceneError> {
+        check_color(&layer.color)?;
+        Ok(Operation::EditLayer {
+            before: scene.layer(layer.id)?.clone(),
+            after: layer,
+        })
+    }
+
+    // Moves a layer to `index` in the stack, bottom first
+    pub fn reorder_layer(scene: &Scene, id: Uuid, index: usize) -> Result<Operation, SceneError> {
+        Ok(Operation::ReorderLayer {
+            id,
+            from: scene.layer_index(id)?,
+            to: index.min(scene.layers().len() - 1),
+        })
+    }
+
+    pub fn move_to_layer(scene: &Scene, ids: &[Uuid], to: Uuid) -> Result<Operation, SceneError> {
+        scene.unlocked_layer(to)?;
+        let mut moves = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let item = scene.get(id)?;
+            scene.unlocked_layer(item.layer)?;
+            moves.push((id, item.layer));
+        }
+        Ok(Operation::MoveToLayer { moves, to })
+    }
+
+    // Moves the shapes of layer `id` onto layer `into` and removes the emptied layer
+    pub fn merge_layers(scene: &Scene, id: Uuid, into: Uuid) -> Result<Operation, SceneError> {
+        if id == into {
+            return Err(SceneError::MergeIntoSelf);
+        }
+        let layer = scene.unlocked_layer(id)?.clone();
+        scene.unlocked_layer(into)?;
+        Ok(Operation::MergeLayers {
+            index: scene.layer_index(id)?,
+            shapes: scene.shapes().iter().filter(|item| item.layer == id).map(|item| item.id).collect(),
+            layer,
+            into,
+        })
+    }
+
+    // Puts shapes `ids` and groups `groups` in a new group, nested in the group they are all in,
+    // if any, returning the group with the operation that adds it
+    pub fn group(scene: &Scene, ids: &[Uuid], groups: &[Uuid]) -> Result<(Operation, Group), SceneError> {
+        let mut shapes = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let item = scene.get(id)?;
+            scene.unlocked_layer(item.layer)?;
+            shapes.push((id, item.group));
+        }
+        let mut nested = Vec::with_capacity(groups.len());
+        for &id in groups {
+            nested.push((id, scene.group(id)?.parent));
+            for item in scene.descendants(id)? {
+                scene.unlocked_layer(item.layer)?;
+            }
+        }
+        let mut parents = shapes.iter().chain(&nested).map(|&(_, parent)| parent);
+        let first = parents.next().ok_or(SceneError::EmptyGroup)?;
+        let parent = if parents.all(|parent| parent == first) { first } else { None };
+        let group = Group::new(parent);
+        Ok((
+            Operation::Group {
+                group: group.clone(),
+                shapes,
+                groups: nested,
+            },
+            group,
+        ))
+    }
+
+    // Removes group `id`, leaving its shapes and nested groups in its parent
+    pub fn ungroup(scene: &Scene, id: Uuid) -> Result<Operation, SceneError> {
+        let index = scene.group_index(id)?;
+        let (shapes, groups) = scene.members(id);
+        for item in &shapes {
+            scene.unlocked_layer(item.layer)?;
+        }
+        Ok(Operation::Ungroup {
+            index,
+            group: scene.groups()[index].clone(),
+            shapes: shapes.iter().map(|item| item.id).collect(),
+            groups: groups.iter().map(|group| group.id).collect(),
+        })
+    }
+
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Operation::Add { .. } => OperationKind::Add,
+            Operation::Delete { .. } => OperationKind::Delete,
+            Operation::Clear { .. } => OperationKind::Clear,
+            Operation::Edit { kind: EditKind::Move, .. } => OperationKind::Move,
+            Operation::Edit { kind: EditKind::Transform, .. } => OperationKind::Transform,
+            Operation::Edit { kind: EditKind::EditProperty, .. } => OperationKind::EditProperty,
+            Operation::EditGroup { kind: EditKind::Move, .. } => OperationKind::Move,
+            Operation::EditGroup { kind: EditKind::Transform, .. } => OperationKind::Transform,
+            Operation::EditGroup { kind: EditKind::EditProperty, .. } => OperationKind::EditProperty,
+            Operation::AddLayer { .. } => OperationKind::AddLayer,
+            Operation::EditLayer { .. } => OperationKind::EditLayer,
+            Operation::ReorderLayer { .. } => OperationKind::ReorderLayer,
+            Operation::MoveToLayer { .. } => OperationKind::MoveToLayer,
+            Operation::MergeLayers { .. } => OperationKind::MergeLayers,
+            Operation::Group { .. } => OperationKind::Group,
+            Operation::Ungroup { .. } => OperationKind::Ungroup,
+        }
+    }
+
+    // Whether the layer stack itself changes, rather than only shapes
+    fn changes_layers(&self) -> bool {
+        matches!(
+            self,
+            Operation::AddLayer { .. }
+                | Operation::EditLayer { .. }
+                | Operation::ReorderLayer { .. }
+                | Operation::MergeLayers { .. }
+        )
+    }
+
+    // Whether groups are added, removed or nested differently
+    fn changes_groups(&self) -> bool {
+        match self {
+            Operation::Delete { groups, .. } | Operation::Clear { groups, .. } => !groups.is_empty(),
+            Operation::Group { .. } | Operation::Ungroup { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn apply(&self, scene: &mut Scene, change: &mut SceneChange) -> Result<(), SceneError> {
+        match self {
+            Operation::Add { index, item } => {
+                scene.insert(*index, item.clone());
+                change.updated.push(item.clone());
+            }
+            Operation::Delete { item, groups, .. } => {
+                scene.remove(item.id)?;
+                change.removed.push(item.id);
+                for (_, group) in groups {
+                    scene.remove_group(group.id)?;
+                }
+            }
+            Operation::Clear { items, groups } => {
+                for (_, item) in items {
+                    scene.remove(item.id)?;
+                    change.removed.push(item.id);
+                }
+                for (_, group) in groups {
+                    scene.remove_group(group.id)?;
+                }
+            }
+            Operation::Edit { id, after, .. } => change.updated.push(scene.update(*id, after.clone())?),
+            Operation::EditGroup { edits, .. } => {
+                for (id, _, after) in edits {
+                    change.updated.push(scene.update(*id, after.clone())?);
+                }
+            }
+            Operation::AddLayer { index, layer } => scene.insert_layer(*index, layer.clone()),
+            Operation::EditLayer { after, .. } => {
+                scene.update_layer(after.clone())?;
+            }
+            Operation::ReorderLayer { id, to, .. } => scene.move_layer(*id, *to)?,
+            Operation::MoveToLayer { moves, to } => {
+                for (id, _) in moves {
+                    change.updated.push(scene.set_layer(*id, *to)?);
+                }
+            }
+            Operation::MergeLayers { layer, into, shapes, .. } => {
+                for id in shapes {
+                    change.updated.push(scene.set_layer(*id, *into)?);
+                }
+                scene.remove_layer(layer.id)?;
+            }
+            Operation::Group { group, shapes, groups } => {
+                scene.insert_group(scene.groups().len(), group.clone());
+                for (id, _) in shapes {
+                    change.updated.push(scene.set_group(*id, Some(group.id))?);
+                }
+                for (id, _) in groups {
+                    scene.set_parent(*id, Some(group.id))?;
+                }
+            }
+            Operation::Ungroup { group, shapes, groups, .. } => {
+                for id in shapes {
+                    change.updated.push(scene.set_group(*id, group.parent)?);
+                }
+                for id in groups {
+                    scene.set_parent(*id, group.parent)?;
+                }
+                scene.remove_group(group.id)?;
+            }
+        }
+        self.record_structure(scene, change);
+        Ok(())
+    }
+
+    fn revert(&self, scene: &mut Scene, change: &mut SceneChange) -> Result<(), SceneError> {
+        match self {
+            Operation::Add { item, .. } => {
+                scene.remove(item.id)?;
+                change.removed.push(item.id);
+            }
+            Operation::Delete { index, item, groups } => {
+                for (index, group) in groups {
+                    scene.insert_group(*index, group.clone());
+                }
+                scene.insert(*index, item.clone());
+                change.updated.push(item.clone());
+            }
+            Operation::Clear { items, groups } => {
+                for (index, group) in groups {
+                    scene.insert_group(*index, group.clone());
+                }
+                for (index, item) in items {
+                    scene.insert(*index, item.clone());
+                    change.updated.push(item.clone());
+                }
+            }
+            Operation::Edit { id, before, .. } => change.updated.push(scene.update(*id, before.clone())?),
+            Operation::EditGroup { edits, .. } => {
+                for (id, before, _) in edits {
+                    change.updated.push(scene.update(*id, before.clone())?);
+                }
+            }
+            Operation::AddLayer { layer, .. } => {
+                scene.remove_layer(layer.id)?;
+            }
+            Operation::EditLayer { before, .. } => {
+                scene.update_layer(before.clone())?;
+            }
+            Operation::ReorderLayer { id, from, .. } => scene.move_layer(*id, *from)?,
+            Operation::MoveToLayer { moves, .. } => {
+                for (id, from) in moves {
+                    change.updated.push(scene.set_layer(*id, *from)?);
+                }
+            }
+            Operation::MergeLayers { index, layer, shapes, .. } => {
+                scene.insert_layer(*index, layer.clone());
+                for id in shapes {
+                    change.updated.push(scene.set_layer(*id, layer.id)?);
+                }
+            }
+            // In reverse, so a member listed twice ends up back where it first was
+            Operation::Group { group, shapes, groups } => {
+                for (id, from) in shapes.iter().rev() {
+                    change.updated.push(scene.set_group(*id, *from)?);
+                }
+                for (id, from) in groups.iter().rev() {
+                    scene.set_parent(*id, *from)?;
+                }
+                scene.remove_group(group.id)?;
+            }
+            Operation::Ungroup { index, group, shapes, groups } => {
+                scene.insert_group(*index, group.clone());
+                for id in shapes {
+                    change.updated.push(scene.set_group(*id, Some(group.id))?);
+                }
+                for id in groups {
+                    scene.set_parent(*id, Some(group.id))?;
+                }
+            }
+        }
+        self.record_structure(scene, change);
+        Ok(())
+    }
+
+    // Sends the whole layer stack or group tree with the change when the step altered it
+    fn record_structure(&self, scene: &Scene, change: &mut SceneChange) {
+        if self.changes_layers() {
+            change.layers = Some(scene.layers().to_vec());
+        }
+        if self.changes_groups() {
+            change.groups = Some(scene.groups().to_vec());
+        }
+    }
+
+    // Folds `next` into this operation so a whole drag, or drawing a shape point by point,
+    // undoes in one step
+    fn merge(&mut self, next: Operation) -> Merge {
+        match (self, next) {
+            (Operation::Add { item, .. }, Operation::Edit { id, after, .. }) if item.id == id => {
+                item.shape = after;
+                Merge::Merged
+            }
+            (Operation::Add { item, .. }, Operation::Delete { item: deleted, .. }) if item.id == deleted.id => {
+                Merge::Cancelled
+            }
+            (
+                Operation::Edit { kind, id, after, .. },
+                Operation::Edit { kind: next_kind, id: next_id, after: next_after, .. },
+            ) if *kind == next_kind && *id == next_id => {
+                *after = next_after;
+                Merge::Merged
+            }
+            (
+                Operation::EditGroup { kind, id, edits },
+                Operation::EditGroup { kind: next_kind, id: next_id, edits: next_edits },
+            ) if *kind == next_kind
+                && *id == next_id
+                && edits.len() == next_edits.len()
+                && edits.iter().zip(&next_edits).all(|(edit, next)| edit.0 == next.0) =>
+            {
+                for (edit, (_, _, after)) in edits.iter_mut().zip(next_edits) {
+                    edit.2 = after;
+                }
+                Merge::Merged
+            }
+            (Operation::EditLayer { after, .. }, Operation::EditLayer { after: next_after, .. })
+                if after.id == next_after.id =>
+            {
+                *after = next_after;
+                Merge::Merged
+            }
+            (_, next) => Merge::Separate(next),
+        }
+    }
+}
+
+// What can be undone and redone next, for labeling the undo and redo buttons
+#[derive(Serialize,Debug,Clone,Copy)]
+pub(crate) struct HistoryState {
+    pub undo: Option<OperationKind>,
+    pub redo: Option<OperationKind>,
+}
+
+// The shapes an undo or redo touched
+#[derive(Serialize,Debug,Clone)]
+pub(crate) struct SceneChange {
+    pub operation: OperationKind,
+    // Shapes added back or edited, as they are now
+    pub updated: Vec<SceneShape>,
+    pub removed: Vec<Uuid>,
+    // The whole layer stack, bottom first, when the step changed it
+    pub layers: Option<Vec<Layer>>,
+    // Every group when the step added, removed or renested one
+    pub groups: Option<Vec<Group>>,
+    pub history: HistoryState,
+}
+
+#[derive(Debug)]
+struct Entry {
+    operation: Operation,
+    // Operations sent with the same gesture, e.g. the updates of one drag, share an undo step
+    gesture: Option<String>,
+}
+
+// Undo and redo stacks for the scene. Every change to the scene goes through `perform` so it
+// can be undone.
+#[derive(Debug,Default)]
+pub(crate) struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl History {
+    pub fn perform(&mut self, scene: &mut Scene, operation: Operation, gesture: Option<String>) -> Result<(), SceneError> {
+        operation.apply(scene, &mut SceneChange::new(operation.kind()))?;
+        self.redo.clear();
+
+        let operation = match (&gesture, self.undo.back_mut()) {
+            (Some(gesture), Some(last)) if last.gesture.as_ref() == Some(gesture) => {
+                match last.operation.merge(operation) {
+                    Merge::Merged => return Ok(()),
+                    Merge::Cancelled => {
+                        self.undo.pop_back();
+                        return Ok(());
+                    }
+                    Merge::Separate(operation) => operation,
+                }
+            }
+            _ => operation,
+        };
+        self.undo.push_back(Entry { operation, gesture });
+        if self.undo.len() > MAX_DEPTH {
+            self.undo.pop_front();
+        }
+        Ok(())
+    }
+
+    // `None` when there is nothing to undo
+    pub fn undo(&mut self, scene: &mut Scene) -> Result<Option<SceneChange>, SceneError> {
+        let Some(entry) = self.undo.back() else {
+            return Ok(None);
+        };
+        let mut change = SceneChange::new(entry.operation.kind());
+        entry.operation.revert(scene, &mut change)?;
+        let entry = self.undo.pop_back().unwrap();
+        self.redo.push(Entry { gesture: None, ..entry });
+        change.history = self.state();
+        Ok(Some(change))
+    }
+
+    // `None` when there is nothing to redo
+    pub fn redo(&mut self, scene: &mut Scene) -> Result<Option<SceneChange>, SceneError> {
+        let Some(entry) = self.redo.last() else {
+            return Ok(None);
+        };
+        let mut change = SceneChange::new(entry.operation.kind());
+        entry.operation.apply(scene, &mut change)?;
+        let entry = self.redo.pop().unwrap();
+        self.undo.push_back(entry);
+        change.history = self.state();
+        Ok(Some(change))
+    }
+
+    // Forgets every step, e.g. after opening another drawing
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn state(&self) -> HistoryState {
+        HistoryState {
+            undo: self.undo.back().map(|entry| entry.operation.kind()),
+            redo: self.redo.last().map(|entry| entry.operation.kind()),
+        }
+    }
+}
+
+impl SceneChange {
+    fn new(operation: OperationKind) -> SceneChange {
+        SceneChange {
+            operation,
+            updated: Vec::new(),
+            removed: Vec::new(),
+            layers: None,
+            groups: None,
+            history: HistoryState { undo: None, redo: None },
+        }
+    }
+}
+
+// The groups matching `remove`, each with where it stands among all groups, in order
+fn indexed_groups(scene: &Scene, remove: impl Fn(&Group) -> bool) -> Vec<(usize, Group)> {
+    scene
+        .groups()
+        .iter()
+        .enumerate()
+        .filter(|(_, group)| remove(group))
+        .map(|(index, group)| (index, group.clone()))
+        .collect()
+}
+
+fn check_color(color: &str) -> Result<(), SceneError> {
+    match parse_color(color) {
+        Some(_) => Ok(()),
+        None => Err(SceneError::InvalidColor { value: color.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, Rectangle};
+
+    fn square(x: f64, size: f64) -> Shape {
+        Shape::Rectangle(Rectangle {
+            top_left: Point { x, y: 0.0 },
+            bottom_right: Point { x: x + size, y: size },
+        })
+    }
+
+    // Scenes have no equality; their saved form does
+    fn snapshot(scene: &Scene) -> serde_json::Value {
+        serde_json::to_value(scene).unwrap()
+    }
+
+    struct Fixture {
+        scene: Scene,
+        walls: Uuid,
+        // `inner` is nested in `outer`; `a` is in `inner`, `b` in `outer` and `c` in neither
+        outer: Uuid,
+        inner: Uuid,
+        a: Uuid,
+        b: Uuid,
+        c: Uuid,
+    }
+
+    fn fixture() -> Fixture {
+        let mut scene = Scene::default();
+        let base = scene.layers()[0].id;
+        let walls = Layer::new("Walls".to_string(), "#ff0000".to_string());
+        let outer = Group::new(None);
+        let inner = Group::new(Some(outer.id));
+        let a = SceneShape { group: Some(inner.id), ..SceneShape::new(square(0.0, 10.0), base) };
+        let b = SceneShape { group: Some(outer.id), ..SceneShape::new(square(20.0, 10.0), walls.id) };
+        let c = SceneShape::new(square(40.0, 10.0), base);
+        let (walls_id, outer_id, inner_id) = (walls.id, outer.id, inner.id);
+        let (a_id, b_id, c_id) = (a.id, b.id, c.id);
+        scene.insert_layer(1, walls);
+        scene.insert_group(0, outer);
+        scene.insert_group(1, inner);
+        for (index, item) in [a, b, c].into_iter().enumerate() {
+            scene.insert(index, item);
+        }
+        Fixture {
+            scene,
+            walls: walls_id,
+            outer: outer_id,
+            inner: inner_id,
+            a: a_id,
+            b: b_id,
+            c: c_id,
+        }
+    }
+
+    // Performs `operation` on `scene`, then checks that undo restores the scene exactly and redo
+    // brings the edit back
+    fn assert_reversible(mut scene: Scene, operation: Operation) {
+        let kind = operation.kind();
+        let before = snapshot(&scene);
+        let mut history = History::default();
+        history.perform(&mut scene, operation, None).unwrap();
+        let after = snapshot(&scene);
+        assert_ne!(before, after, "{:?} changed nothing", kind);
+
+        let change = history.undo(&mut scene).unwrap().unwrap();
+        assert_eq!(change.operation, kind);
+        assert_eq!(snapshot(&scene), before, "undoing {:?}", kind);
+        history.redo(&mut scene).unwrap().unwrap();
+        assert_eq!(snapshot(&scene), after, "redoing {:?}", kind);
+    }
+
+    #[test]
+    fn every_operation_undoes_and_redoes() {
+        let f = fixture();
+        let scene = &f.scene;
+        let base = scene.layers()[0].id;
+        let scaled = |shape: &Shape| Ok(shape.scaled(2.0));
+        let mut renamed = scene.layer(f.walls).unwrap().clone();
+        renamed.name = "Outer walls".to_string();
+        renamed.visible = false;
+        let operations = [
+            Operation::add(scene, SceneShape::new(square(60.0, 5.0), base)).unwrap(),
+            Operation::delete(scene, f.c).unwrap(),
+            Operation::clear(scene),
+            Operation::edit(scene, EditKind::Move, f.b, square(25.0, 10.0)).unwrap(),
+            Operation::edit_group(scene, EditKind::Transform, f.outer, scaled).unwrap(),
+            Operation::add_layer(scene, Layer::new("Notes".to_string(), "#0000ff".to_string())).unwrap(),
+            Operation::edit_layer(scene, renamed).unwrap(),
+            Operation::reorder_layer(scene, f.walls, 0).unwrap(),
+            Operation::move_to_layer(scene, &[f.a, f.c], f.walls).unwrap(),
+            Operation::merge_layers(scene, base, f.walls).unwrap(),
+            Operation::group(scene, &[f.c], &[f.outer]).unwrap().0,
+            Operation::ungroup(scene, f.outer).unwrap(),
+        ];
+        for operation in operations {
+            assert_reversible(scene.clone(), operation);
+        }
+    }
+
+    #[test]
+    fn undoing_a_delete_restores_the_groups_it_emptied() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let mut history = History::default();
+        // Deleting `b` first leaves `a` the only shape under `outer`
+        let operation = Operation::delete(&scene, f.b).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+        let before = snapshot(&scene);
+        let operation = Operation::delete(&scene, f.a).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+        assert!(scene.groups().is_empty());
+
+        let change = history.undo(&mut scene).unwrap().unwrap();
+        assert_eq!(snapshot(&scene), before);
+        let groups: Vec<Uuid> = change.groups.unwrap().iter().map(|group| group.id).collect();
+        assert_eq!(groups, [f.outer, f.inner]);
+        assert_eq!(scene.get(f.a).unwrap().group, Some(f.inner));
+    }
+
+    #[test]
+    fn deleting_keeps_groups_that_still_hold_shapes() {
+        let f = fixture();
+        match Operation::delete(&f.scene, f.a).unwrap() {
+            // `outer` still holds `b`, so only `inner` goes
+            Operation::Delete { groups, .. } => {
+                assert_eq!(groups.iter().map(|(_, group)| group.id).collect::<Vec<_>>(), [f.inner]);
+            }
+            operation => panic!("expected a delete, got {:?}", operation),
+        }
+        match Operation::delete(&f.scene, f.c).unwrap() {
+            Operation::Delete { groups, .. } => assert!(groups.is_empty()),
+            operation => panic!("expected a delete, got {:?}", operation),
+        }
+    }
+
+    #[test]
+    fn edits_of_one_gesture_undo_as_one_step() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let before = snapshot(&scene);
+        let mut history = History::default();
+        for x in [1.0, 2.0, 3.0] {
+            let operation = Operation::edit(&scene, EditKind::Move, f.c, square(40.0 + x, 10.0)).unwrap();
+            history.perform(&mut scene, operation, Some("drag".to_string())).unwrap();
+        }
+        history.undo(&mut scene).unwrap().unwrap();
+        assert_eq!(snapshot(&scene), before);
+        assert_eq!(history.state().undo, None);
+
+        // Redoing replays the whole drag
+        history.redo(&mut scene).unwrap().unwrap();
+        match &scene.get(f.c).unwrap().shape {
+            Shape::Rectangle(rect) => assert_eq!(rect.top_left.x, 43.0),
+            shape => panic!("expected a rectangle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn edits_of_another_gesture_or_kind_are_separate_steps() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let mut history = History::default();
+        let steps = [
+            (EditKind::Move, "drag"),
+            (EditKind::Move, "another drag"),
+            (EditKind::Transform, "another drag"),
+        ];
+        for (index, (kind, gesture)) in steps.into_iter().enumerate() {
+            let operation = Operation::edit(&scene, kind, f.c, square(50.0 + index as f64, 10.0)).unwrap();
+            history.perform(&mut scene, operation, Some(gesture.to_string())).unwrap();
+        }
+        let mut undone = 0;
+        while history.undo(&mut scene).unwrap().is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+    }
+
+    #[test]
+    fn drawing_a_shape_point_by_point_adds_it_in_one_step() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let before = snapshot(&scene);
+        let base = scene.layers()[0].id;
+        let mut history = History::default();
+        let item = SceneShape::new(square(60.0, 1.0), base);
+        let id = item.id;
+        let operation = Operation::add(&scene, item).unwrap();
+        history.perform(&mut scene, operation, Some("draw".to_string())).unwrap();
+        for size in [2.0, 3.0] {
+            let operation = Operation::edit(&scene, EditKind::EditProperty, id, square(60.0, size)).unwrap();
+            history.perform(&mut scene, operation, Some("draw".to_string())).unwrap();
+        }
+        assert_eq!(history.state().undo, Some(OperationKind::Add));
+        history.undo(&mut scene).unwrap().unwrap();
+        assert_eq!(snapshot(&scene), before);
+    }
+
+    #[test]
+    fn cancelling_a_shape_while_drawing_leaves_no_step() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let before = snapshot(&scene);
+        let base = scene.layers()[0].id;
+        let mut history = History::default();
+        let item = SceneShape::new(square(60.0, 1.0), base);
+        let id = item.id;
+        let operation = Operation::add(&scene, item).unwrap();
+        history.perform(&mut scene, operation, Some("draw".to_string())).unwrap();
+        let operation = Operation::delete(&scene, id).unwrap();
+        history.perform(&mut scene, operation, Some("draw".to_string())).unwrap();
+
+        assert_eq!(snapshot(&scene), before);
+        assert_eq!(history.state().undo, None);
+        assert!(history.undo(&mut scene).unwrap().is_none());
+    }
+
+    #[test]
+    fn keeps_only_the_latest_steps() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let mut history = History::default();
+        for step in 0..=MAX_DEPTH {
+            let operation = Operation::edit(&scene, EditKind::Move, f.c, square(step as f64, 10.0)).unwrap();
+            history.perform(&mut scene, operation, None).unwrap();
+        }
+        let mut undone = 0;
+        while history.undo(&mut scene).unwrap().is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_DEPTH);
+        // The first move was dropped, so undoing stops where it left the shape
+        match &scene.get(f.c).unwrap().shape {
+            Shape::Rectangle(rect) => assert_eq!(rect.top_left.x, 0.0),
+            shape => panic!("expected a rectangle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let mut history = History::default();
+        let operation = Operation::delete(&scene, f.c).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+        history.undo(&mut scene).unwrap().unwrap();
+        assert_eq!(history.state().redo, Some(OperationKind::Delete));
+
+        let operation = Operation::edit(&scene, EditKind::Move, f.b, square(25.0, 10.0)).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+        assert_eq!(history.state().redo, None);
+        assert!(history.redo(&mut scene).unwrap().is_none());
+        assert!(scene.get(f.c).is_ok());
+    }
+
+    fn lock(scene: &mut Scene, id: Uuid) {
+        let mut layer = scene.layer(id).unwrap().clone();
+        layer.locked = true;
+        scene.update_layer(layer).unwrap();
+    }
+
+    fn assert_locked<T: std::fmt::Debug>(result: Result<T, SceneError>) {
+        match result {
+            Err(SceneError::LayerLocked { name }) => assert_eq!(name, "Walls"),
+            result => panic!("expected a locked layer, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn locked_layers_refuse_shape_edits() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        lock(&mut scene, f.walls);
+        let base = scene.layers()[0].id;
+        let same = |shape: &Shape| Ok(shape.clone());
+
+        assert_locked(Operation::add(&scene, SceneShape::new(square(60.0, 5.0), f.walls)));
+        assert_locked(Operation::delete(&scene, f.b));
+        assert_locked(Operation::edit(&scene, EditKind::Move, f.b, square(25.0, 10.0)));
+        assert_locked(Operation::edit_group(&scene, EditKind::Move, f.outer, same));
+        assert_locked(Operation::move_to_layer(&scene, &[f.b], base));
+        assert_locked(Operation::move_to_layer(&scene, &[f.c], f.walls));
+        assert_locked(Operation::merge_layers(&scene, f.walls, base));
+        assert_locked(Operation::merge_layers(&scene, base, f.walls));
+
+        // Shapes on other layers stay editable, and the lock itself can be lifted
+        assert!(Operation::edit(&scene, EditKind::Move, f.c, square(45.0, 10.0)).is_ok());
+        assert!(Operation::edit_group(&scene, EditKind::Move, f.inner, same).is_ok());
+        let mut unlocked = scene.layer(f.walls).unwrap().clone();
+        unlocked.locked = false;
+        assert!(Operation::edit_layer(&scene, unlocked).is_ok());
+    }
+
+    #[test]
+    fn clearing_keeps_locked_shapes_and_their_groups() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        lock(&mut scene, f.walls);
+        let mut history = History::default();
+        let operation = Operation::clear(&scene);
+        history.perform(&mut scene, operation, None).unwrap();
+        let shapes: Vec<Uuid> = scene.shapes().iter().map(|item| item.id).collect();
+        assert_eq!(shapes, [f.b]);
+        let groups: Vec<Uuid> = scene.groups().iter().map(|group| group.id).collect();
+        assert_eq!(groups, [f.outer]);
+    }
+
+    #[test]
+    fn merging_layers_moves_shapes_and_removes_the_layer() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let base = scene.layers()[0].id;
+        let order: Vec<Uuid> = scene.shapes().iter().map(|item| item.id).collect();
+        let mut history = History::default();
+        let operation = Operation::merge_layers(&scene, base, f.walls).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+
+        assert_eq!(scene.layers().len(), 1);
+        assert_eq!(scene.layers()[0].id, f.walls);
+        assert!(scene.shapes().iter().all(|item| item.layer == f.walls));
+        let merged: Vec<Uuid> = scene.shapes().iter().map(|item| item.id).collect();
+        assert_eq!(merged, order);
+        assert_eq!(scene.problem(), None);
+
+        assert!(matches!(Operation::merge_layers(&scene, f.walls, f.walls), Err(SceneError::MergeIntoSelf)));
+        let missing = Uuid::new_v4();
+        assert!(matches!(
+            Operation::merge_layers(&scene, missing, f.walls),
+            Err(SceneError::LayerNotFound { id }) if id == missing
+        ));
+    }
+
+    #[test]
+    fn reordering_past_the_top_puts_a_layer_on_top() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let base = scene.layers()[0].id;
+        let mut history = History::default();
+        let operation = Operation::reorder_layer(&scene, base, 10).unwrap();
+        history.perform(&mut scene, operation, None).unwrap();
+        let layers: Vec<Uuid> = scene.layers().iter().map(|layer| layer.id).collect();
+        assert_eq!(layers, [f.walls, base]);
+    }
+
+    #[test]
+    fn layers_need_valid_colors() {
+        let f = fixture();
+        let layer = Layer::new("Notes".to_string(), "blue".to_string());
+        assert!(matches!(
+            Operation::add_layer(&f.scene, layer),
+            Err(SceneError::InvalidColor { value }) if value == "blue"
+        ));
+        let mut walls = f.scene.layer(f.walls).unwrap().clone();
+        walls.color = "#12345".to_string();
+        assert!(matches!(Operation::edit_layer(&f.scene, walls), Err(SceneError::InvalidColor { .. })));
+    }
+
+    fn perform(scene: &mut Scene, operation: Operation) {
+        History::default().perform(scene, operation, None).unwrap();
+    }
+
+    #[test]
+    fn grouping_nests_the_new_group_in_the_shared_parent() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let (operation, group) = Operation::group(&scene, &[f.b], &[f.inner]).unwrap();
+        perform(&mut scene, operation);
+
+        assert_eq!(group.parent, Some(f.outer));
+        assert_eq!(scene.groups().last().unwrap().id, group.id);
+        assert_eq!(scene.get(f.b).unwrap().group, Some(group.id));
+        assert_eq!(scene.group(f.inner).unwrap().parent, Some(group.id));
+        let ids: Vec<Uuid> = scene.descendants(f.outer).unwrap().iter().map(|item| item.id).collect();
+        assert_eq!(ids, [f.a, f.b]);
+        assert_eq!(scene.problem(), None);
+    }
+
+    #[test]
+    fn grouping_members_of_different_groups_starts_a_top_level_group() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let (operation, group) = Operation::group(&scene, &[f.c], &[f.inner]).unwrap();
+        perform(&mut scene, operation);
+        assert_eq!(group.parent, None);
+        assert_eq!(scene.group(f.inner).unwrap().parent, Some(group.id));
+
+        // A group and one nested in it become siblings rather than a cycle
+        let mut scene = f.scene.clone();
+        let (operation, group) = Operation::group(&scene, &[], &[f.outer, f.inner]).unwrap();
+        perform(&mut scene, operation);
+        assert_eq!(group.parent, None);
+        assert_eq!(scene.group(f.outer).unwrap().parent, Some(group.id));
+        assert_eq!(scene.group(f.inner).unwrap().parent, Some(group.id));
+        assert_eq!(scene.problem(), None);
+        assert_eq!(scene.descendants(group.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn grouping_needs_existing_members() {
+        let f = fixture();
+        assert!(matches!(Operation::group(&f.scene, &[], &[]), Err(SceneError::EmptyGroup)));
+        let missing = Uuid::new_v4();
+        assert!(matches!(Operation::group(&f.scene, &[missing], &[]), Err(SceneError::NotFound { .. })));
+        assert!(matches!(Operation::group(&f.scene, &[], &[missing]), Err(SceneError::GroupNotFound { .. })));
+    }
+
+    #[test]
+    fn grouping_checks_the_layers_of_shapes_in_nested_groups() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let base = scene.layers()[0].id;
+        lock(&mut scene, base);
+        // `a` is on the locked layer two groups down from `outer`
+        match Operation::group(&scene, &[], &[f.outer]) {
+            Err(SceneError::LayerLocked { name }) => assert_eq!(name, "Layer 1"),
+            result => panic!("expected a locked layer, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn ungrouping_leaves_members_in_the_parent() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        perform(&mut scene, Operation::ungroup(&f.scene, f.inner).unwrap());
+        assert_eq!(scene.get(f.a).unwrap().group, Some(f.outer));
+        assert!(scene.group(f.inner).is_err());
+
+        let mut scene = f.scene.clone();
+        perform(&mut scene, Operation::ungroup(&f.scene, f.outer).unwrap());
+        assert_eq!(scene.get(f.b).unwrap().group, None);
+        assert_eq!(scene.group(f.inner).unwrap().parent, None);
+        assert_eq!(scene.get(f.a).unwrap().group, Some(f.inner));
+    }
+
+    #[test]
+    fn editing_a_group_edits_every_shape_nested_in_it() {
+        let f = fixture();
+        let mut scene = f.scene.clone();
+        let scaled = |shape: &Shape| Ok(shape.scaled(2.0));
+        perform(&mut scene, Operation::edit_group(&f.scene, EditKind::Transform, f.outer, scaled).unwrap());
+        for (id, scale) in [(f.a, 2.0), (f.b, 2.0), (f.c, 1.0)] {
+            let before = f.scene.get(id).unwrap().shape.bounding_box().unwrap();
+            let after = scene.get(id).unwrap().shape.bounding_box().unwrap();
+            assert_eq!(after.bottom_right.x, before.bottom_right.x * scale);
+        }
+
+        let empty = Group::new(None);
+        let id = empty.id;
+        scene.insert_group(0, empty);
+        let same = |shape: &Shape| Ok(shape.clone());
+        assert!(matches!(Operation::edit_group(&scene, EditKind::Move, id, same), Err(SceneError::EmptyGroup)));
+    }
+}
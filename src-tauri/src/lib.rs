@@ -1,56 +1,2705 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-use serde::Deserialize;
-use tauri::{generate_context, generate_handler};
+mod bezier;
+mod boolean;
+mod document;
+mod drawing;
+mod dxf;
+mod error;
+mod flatten;
+mod gis;
+mod history;
+mod pdf;
+mod precision;
+mod raster;
+mod scene;
+mod svg;
+mod svg_import;
+mod transform;
+mod units;
+mod validation;
 
-#[derive(Deserialize,Debug)]
+use std::f64::consts::PI;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use bezier::Path;
+use boolean::{BooleanOp, BooleanResult};
+use document::{Document, DocumentProperties};
+use drawing::{Drawing, DrawingOptions};
+use dxf::DxfImport;
+use error::{DocumentError, GeometryError, SceneError};
+use gis::{GeometryFormat, GeometryImport};
+use history::{EditKind, History, HistoryState, Operation, SceneChange, HISTORY_CHANGED, SCENE_CHANGED};
+use pdf::PdfOptions;
+use precision::Precision;
+use raster::RasterOptions;
+use scene::{Group, Layer, Scene, SceneShape, DEFAULT_LAYER_COLOR};
+use serde::{Deserialize, Serialize};
+use svg_import::SvgImport;
+use tauri::{generate_context, generate_handler, AppHandle, Emitter, State};
+use transform::{Transform, TransformStep};
+use units::{MeasurementUnit, UnitSettings};
+use uuid::Uuid;
+use validation::PolygonValidation;
+
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
 struct Point {
-    x: i32,
-    y: i32,
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    // `field` is this point's path in the command payload, e.g. `points[2]`
+    fn check_finite(&self, field: &str) -> Result<(), GeometryError> {
+        check_finite(self.x, &format!("{}.x", field))?;
+        check_finite(self.y, &format!("{}.y", field))
+    }
+
+    fn distance(&self, other: &Point) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+
+    fn scaled(&self, factor: f64) -> Point {
+        Point {
+            x: self.x * factor,
+            y: self.y * factor,
+        }
+    }
 }
 
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
 struct Rectangle {
     top_left: Point,
     bottom_right: Point,
 }
 
+// A rectangle turned `angle` degrees counter-clockwise (Y up) about its center, with `width`
+// along its own X axis
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+struct OrientedRectangle {
+    center: Point,
+    width: f64,
+    height: f64,
+    angle: f64,
+}
+
+#[derive(Deserialize,Serialize,Debug,Clone)]
+struct Circle {
+    center: Point,
+    radius: f64,
+}
+
+// Semi-axes `rx` along the ellipse's own X axis and `ry` along its Y axis, turned `angle`
+// degrees counter-clockwise (Y up) about the center
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+struct Ellipse {
+    center: Point,
+    rx: f64,
+    ry: f64,
+    angle: f64,
+}
+
+// How the ends of an arc are joined
+#[derive(Deserialize,Serialize,Debug,Clone,Copy,PartialEq,Eq,Default)]
+#[serde(rename_all = "snake_case")]
+enum ArcClosure {
+    // Just the curve, which has a length but no area
+    Open,
+    // A straight line between the ends, giving a circular segment
+    Chord,
+    // Lines from both ends to the center, giving a pie slice
+    #[default]
+    Sector,
+}
+
+// Part of a circle from `start_angle` sweeping `sweep_angle` degrees, counter-clockwise (Y up)
+// when positive
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+struct Arc {
+    center: Point,
+    radius: f64,
+    start_angle: f64,
+    sweep_angle: f64,
+    #[serde(default)]
+    closure: ArcClosure,
+}
+
+// Straight line from `start` to `end`
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+struct Segment {
+    start: Point,
+    end: Point,
+}
+
+// Open chain of straight segments through `points`, in order
+#[derive(Deserialize,Serialize,Debug,Clone)]
+struct Polyline {
+    points: Vec<Point>,
+}
+
+// `points` is the outer ring; `holes` are rings cut out of it. Neither repeats its first point.
+#[derive(Deserialize,Serialize,Debug,Clone)]
+struct Polygon {
+    points: Vec<Point>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    holes: Vec<Vec<Point>>,
+}
+
+// Several separate polygons measured as one shape, e.g. the rooms of a floor
+#[derive(Deserialize,Serialize,Debug,Clone)]
+struct MultiPolygon {
+    polygons: Vec<Polygon>,
+}
+
+// Mirrors `Shape` in src/types/shapes.ts, tagged with the frontend's `ShapeType`
+#[derive(Deserialize,Serialize,Debug,Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Shape {
+    Rectangle(Rectangle),
+    OrientedRectangle(OrientedRectangle),
+    Circle(Circle),
+    Ellipse(Ellipse),
+    Arc(Arc),
+    Segment(Segment),
+    Polyline(Polyline),
+    Path(Path),
+    Polygon(Polygon),
+    MultiPolygon(MultiPolygon),
+}
+
 impl Rectangle {
-    fn length(&self) -> i32 {
-        let Point { x: x1, y: _y1 } = self.top_left;
-        let Point { x: x2, y: _y2 } = self.bottom_right;
-        return x2 - x1;
+    // Returns the same rectangle with `top_left` at the minimum corner, whichever
+    // corners were supplied and whichever way the Y axis points
+    fn normalized(&self) -> Rectangle {
+        Rectangle {
+            top_left: Point {
+                x: self.top_left.x.min(self.bottom_right.x),
+                y: self.top_left.y.min(self.bottom_right.y),
+            },
+            bottom_right: Point {
+                x: self.top_left.x.max(self.bottom_right.x),
+                y: self.top_left.y.max(self.bottom_right.y),
+            },
+        }
+    }
+
+    fn length(&self) -> f64 {
+        (self.bottom_right.x - self.top_left.x).abs()
+    }
+
+    fn height(&self) -> f64 {
+        (self.bottom_right.y - self.top_left.y).abs()
+    }
+
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.top_left.check_finite("top_left")?;
+        self.bottom_right.check_finite("bottom_right")?;
+        let rect = self.normalized();
+        let length = precision.snap(rect.length());
+        let height = precision.snap(rect.height());
+        if !length.is_finite() {
+            return Err(GeometryError::Overflow { field: "length".to_string() });
+        }
+        if !height.is_finite() {
+            return Err(GeometryError::Overflow { field: "height".to_string() });
+        }
+        checked_area(length * height)
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * (self.length() + self.height())
+    }
+
+    fn centroid(&self) -> Point {
+        Point {
+            x: (self.top_left.x + self.bottom_right.x) / 2.0,
+            y: (self.top_left.y + self.bottom_right.y) / 2.0,
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.normalized()
+    }
+
+    // Smallest axis-aligned rectangle covering both; expects normalized rectangles
+    fn union(&self, other: &Rectangle) -> Rectangle {
+        Rectangle {
+            top_left: Point {
+                x: self.top_left.x.min(other.top_left.x),
+                y: self.top_left.y.min(other.top_left.y),
+            },
+            bottom_right: Point {
+                x: self.bottom_right.x.max(other.bottom_right.x),
+                y: self.bottom_right.y.max(other.bottom_right.y),
+            },
+        }
+    }
+}
+
+impl OrientedRectangle {
+    fn validate(&self) -> Result<(), GeometryError> {
+        self.center.check_finite("center")?;
+        for (field, value) in [("width", self.width), ("height", self.height)] {
+            check_finite(value, field)?;
+            if value < 0.0 {
+                return Err(GeometryError::NegativeExtent { field: field.to_string() });
+            }
+        }
+        check_finite(self.angle, "angle")
+    }
+
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.validate()?;
+        checked_area(precision.snap(self.width) * precision.snap(self.height))
     }
 
-    fn height(&self) -> i32 {
-        let Point { x: _x1, y: y1 } = self.top_left;
-        let Point { x: _x2, y: y2 } = self.bottom_right;
-        return y2 - y1;
+    fn perimeter(&self) -> f64 {
+        2.0 * (self.width + self.height)
+    }
+
+    // Unit vectors along the rectangle's own X and Y axes
+    fn axes(&self) -> (Point, Point) {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        (Point { x: cos, y: sin }, Point { x: -sin, y: cos })
+    }
+
+    // Counter-clockwise, starting at the corner that is bottom left before rotating
+    fn corners(&self) -> [Point; 4] {
+        let (u, v) = self.axes();
+        let (w, h) = (self.width / 2.0, self.height / 2.0);
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|(s, t)| Point {
+            x: self.center.x + s * u.x + t * v.x,
+            y: self.center.y + s * u.y + t * v.y,
+        })
+    }
+
+    fn outline(&self) -> Polygon {
+        Polygon::new(self.corners().to_vec())
+    }
+
+    // The rectangle traced by a four-vertex polygon without holes whose corners are right
+    // angles, in either winding. The angle is that of the first edge, folded into (-90, 90].
+    fn from_polygon(polygon: &Polygon, precision: &Precision) -> Option<OrientedRectangle> {
+        let p = &polygon.points;
+        if p.len() != 4 || !polygon.holes.is_empty() {
+            return None;
+        }
+        let edge = |i: usize| Point {
+            x: p[(i + 1) % 4].x - p[i].x,
+            y: p[(i + 1) % 4].y - p[i].y,
+        };
+        let (width, height) = (p[0].distance(&p[1]), p[1].distance(&p[2]));
+        if precision.is_zero(width) || precision.is_zero(height) {
+            return None;
+        }
+        // Right angles at every corner, compared relative to the edge lengths
+        let square = (0..4).all(|i| {
+            let (a, b) = (edge(i), edge((i + 1) % 4));
+            precision.is_zero((a.x * b.x + a.y * b.y) / (a.x.hypot(a.y) * b.x.hypot(b.y)))
+        });
+        let closes = precision.is_zero((p[2].distance(&p[3]) - width) / width)
+            && precision.is_zero((p[3].distance(&p[0]) - height) / height);
+        if !square || !closes {
+            return None;
+        }
+        let mut angle = edge(0).y.atan2(edge(0).x).to_degrees();
+        if angle > 90.0 {
+            angle -= 180.0;
+        } else if angle <= -90.0 {
+            angle += 180.0;
+        }
+        Some(OrientedRectangle {
+            center: Point {
+                x: (p[0].x + p[2].x) / 2.0,
+                y: (p[0].y + p[2].y) / 2.0,
+            },
+            width,
+            height,
+            angle,
+        })
+    }
+
+    // Inside or on the boundary
+    fn contains(&self, p: &Point) -> bool {
+        let (u, v) = self.axes();
+        let (dx, dy) = (p.x - self.center.x, p.y - self.center.y);
+        (dx * u.x + dy * u.y).abs() <= self.width / 2.0 && (dx * v.x + dy * v.y).abs() <= self.height / 2.0
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        ring_bounding_box(&self.corners())
+    }
+
+    fn scaled(&self, factor: f64) -> OrientedRectangle {
+        OrientedRectangle {
+            center: self.center.scaled(factor),
+            width: self.width * factor,
+            height: self.height * factor,
+            angle: self.angle,
+        }
     }
 }
 
+// Floating-point products saturate to infinity instead of wrapping; treat that as overflow
+fn checked_area(area: f64) -> Result<f64, GeometryError> {
+    if area.is_finite() {
+        Ok(area)
+    } else {
+        Err(GeometryError::Overflow { field: "area".to_string() })
+    }
+}
+
+fn check_finite(value: f64, field: &str) -> Result<(), GeometryError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(GeometryError::NonFinite { field: field.to_string() })
+    }
+}
+
+// `tolerance`, by default a quarter pixel. A zero, negative or non-finite tolerance would
+// flatten curves to a handful of segments or to tens of thousands, so it is rejected.
+fn flatten_tolerance(tolerance: Option<f64>) -> Result<f64, GeometryError> {
+    let tolerance = tolerance.unwrap_or(flatten::DEFAULT_TOLERANCE);
+    if tolerance.is_finite() && tolerance > 0.0 {
+        Ok(tolerance)
+    } else {
+        Err(GeometryError::OutOfRange {
+            field: "tolerance".to_string(),
+            min: 0.0,
+            max: f64::INFINITY,
+        })
+    }
+}
+
+impl Circle {
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.center.check_finite("center")?;
+        check_finite(self.radius, "radius")?;
+        let radius = precision.snap(self.radius);
+        if radius < 0.0 {
+            return Err(GeometryError::NegativeExtent { field: "radius".to_string() });
+        }
+        checked_area(std::f64::consts::PI * radius * radius)
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * std::f64::consts::PI * self.radius
+    }
+
+    fn centroid(&self) -> Point {
+        self.center
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle {
+            top_left: Point {
+                x: self.center.x - self.radius,
+                y: self.center.y - self.radius,
+            },
+            bottom_right: Point {
+                x: self.center.x + self.radius,
+                y: self.center.y + self.radius,
+            },
+        }
+    }
+}
+
+impl Ellipse {
+    fn validate(&self) -> Result<(), GeometryError> {
+        self.center.check_finite("center")?;
+        for (field, value) in [("rx", self.rx), ("ry", self.ry)] {
+            check_finite(value, field)?;
+            if value < 0.0 {
+                return Err(GeometryError::NegativeExtent { field: field.to_string() });
+            }
+        }
+        check_finite(self.angle, "angle")
+    }
+
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.validate()?;
+        checked_area(PI * precision.snap(self.rx) * precision.snap(self.ry))
+    }
+
+    // Ramanujan's second approximation, exact for circles and within 0.04% of the true
+    // length even for the flattest ellipses
+    fn perimeter(&self) -> f64 {
+        let (a, b) = (self.rx, self.ry);
+        if a + b == 0.0 {
+            return 0.0;
+        }
+        let h = ((a - b) / (a + b)).powi(2);
+        PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+    }
+
+    // Turns a point of the unrotated ellipse centered on the origin into place
+    fn place(&self, p: Point) -> Point {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        Point {
+            x: self.center.x + p.x * cos - p.y * sin,
+            y: self.center.y + p.x * sin + p.y * cos,
+        }
+    }
+
+    // Closed outline within `tolerance`, without repeating the first point
+    fn outline(&self, tolerance: f64) -> Vec<Point> {
+        flatten::ellipse(Point { x: 0.0, y: 0.0 }, self.rx, self.ry, tolerance)
+            .into_iter()
+            .map(|p| self.place(p))
+            .collect()
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (dx, dy) = (p.x - self.center.x, p.y - self.center.y);
+        let (x, y) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+        if self.rx == 0.0 || self.ry == 0.0 {
+            return false;
+        }
+        (x / self.rx).powi(2) + (y / self.ry).powi(2) <= 1.0
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let half_width = (self.rx * cos).hypot(self.ry * sin);
+        let half_height = (self.rx * sin).hypot(self.ry * cos);
+        Rectangle {
+            top_left: Point {
+                x: self.center.x - half_width,
+                y: self.center.y - half_height,
+            },
+            bottom_right: Point {
+                x: self.center.x + half_width,
+                y: self.center.y + half_height,
+            },
+        }
+    }
+
+    fn scaled(&self, factor: f64) -> Ellipse {
+        Ellipse {
+            center: self.center.scaled(factor),
+            rx: self.rx * factor,
+            ry: self.ry * factor,
+            angle: self.angle,
+        }
+    }
+}
+
+impl Arc {
+    fn validate(&self) -> Result<(), GeometryError> {
+        self.center.check_finite("center")?;
+        check_finite(self.radius, "radius")?;
+        if self.radius < 0.0 {
+            return Err(GeometryError::NegativeExtent { field: "radius".to_string() });
+        }
+        check_finite(self.start_angle, "start_angle")?;
+        check_finite(self.sweep_angle, "sweep_angle")?;
+        if self.sweep_angle.abs() > 360.0 {
+            return Err(GeometryError::OutOfRange {
+                field: "sweep_angle".to_string(),
+                min: -360.0,
+                max: 360.0,
+            });
+        }
+        Ok(())
+    }
+
+    // Absolute sweep in radians
+    fn span(&self) -> f64 {
+        self.sweep_angle.abs().to_radians()
+    }
+
+    fn is_full_circle(&self) -> bool {
+        self.sweep_angle.abs() >= 360.0
+    }
+
+    fn point_at(&self, degrees: f64) -> Point {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Point {
+            x: self.center.x + self.radius * cos,
+            y: self.center.y + self.radius * sin,
+        }
+    }
+
+    fn start(&self) -> Point {
+        self.point_at(self.start_angle)
+    }
+
+    fn end(&self) -> Point {
+        self.point_at(self.start_angle + self.sweep_angle)
+    }
+
+    // Sector: r²θ/2; chord: the sector minus the triangle r²·sin(θ)/2
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.validate()?;
+        let (radius, span) = (precision.snap(self.radius), self.span());
+        match self.closure {
+            ArcClosure::Open => Err(GeometryError::OpenShape { field: "closure".to_string() }),
+            ArcClosure::Sector => checked_area(radius * radius * span / 2.0),
+            ArcClosure::Chord => checked_area(radius * radius * (span - span.sin()) / 2.0),
+        }
+    }
+
+    // Length along the curve only
+    fn length(&self) -> f64 {
+        self.radius * self.span()
+    }
+
+    // The curve plus whatever closes it; a full circle needs no closing lines
+    fn perimeter(&self) -> f64 {
+        let closing = match self.closure {
+            _ if self.is_full_circle() => 0.0,
+            ArcClosure::Open => 0.0,
+            ArcClosure::Chord => self.start().distance(&self.end()),
+            ArcClosure::Sector => 2.0 * self.radius,
+        };
+        self.length() + closing
+    }
+
+    // Of the curve for open arcs and of the enclosed area otherwise; it lies on the bisector
+    fn centroid(&self) -> Point {
+        let half = self.span() / 2.0;
+        if half == 0.0 {
+            return self.start();
+        }
+        let r = self.radius;
+        let distance = match self.closure {
+            ArcClosure::Open => r * half.sin() / half,
+            ArcClosure::Sector => 2.0 * r * half.sin() / (3.0 * half),
+            ArcClosure::Chord => 4.0 * r * half.sin().powi(3) / (3.0 * (2.0 * half - (2.0 * half).sin())),
+        };
+        let (sin, cos) = (self.start_angle + self.sweep_angle / 2.0).to_radians().sin_cos();
+        Point {
+            x: self.center.x + distance * cos,
+            y: self.center.y + distance * sin,
+        }
+    }
+
+    // Points along the curve within `tolerance`, both ends included
+    fn points(&self, tolerance: f64) -> Vec<Point> {
+        flatten::ellipse_arc(
+            self.center,
+            self.radius,
+            self.radius,
+            self.start_angle.to_radians(),
+            self.sweep_angle.to_radians(),
+            tolerance,
+        )
+    }
+
+    // Closed outline of a chord or sector arc, without repeating the first point
+    fn outline(&self, tolerance: f64) -> Vec<Point> {
+        let mut points = self.points(tolerance);
+        if self.is_full_circle() {
+            points.pop();
+        } else if self.closure == ArcClosure::Sector {
+            points.push(self.center);
+        }
+        points
+    }
+
+    // Whether the direction `degrees` from the center falls within the sweep
+    fn covers(&self, degrees: f64) -> bool {
+        let offset = (degrees - self.start_angle) * self.sweep_angle.signum();
+        offset.rem_euclid(360.0) <= self.sweep_angle.abs() || self.is_full_circle()
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        if self.center.distance(p) > self.radius {
+            return false;
+        }
+        match self.closure {
+            ArcClosure::Open => false,
+            _ if self.is_full_circle() => true,
+            ArcClosure::Sector => self.covers((p.y - self.center.y).atan2(p.x - self.center.x).to_degrees()),
+            // The segment lies to the right of the chord from start to end, for either direction of sweep
+            ArcClosure::Chord => cross(&self.start(), &self.end(), p) * self.sweep_angle.signum() <= 0.0,
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        let mut points = vec![self.start(), self.end()];
+        points.extend([0.0, 90.0, 180.0, 270.0].into_iter().filter(|&angle| self.covers(angle)).map(|angle| self.point_at(angle)));
+        if self.closure == ArcClosure::Sector {
+            points.push(self.center);
+        }
+        ring_bounding_box(&points)
+    }
+
+    fn scaled(&self, factor: f64) -> Arc {
+        Arc {
+            center: self.center.scaled(factor),
+            radius: self.radius * factor,
+            ..*self
+        }
+    }
+}
+
+impl Polyline {
+    fn validate(&self) -> Result<(), GeometryError> {
+        if self.points.len() < 2 {
+            return Err(GeometryError::TooFewPoints {
+                field: "points".to_string(),
+                required: 2,
+                actual: self.points.len(),
+            });
+        }
+        for (i, point) in self.points.iter().enumerate() {
+            point.check_finite(&format!("points[{}]", i))?;
+        }
+        Ok(())
+    }
+
+    fn length(&self) -> f64 {
+        self.points.windows(2).map(|pair| pair[0].distance(&pair[1])).sum()
+    }
+
+    // Midpoints of the segments weighted by their lengths; the first point for zero length
+    fn centroid(&self) -> Point {
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for pair in self.points.windows(2) {
+            let length = pair[0].distance(&pair[1]);
+            x += (pair[0].x + pair[1].x) / 2.0 * length;
+            y += (pair[0].y + pair[1].y) / 2.0 * length;
+            total += length;
+        }
+        if total == 0.0 {
+            return self.points[0];
+        }
+        Point { x: x / total, y: y / total }
+    }
+}
+
+// Edges of a closed ring as (start, end) pairs, including the closing edge back to the first vertex
+fn ring_edges(ring: &[Point]) -> impl Iterator<Item = (&Point, &Point)> {
+    let n = ring.len();
+    (0..n).map(move |i| (&ring[i], &ring[(i + 1) % n]))
+}
+
+// Shoelace formula; positive for counter-clockwise vertex order (Y up)
+fn ring_signed_area(ring: &[Point]) -> f64 {
+    // Work relative to the first vertex so far-from-origin rings keep their precision
+    let origin = ring[0];
+    let twice_area: f64 = ring_edges(ring)
+        .map(|(p, q)| (p.x - origin.x) * (q.y - origin.y) - (q.x - origin.x) * (p.y - origin.y))
+        .sum();
+    twice_area / 2.0
+}
+
+fn ring_perimeter(ring: &[Point]) -> f64 {
+    ring_edges(ring).map(|(p, q)| p.distance(q)).sum()
+}
+
+// Area-weighted centroid of a ring with non-zero `signed_area`
+fn ring_centroid(ring: &[Point], signed_area: f64) -> Point {
+    let origin = ring[0];
+    let (mut cx, mut cy) = (0.0, 0.0);
+    for (p, q) in ring_edges(ring) {
+        let (px, py) = (p.x - origin.x, p.y - origin.y);
+        let (qx, qy) = (q.x - origin.x, q.y - origin.y);
+        let cross = px * qy - qx * py;
+        cx += (px + qx) * cross;
+        cy += (py + qy) * cross;
+    }
+    Point {
+        x: origin.x + cx / (6.0 * signed_area),
+        y: origin.y + cy / (6.0 * signed_area),
+    }
+}
+
+// True when any two non-adjacent edges of the ring touch or cross
+fn ring_self_intersecting(ring: &[Point], precision: &Precision) -> bool {
+    let n = ring.len();
+    let edges: Vec<_> = ring_edges(ring).collect();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            // Adjacent edges always share a vertex
+            if j == i + 1 || (i == 0 && j == n - 1) {
+                continue;
+            }
+            let (a, b) = edges[i];
+            let (c, d) = edges[j];
+            if segments_intersect(a, b, c, d, precision) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// True when any edge of one ring touches or crosses an edge of the other
+fn rings_intersect(first: &[Point], second: &[Point], precision: &Precision) -> bool {
+    ring_edges(first).any(|(a, b)| ring_edges(second).any(|(c, d)| segments_intersect(a, b, c, d, precision)))
+}
+
+// Even-odd ray casting; points on the boundary may land on either side
+fn ring_contains(ring: &[Point], p: &Point) -> bool {
+    let mut inside = false;
+    for (a, b) in ring_edges(ring) {
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Corner-to-corner box of a non-empty ring
+fn ring_bounding_box(ring: &[Point]) -> Rectangle {
+    let mut bbox = Rectangle {
+        top_left: ring[0],
+        bottom_right: ring[0],
+    };
+    for p in &ring[1..] {
+        bbox.top_left.x = bbox.top_left.x.min(p.x);
+        bbox.top_left.y = bbox.top_left.y.min(p.y);
+        bbox.bottom_right.x = bbox.bottom_right.x.max(p.x);
+        bbox.bottom_right.y = bbox.bottom_right.y.max(p.y);
+    }
+    bbox
+}
+
+// Groups closed rings into polygons with holes. A ring directly inside an outer ring is one of
+// its holes under the even-odd rule; under the nonzero rule only if it is wound the other way.
+// Rings inside holes become polygons of their own.
+fn nest_rings(rings: Vec<Vec<Point>>, even_odd: bool) -> Vec<Polygon> {
+    let n = rings.len();
+    let areas: Vec<f64> = rings.iter().map(|ring| ring_signed_area(ring)).collect();
+    // Innermost ring containing each ring, judged by its first vertex
+    let parents: Vec<Option<usize>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .filter(|&j| j != i && areas[j].abs() > areas[i].abs() && ring_contains(&rings[j], &rings[i][0]))
+                .min_by(|&a, &b| areas[a].abs().total_cmp(&areas[b].abs()))
+        })
+        .collect();
+    // Larger rings first, so each container is classified before what it contains
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| areas[b].abs().total_cmp(&areas[a].abs()));
+    let mut is_hole = vec![false; n];
+    for &i in &order {
+        is_hole[i] = match parents[i] {
+            Some(parent) if !is_hole[parent] => even_odd || areas[i].signum() != areas[parent].signum(),
+            _ => false,
+        };
+    }
+
+    let mut polygon_of = vec![usize::MAX; n];
+    let mut polygons = Vec::new();
+    let mut rings: Vec<Option<Vec<Point>>> = rings.into_iter().map(Some).collect();
+    for i in (0..n).filter(|&i| !is_hole[i]) {
+        polygon_of[i] = polygons.len();
+        polygons.push(Polygon::new(rings[i].take().unwrap_or_default()));
+    }
+    for i in (0..n).filter(|&i| is_hole[i]) {
+        if let (Some(parent), Some(ring)) = (parents[i], rings[i].take()) {
+            polygons[polygon_of[parent]].holes.push(ring);
+        }
+    }
+    polygons
+}
+
+impl Polygon {
+    // A polygon without holes
+    fn new(points: Vec<Point>) -> Polygon {
+        Polygon {
+            points,
+            holes: Vec::new(),
+        }
+    }
+
+    // Outer ring first, then the holes
+    fn rings(&self) -> impl Iterator<Item = &Vec<Point>> {
+        std::iter::once(&self.points).chain(&self.holes)
+    }
+
+    // Signed area of the outer ring; positive for counter-clockwise vertex order (Y up)
+    fn signed_area(&self) -> f64 {
+        ring_signed_area(&self.points)
+    }
+
+    // Only simple polygons with a non-zero area, and holes inside them, have a meaningful area
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        let area = self.unchecked_area()?;
+        if self.is_self_intersecting(precision) {
+            return Err(GeometryError::SelfIntersecting { field: "points".to_string() });
+        }
+        if let Some(i) = self.misplaced_hole() {
+            return Err(GeometryError::HoleOutside { field: format!("holes[{}]", i) });
+        }
+        if precision.is_zero(area) {
+            return Err(GeometryError::DegeneratePolygon { field: "points".to_string() });
+        }
+        Ok(area)
+    }
+
+    // Shoelace area of the outer ring minus that of the holes, without rejecting crossing
+    // edges, misplaced holes or zero area
+    fn unchecked_area(&self) -> Result<f64, GeometryError> {
+        self.validate()?;
+        let holes: f64 = self.holes.iter().map(|hole| ring_signed_area(hole).abs()).sum();
+        checked_area(self.signed_area().abs() - holes)
+    }
+
+    fn validate(&self) -> Result<(), GeometryError> {
+        for (i, ring) in self.rings().enumerate() {
+            let field = if i == 0 { "points".to_string() } else { format!("holes[{}]", i - 1) };
+            if ring.len() < 3 {
+                return Err(GeometryError::TooFewPoints {
+                    field,
+                    required: 3,
+                    actual: ring.len(),
+                });
+            }
+            for (j, point) in ring.iter().enumerate() {
+                let path = if i == 0 { format!("points[{}]", j) } else { format!("{}[{}]", field, j) };
+                point.check_finite(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Index of the first hole not inside the outer ring or overlapping another hole
+    fn misplaced_hole(&self) -> Option<usize> {
+        (0..self.holes.len()).find(|&i| {
+            let hole = &self.holes[i];
+            !hole.iter().all(|p| ring_contains(&self.points, p))
+                || self.holes.iter().enumerate().any(|(j, other)| {
+                    j != i && (hole.iter().any(|p| ring_contains(other, p)) || other.iter().any(|p| ring_contains(hole, p)))
+                })
+        })
+    }
+
+    // Whether `p` is inside the outer ring and not inside a hole
+    fn contains(&self, p: &Point) -> bool {
+        ring_contains(&self.points, p) && !self.holes.iter().any(|hole| ring_contains(hole, p))
+    }
+
+    // Outer ring and hole outlines together
+    fn perimeter(&self) -> f64 {
+        self.rings().map(|ring| ring_perimeter(ring)).sum()
+    }
+
+    fn centroid(&self, precision: &Precision) -> Point {
+        let outer = self.signed_area().abs();
+        let holes: f64 = self.holes.iter().map(|hole| ring_signed_area(hole).abs()).sum();
+        if precision.is_zero(outer - holes) || precision.is_zero(outer) {
+            // Degenerate polygon: fall back to the mean of its outer vertices
+            let n = self.points.len() as f64;
+            return Point {
+                x: self.points.iter().map(|p| p.x).sum::<f64>() / n,
+                y: self.points.iter().map(|p| p.y).sum::<f64>() / n,
+            };
+        }
+        // Holes contribute negative area to the weighted mean
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for (i, ring) in self.rings().enumerate() {
+            let signed_area = ring_signed_area(ring);
+            if precision.is_zero(signed_area) {
+                continue;
+            }
+            let weight = if i == 0 { signed_area.abs() } else { -signed_area.abs() };
+            let c = ring_centroid(ring, signed_area);
+            x += c.x * weight;
+            y += c.y * weight;
+            total += weight;
+        }
+        Point { x: x / total, y: y / total }
+    }
+
+    // Holes lie inside the outer ring, so it alone determines the box
+    fn bounding_box(&self) -> Rectangle {
+        ring_bounding_box(&self.points)
+    }
+
+    // True when any ring crosses itself or any two rings touch or cross
+    fn is_self_intersecting(&self, precision: &Precision) -> bool {
+        let rings: Vec<_> = self.rings().collect();
+        rings.iter().any(|ring| ring_self_intersecting(ring, precision))
+            || (0..rings.len()).any(|i| ((i + 1)..rings.len()).any(|j| rings_intersect(rings[i], rings[j], precision)))
+    }
+
+    // A simple polygon without holes is convex when every turn goes the same way
+    fn is_convex(&self, precision: &Precision) -> bool {
+        if !self.holes.is_empty() || self.is_self_intersecting(precision) {
+            return false;
+        }
+        let n = self.points.len();
+        let mut turn_sign = 0.0;
+        for i in 0..n {
+            let turn = cross(&self.points[i], &self.points[(i + 1) % n], &self.points[(i + 2) % n]);
+            if precision.is_zero(turn) {
+                continue;
+            }
+            if turn_sign == 0.0 {
+                turn_sign = turn.signum();
+            } else if turn.signum() != turn_sign {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn scaled(&self, factor: f64) -> Polygon {
+        let scale = |ring: &Vec<Point>| ring.iter().map(|p| p.scaled(factor)).collect();
+        Polygon {
+            points: scale(&self.points),
+            holes: self.holes.iter().map(scale).collect(),
+        }
+    }
+}
+
+impl MultiPolygon {
+    fn validate(&self) -> Result<(), GeometryError> {
+        if self.polygons.is_empty() {
+            return Err(GeometryError::DegeneratePolygon { field: "polygons".to_string() });
+        }
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            polygon.validate().map_err(|err| err.within(&format!("polygons[{}]", i)))?;
+        }
+        Ok(())
+    }
+
+    // Member polygons must each be valid and must not overlap, though one may sit in another's hole
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        self.validate()?;
+        let mut area = 0.0;
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            area += polygon.area(precision).map_err(|err| err.within(&format!("polygons[{}]", i)))?;
+        }
+        if self.has_overlaps(precision) {
+            return Err(GeometryError::SelfIntersecting { field: "polygons".to_string() });
+        }
+        checked_area(area)
+    }
+
+    fn unchecked_area(&self) -> Result<f64, GeometryError> {
+        self.validate()?;
+        let mut area = 0.0;
+        for polygon in &self.polygons {
+            area += polygon.unchecked_area()?;
+        }
+        checked_area(area)
+    }
+
+    fn has_overlaps(&self, precision: &Precision) -> bool {
+        let polygons = &self.polygons;
+        (0..polygons.len()).any(|i| {
+            ((i + 1)..polygons.len()).any(|j| {
+                let (a, b) = (&polygons[i], &polygons[j]);
+                a.rings().any(|first| b.rings().any(|second| rings_intersect(first, second, precision)))
+                    || b.points.iter().any(|p| a.contains(p))
+                    || a.points.iter().any(|p| b.contains(p))
+            })
+        })
+    }
+
+    fn perimeter(&self) -> f64 {
+        self.polygons.iter().map(Polygon::perimeter).sum()
+    }
+
+    // Mean of the member centroids weighted by their areas
+    fn centroid(&self, precision: &Precision) -> Point {
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for polygon in &self.polygons {
+            let area = polygon.unchecked_area().unwrap_or(0.0);
+            let c = polygon.centroid(precision);
+            x += c.x * area;
+            y += c.y * area;
+            total += area;
+        }
+        if precision.is_zero(total) {
+            return self.polygons[0].centroid(precision);
+        }
+        Point { x: x / total, y: y / total }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.polygons
+            .iter()
+            .map(|polygon| polygon.bounding_box())
+            .reduce(|a, b| a.union(&b))
+            .expect("validated multi-polygons have members")
+    }
+
+    fn is_self_intersecting(&self, precision: &Precision) -> bool {
+        self.polygons.iter().any(|polygon| polygon.is_self_intersecting(precision)) || self.has_overlaps(precision)
+    }
+}
+
+// Z component of (b - a) x (c - a); positive when a -> b -> c turns counter-clockwise
+fn cross(a: &Point, b: &Point, c: &Point) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Whether segments ab and cd share at least one point, collinear overlaps included
+fn segments_intersect(a: &Point, b: &Point, c: &Point, d: &Point, precision: &Precision) -> bool {
+    let d1 = precision.snap(cross(c, d, a));
+    let d2 = precision.snap(cross(c, d, b));
+    let d3 = precision.snap(cross(a, b, c));
+    let d4 = precision.snap(cross(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    let on_segment = |p: &Point, q: &Point, r: &Point| {
+        r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+    };
+    (d1 == 0.0 && on_segment(c, d, a))
+        || (d2 == 0.0 && on_segment(c, d, b))
+        || (d3 == 0.0 && on_segment(a, b, c))
+        || (d4 == 0.0 && on_segment(a, b, d))
+}
+
+// A point shared by segments ab and cd: the crossing point, or for touching and collinear
+// overlapping segments the first endpoint lying on the other segment
+fn segment_intersection(a: &Point, b: &Point, c: &Point, d: &Point, precision: &Precision) -> Option<Point> {
+    let d1 = precision.snap(cross(c, d, a));
+    let d2 = precision.snap(cross(c, d, b));
+    let d3 = precision.snap(cross(a, b, c));
+    let d4 = precision.snap(cross(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        let t = d1 / (d1 - d2);
+        return Some(Point {
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+        });
+    }
+    let on_segment = |p: &Point, q: &Point, r: &Point| {
+        r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+    };
+    [(d1, c, d, a), (d2, c, d, b), (d3, a, b, c), (d4, a, b, d)]
+        .into_iter()
+        .find(|&(side, p, q, r)| side == 0.0 && on_segment(p, q, r))
+        .map(|(_, _, _, r)| *r)
+}
+
+impl Shape {
+    // Scales every coordinate about the origin, e.g. to change measurement units
+    fn scaled(&self, factor: f64) -> Shape {
+        match self {
+            Shape::Rectangle(rect) => Shape::Rectangle(Rectangle {
+                top_left: rect.top_left.scaled(factor),
+                bottom_right: rect.bottom_right.scaled(factor),
+            }),
+            Shape::OrientedRectangle(rect) => Shape::OrientedRectangle(rect.scaled(factor)),
+            Shape::Ellipse(ellipse) => Shape::Ellipse(ellipse.scaled(factor)),
+            Shape::Arc(arc) => Shape::Arc(arc.scaled(factor)),
+            Shape::Segment(segment) => Shape::Segment(Segment {
+                start: segment.start.scaled(factor),
+                end: segment.end.scaled(factor),
+            }),
+            Shape::Polyline(polyline) => Shape::Polyline(Polyline {
+                points: polyline.points.iter().map(|p| p.scaled(factor)).collect(),
+            }),
+            Shape::Path(path) => Shape::Path(path.map(|p| p.scaled(factor))),
+            Shape::Circle(circle) => Shape::Circle(Circle {
+                center: circle.center.scaled(factor),
+                radius: circle.radius * factor,
+            }),
+            Shape::Polygon(polygon) => Shape::Polygon(polygon.scaled(factor)),
+            Shape::MultiPolygon(multi) => Shape::MultiPolygon(MultiPolygon {
+                polygons: multi.polygons.iter().map(|polygon| polygon.scaled(factor)).collect(),
+            }),
+        }
+    }
+
+    // Hit-testing: whether `p` is inside the shape. Points on the edges of rectangles and circles
+    // count as inside; on polygon edges they may land on either side. Open shapes enclose nothing.
+    fn contains(&self, p: &Point) -> bool {
+        match self {
+            Shape::Rectangle(rect) => {
+                let rect = rect.normalized();
+                (rect.top_left.x..=rect.bottom_right.x).contains(&p.x)
+                    && (rect.top_left.y..=rect.bottom_right.y).contains(&p.y)
+            }
+            Shape::OrientedRectangle(rect) => rect.contains(p),
+            Shape::Circle(circle) => circle.center.distance(p) <= circle.radius.abs(),
+            Shape::Ellipse(ellipse) => ellipse.contains(p),
+            Shape::Arc(arc) => arc.contains(p),
+            Shape::Segment(_) | Shape::Polyline(_) => false,
+            Shape::Path(path) => path.contains(p),
+            Shape::Polygon(polygon) => polygon.points.len() >= 3 && polygon.contains(p),
+            Shape::MultiPolygon(multi) => multi
+                .polygons
+                .iter()
+                .any(|polygon| polygon.points.len() >= 3 && polygon.contains(p)),
+        }
+    }
+
+    // `None` for a polygon that has no points yet
+    fn bounding_box(&self) -> Option<Rectangle> {
+        match self {
+            Shape::Rectangle(rect) => Some(rect.bounding_box()),
+            Shape::OrientedRectangle(rect) => Some(rect.bounding_box()),
+            Shape::Circle(circle) => Some(circle.bounding_box()),
+            Shape::Ellipse(ellipse) => Some(ellipse.bounding_box()),
+            Shape::Arc(arc) => Some(arc.bounding_box()),
+            Shape::Segment(segment) => Some(ring_bounding_box(&[segment.start, segment.end])),
+            Shape::Polyline(polyline) if polyline.points.is_empty() => None,
+            Shape::Polyline(polyline) => Some(ring_bounding_box(&polyline.points)),
+            Shape::Path(path) => Some(path.bounding_box()),
+            Shape::Polygon(polygon) if polygon.points.is_empty() => None,
+            Shape::Polygon(polygon) => Some(polygon.bounding_box()),
+            Shape::MultiPolygon(multi) => multi
+                .polygons
+                .iter()
+                .filter(|polygon| !polygon.points.is_empty())
+                .map(|polygon| polygon.bounding_box())
+                .reduce(|a, b| a.union(&b)),
+        }
+    }
+
+    fn area(&self, precision: &Precision) -> Result<f64, GeometryError> {
+        let area = match self {
+            Shape::Rectangle(rect) => rect.area(precision)?,
+            Shape::OrientedRectangle(rect) => rect.area(precision)?,
+            Shape::Circle(circle) => circle.area(precision)?,
+            Shape::Ellipse(ellipse) => ellipse.area(precision)?,
+            Shape::Arc(arc) => arc.area(precision)?,
+            Shape::Segment(_) | Shape::Polyline(_) => {
+                self.vertices()?;
+                return Err(GeometryError::OpenShape { field: "type".to_string() });
+            }
+            Shape::Path(path) => path.area()?,
+            Shape::Polygon(polygon) => polygon.area(precision)?,
+            Shape::MultiPolygon(multi) => multi.area(precision)?,
+        };
+        Ok(precision.round(area))
+    }
+
+    // Unlike `area`, reports self-intersecting and zero-area polygons instead of rejecting them,
+    // and a zero area for open shapes, whose perimeter is their length
+    fn measure(&self, precision: &Precision, unit: MeasurementUnit) -> Result<ShapeMetrics, GeometryError> {
+        let area = match self {
+            Shape::Arc(arc) if arc.closure == ArcClosure::Open => {
+                arc.validate()?;
+                0.0
+            }
+            Shape::Segment(_) | Shape::Polyline(_) => {
+                self.vertices()?;
+                0.0
+            }
+            Shape::Path(path) if !path.closed => {
+                path.validate()?;
+                0.0
+            }
+            Shape::Polygon(polygon) => precision.round(polygon.unchecked_area()?),
+            Shape::MultiPolygon(multi) => precision.round(multi.unchecked_area()?),
+            _ => self.area(precision)?,
+        };
+        let (perimeter, centroid, bbox) = match self {
+            Shape::Rectangle(rect) => (rect.perimeter(), rect.centroid(), rect.bounding_box()),
+            Shape::OrientedRectangle(rect) => (rect.perimeter(), rect.center, rect.bounding_box()),
+            Shape::Circle(circle) => (circle.perimeter(), circle.centroid(), circle.bounding_box()),
+            Shape::Ellipse(ellipse) => (ellipse.perimeter(), ellipse.center, ellipse.bounding_box()),
+            Shape::Arc(arc) => (arc.perimeter(), arc.centroid(), arc.bounding_box()),
+            Shape::Segment(segment) => {
+                let polyline = Polyline { points: vec![segment.start, segment.end] };
+                (polyline.length(), polyline.centroid(), ring_bounding_box(&polyline.points))
+            }
+            Shape::Polyline(polyline) => (polyline.length(), polyline.centroid(), ring_bounding_box(&polyline.points)),
+            Shape::Path(path) => (path.length(), path.centroid(), path.bounding_box()),
+            Shape::Polygon(polygon) => (
+                polygon.perimeter(),
+                polygon.centroid(precision),
+                polygon.bounding_box(),
+            ),
+            Shape::MultiPolygon(multi) => (multi.perimeter(), multi.centroid(precision), multi.bounding_box()),
+        };
+        let (convex, self_intersecting) = match self {
+            Shape::Polygon(polygon) => (
+                Some(polygon.is_convex(precision)),
+                Some(polygon.is_self_intersecting(precision)),
+            ),
+            Shape::MultiPolygon(multi) => (Some(false), Some(multi.is_self_intersecting(precision))),
+            _ => (None, None),
+        };
+        let round_point = |p: Point| Point {
+            x: precision.round(p.x),
+            y: precision.round(p.y),
+        };
+        Ok(ShapeMetrics {
+            area,
+            perimeter: precision.round(perimeter),
+            centroid: round_point(centroid),
+            bbox: Rectangle {
+                top_left: round_point(bbox.top_left),
+                bottom_right: round_point(bbox.bottom_right),
+            },
+            convex,
+            self_intersecting,
+            unit,
+            area_unit: unit.squared_symbol(),
+        })
+    }
+}
+
+impl Shape {
+    // Vertices of a straight-edged shape and whether the last joins back to the first.
+    // Polygons contribute their outer ring.
+    fn vertices(&self) -> Result<(Vec<Point>, bool), GeometryError> {
+        match self {
+            Shape::Segment(segment) => {
+                segment.start.check_finite("start")?;
+                segment.end.check_finite("end")?;
+                Ok((vec![segment.start, segment.end], false))
+            }
+            Shape::Polyline(polyline) => {
+                polyline.validate()?;
+                Ok((polyline.points.clone(), false))
+            }
+            Shape::Polygon(polygon) => {
+                polygon.validate()?;
+                Ok((polygon.points.clone(), true))
+            }
+            Shape::Rectangle(rect) => {
+                rect.area(&Precision::default())?;
+                let (a, b) = (rect.top_left, rect.bottom_right);
+                Ok((vec![a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }], true))
+            }
+            Shape::OrientedRectangle(rect) => {
+                rect.validate()?;
+                Ok((rect.corners().to_vec(), true))
+            }
+            _ => Err(GeometryError::NotStraight { field: "type".to_string() }),
+        }
+    }
+
+    fn measure_path(&self, precision: &Precision, unit: MeasurementUnit) -> Result<PathMetrics, GeometryError> {
+        let (points, closed) = self.vertices()?;
+        let count = if closed { points.len() } else { points.len() - 1 };
+        let mut segments: Vec<SegmentMetrics> = Vec::with_capacity(count);
+        let mut previous: Option<f64> = None;
+        for from in 0..count {
+            let (a, b) = (points[from], points[(from + 1) % points.len()]);
+            let angle = (b.y - a.y).atan2(b.x - a.x).to_degrees();
+            // Signed change of direction, folded into (-180, 180]
+            let turn = previous.map(|previous| {
+                let turn = (angle - previous).rem_euclid(360.0);
+                if turn > 180.0 { turn - 360.0 } else { turn }
+            });
+            previous = Some(angle);
+            segments.push(SegmentMetrics {
+                from,
+                length: precision.round(a.distance(&b)),
+                angle: precision.round(angle),
+                turn: turn.map(|turn| precision.round(turn)),
+            });
+        }
+        let length: f64 = (0..count).map(|i| points[i].distance(&points[(i + 1) % points.len()])).sum();
+        Ok(PathMetrics {
+            length: precision.round(length),
+            segments,
+            closed,
+            unit,
+        })
+    }
+}
+
+#[derive(Serialize,Debug)]
+struct SegmentMetrics {
+    // Index of the vertex the segment starts at
+    from: usize,
+    length: f64,
+    // Direction in degrees counter-clockwise from the X axis (Y up), in (-180, 180]
+    angle: f64,
+    // Change of direction from the previous segment, positive when turning left
+    turn: Option<f64>,
+}
+
+#[derive(Serialize,Debug)]
+struct PathMetrics {
+    length: f64,
+    segments: Vec<SegmentMetrics>,
+    // Whether the last segment returns to the first vertex
+    closed: bool,
+    unit: MeasurementUnit,
+}
+
+#[derive(Serialize,Debug)]
+struct ShapeMetrics {
+    area: f64,
+    perimeter: f64,
+    centroid: Point,
+    // Axis-aligned, with `top_left` at the minimum corner
+    bbox: Rectangle,
+    // Only reported for polygons; multi-polygons are never convex
+    convex: Option<bool>,
+    self_intersecting: Option<bool>,
+    // Lengths are in this unit, the area in its square
+    unit: MeasurementUnit,
+    area_unit: String,
+}
+
+#[derive(Serialize,Debug)]
+struct GroupMetrics {
+    // Area covered by the group, counting overlapping shapes once
+    union_area: f64,
+    // Areas of the shapes added up, counting overlaps once per shape
+    sum_area: f64,
+    // Of the covered area, or of the shapes' outlines when none encloses an area
+    centroid: Point,
+    bbox: Rectangle,
+    // Shapes in the group and its nested groups
+    shapes: usize,
+    unit: MeasurementUnit,
+    area_unit: String,
+}
+
+// Re-expresses `target`, whose coordinates are in `from_unit` (internal pixels by default),
+// in `unit` (the user's display unit by default)
+fn convert_shape(
+    target: Shape,
+    from_unit: Option<MeasurementUnit>,
+    unit: Option<MeasurementUnit>,
+    settings: &UnitSettings,
+) -> Result<(Shape, MeasurementUnit), GeometryError> {
+    settings.validate()?;
+    let from_unit = from_unit.unwrap_or(MeasurementUnit::Pixel);
+    let unit = unit.unwrap_or(settings.unit);
+    if from_unit == unit {
+        return Ok((target, unit));
+    }
+    Ok((target.scaled(settings.length_factor(from_unit, unit)), unit))
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
 #[tauri::command(rename_all = "snake_case")]
-fn calc_area(target: Rectangle) -> Result<u32, String> {
-    if target.length() < 0 {
-        return Err("Length cannot be negative".to_string());
+fn calc_area(
+    target: Shape,
+    precision: Option<Precision>,
+    from_unit: Option<MeasurementUnit>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<f64, GeometryError> {
+    let settings = *settings.lock().unwrap();
+    let (target, _) = convert_shape(target, from_unit, unit, &settings)?;
+    target.area(&precision.unwrap_or_default())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+fn measure_shape(
+    target: Shape,
+    precision: Option<Precision>,
+    from_unit: Option<MeasurementUnit>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<ShapeMetrics, GeometryError> {
+    let settings = *settings.lock().unwrap();
+    let (target, unit) = convert_shape(target, from_unit, unit, &settings)?;
+    target.measure(&precision.unwrap_or_default(), unit)
+}
+
+// Total and per-segment lengths and directions of a segment, polyline, polygon outline or
+// rectangle, in `unit`
+#[tauri::command(rename_all = "snake_case")]
+fn measure_path(
+    target: Shape,
+    precision: Option<Precision>,
+    from_unit: Option<MeasurementUnit>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<PathMetrics, GeometryError> {
+    let settings = *settings.lock().unwrap();
+    let (target, unit) = convert_shape(target, from_unit, unit, &settings)?;
+    target.measure_path(&precision.unwrap_or_default(), unit)
+}
+
+// Combines two sets of shapes, e.g. a room minus its columns. Circles are flattened to within
+// `tolerance` internal pixels; result polygons are in internal pixels and the area is in `unit`.
+#[tauri::command(rename_all = "snake_case")]
+fn boolean_op(
+    op: BooleanOp,
+    subject: Vec<Shape>,
+    clip: Vec<Shape>,
+    tolerance: Option<f64>,
+    precision: Option<Precision>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<BooleanResult, GeometryError> {
+    let settings = *settings.lock().unwrap();
+    settings.validate()?;
+    let unit = unit.unwrap_or(settings.unit);
+    boolean::apply(
+        op,
+        &subject,
+        &clip,
+        flatten_tolerance(tolerance)?,
+        &precision.unwrap_or_default(),
+        unit,
+        settings.convert(1.0, MeasurementUnit::Pixel, unit, 2),
+    )
+}
+
+// Moves, rotates, scales or mirrors `target` by `steps` applied in order, in internal pixels.
+// Rectangles rotated off the axes become oriented rectangles and unevenly scaled circles
+// ellipses. Sheared rectangles and closed arcs become polygons, with arcs flattened to within
+// `tolerance` internal pixels.
+#[tauri::command]
+fn transform_shape(
+    target: Shape,
+    steps: Vec<TransformStep>,
+    tolerance: Option<f64>,
+    precision: Option<Precision>,
+) -> Result<Shape, GeometryError> {
+    let precision = precision.unwrap_or_default();
+    let tolerance = flatten_tolerance(tolerance)?;
+    let center = match target.bounding_box() {
+        Some(bbox) => bbox.centroid(),
+        None => return Ok(target),
+    };
+    let transform = Transform::from_steps(&steps, center)?;
+    transform::apply(&target, &transform, tolerance, &precision)
+}
+
+// Whether `point` lies inside `target`, both in the same unit
+#[tauri::command]
+fn hit_test(target: Shape, point: Point) -> Result<bool, GeometryError> {
+    point.check_finite("point")?;
+    Ok(target.contains(&point))
+}
+
+// The four corners of `target` as a counter-clockwise polygon
+#[tauri::command]
+fn oriented_rectangle_to_polygon(target: OrientedRectangle) -> Result<Polygon, GeometryError> {
+    target.validate()?;
+    Ok(target.outline())
+}
+
+// The rectangle `polygon` traces, or `None` when its corners are not right angles
+#[tauri::command]
+fn polygon_to_oriented_rectangle(
+    polygon: Polygon,
+    precision: Option<Precision>,
+) -> Result<Option<OrientedRectangle>, GeometryError> {
+    polygon.validate()?;
+    Ok(OrientedRectangle::from_polygon(&polygon, &precision.unwrap_or_default()))
+}
+
+// Reads SVG path data (`M`, `L`, `H`, `V`, `Q`, `T`, `C`, `S` and `Z`, absolute or relative)
+// of a single subpath into a path shape, coordinates unchanged
+#[tauri::command]
+fn parse_path(data: String) -> Result<Path, DocumentError> {
+    Path::parse(&data).map_err(|reason| DocumentError::Malformed { format: "path data", reason })
+}
+
+// SVG path data for `path`, with absolute commands
+#[tauri::command]
+fn format_path(path: Path) -> Result<String, GeometryError> {
+    path.validate()?;
+    Ok(path.to_data())
+}
+
+// Points along `path` within `tolerance` (default a quarter pixel) of its curves, for drawing
+#[tauri::command]
+fn flatten_path(path: Path, tolerance: Option<f64>) -> Result<Vec<Point>, GeometryError> {
+    let tolerance = flatten_tolerance(tolerance)?;
+    path.validate()?;
+    Ok(path.points(tolerance))
+}
+
+// Reports self-intersections, duplicate and collinear vertices, zero area and winding order
+#[tauri::command]
+fn validate_polygon(polygon: Polygon, precision: Option<Precision>) -> Result<PolygonValidation, GeometryError> {
+    validation::validate(&polygon, &precision.unwrap_or_default())
+}
+
+// Converts a length (`dimension` 1, the default) or an area (`dimension` 2) between units
+#[tauri::command(rename_all = "snake_case")]
+fn convert_units(
+    value: f64,
+    from: MeasurementUnit,
+    to: MeasurementUnit,
+    dimension: Option<i32>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<f64, GeometryError> {
+    let settings = *settings.lock().unwrap();
+    settings.validate()?;
+    check_finite(value, "value")?;
+    let dimension = dimension.unwrap_or(1);
+    if !(1..=2).contains(&dimension) {
+        return Err(GeometryError::OutOfRange {
+            field: "dimension".to_string(),
+            min: 1.0,
+            max: 2.0,
+        });
+    }
+    Ok(settings.convert(value, from, to, dimension))
+}
+
+#[tauri::command]
+fn get_unit_settings(settings: State<'_, Mutex<UnitSettings>>) -> UnitSettings {
+    *settings.lock().unwrap()
+}
+
+#[tauri::command]
+fn set_unit_settings(
+    new_settings: UnitSettings,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<UnitSettings, GeometryError> {
+    new_settings.validate()?;
+    *settings.lock().unwrap() = new_settings;
+    Ok(new_settings)
+}
+
+// Applies `operation` to the scene as an undoable step and tells the frontend what can be undone
+fn perform(
+    app: &AppHandle,
+    scene: &mut Scene,
+    history: &Mutex<History>,
+    operation: Operation,
+    gesture: Option<String>,
+) -> Result<(), SceneError> {
+    let mut history = history.lock().unwrap();
+    history.perform(scene, operation, gesture)?;
+    let _ = app.emit(HISTORY_CHANGED, history.state());
+    Ok(())
+}
+
+// Adds a shape in internal pixels on top of `layer`, by default the top layer, returning it
+// with its new id. Scene changes sent with the same `gesture`, e.g. drawing a shape and then
+// dragging out its size, undo as one step.
+#[tauri::command]
+fn add_shape(
+    shape: Shape,
+    layer: Option<Uuid>,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<SceneShape, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let layer = layer.unwrap_or_else(|| scene.layers().last().expect("scenes have a layer").id);
+    let item = SceneShape::new(shape, layer);
+    let operation = Operation::add(&scene, item.clone())?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(item)
+}
+
+// Replaces a shape's geometry; `kind` labels the undo step and defaults to a property edit
+#[tauri::command]
+fn update_shape(
+    id: Uuid,
+    shape: Shape,
+    kind: Option<EditKind>,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<SceneShape, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::edit(&scene, kind.unwrap_or_default(), id, shape)?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(scene.get(id)?.clone())
+}
+
+// Drags a shape by `dx`, `dy` internal pixels
+#[tauri::command]
+fn move_shape(
+    id: Uuid,
+    dx: f64,
+    dy: f64,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<SceneShape, SceneError> {
+    check_finite(dx, "dx")?;
+    check_finite(dy, "dy")?;
+    let mut scene = scene.lock().unwrap();
+    let moved = transform::apply(
+        &scene.get(id)?.shape,
+        &Transform::translate(dx, dy),
+        flatten::DEFAULT_TOLERANCE,
+        &Precision::default(),
+    )?;
+    let operation = Operation::edit(&scene, EditKind::Move, id, moved)?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(scene.get(id)?.clone())
+}
+
+// Like `transform_shape`, for a shape in the scene
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn transform_scene_shape(
+    id: Uuid,
+    steps: Vec<TransformStep>,
+    tolerance: Option<f64>,
+    precision: Option<Precision>,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<SceneShape, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let transformed = transform_shape(scene.get(id)?.shape.clone(), steps, tolerance, precision)?;
+    let operation = Operation::edit(&scene, EditKind::Transform, id, transformed)?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(scene.get(id)?.clone())
+}
+
+// Returns the removed shape. Groups it leaves without shapes are removed in the same step.
+#[tauri::command]
+fn delete_shape(
+    id: Uuid,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<SceneShape, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let item = scene.get(id)?.clone();
+    let operation = Operation::delete(&scene, id)?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(item)
+}
+
+#[tauri::command]
+fn get_shape(id: Uuid, scene: State<'_, Mutex<Scene>>) -> Result<SceneShape, SceneError> {
+    scene.lock().unwrap().get(id).cloned()
+}
+
+// Every shape in painting order, bottom first, including those on hidden layers
+#[tauri::command]
+fn list_shapes(scene: State<'_, Mutex<Scene>>) -> Vec<SceneShape> {
+    scene.lock().unwrap().ordered().into_iter().cloned().collect()
+}
+
+// Every layer, bottom first
+#[tauri::command]
+fn list_layers(scene: State<'_, Mutex<Scene>>) -> Vec<Layer> {
+    scene.lock().unwrap().layers().to_vec()
+}
+
+// Adds an empty, visible and unlocked layer on top of the stack
+#[tauri::command]
+fn create_layer(
+    name: String,
+    color: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Layer, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let layer = Layer::new(name, color.unwrap_or_else(|| DEFAULT_LAYER_COLOR.to_string()));
+    let operation = Operation::add_layer(&scene, layer.clone())?;
+    perform(&app, &mut scene, &history, operation, None)?;
+    Ok(layer)
+}
+
+// Renames, recolors, shows, hides, locks or unlocks the layer with the same id as `layer`
+#[tauri::command]
+fn update_layer(
+    layer: Layer,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Layer, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::edit_layer(&scene, layer.clone())?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(layer)
+}
+
+// Moves a layer to `index` in the stack, 0 being the bottom, returning the reordered layers
+#[tauri::command]
+fn reorder_layer(
+    id: Uuid,
+    index: usize,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Vec<Layer>, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::reorder_layer(&scene, id, index)?;
+    perform(&app, &mut scene, &history, operation, None)?;
+    Ok(scene.layers().to_vec())
+}
+
+// Moves every shape of layer `id` onto layer `into` and removes layer `id`, returning the
+// remaining layers
+#[tauri::command]
+fn merge_layers(
+    id: Uuid,
+    into: Uuid,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Vec<Layer>, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::merge_layers(&scene, id, into)?;
+    perform(&app, &mut scene, &history, operation, None)?;
+    Ok(scene.layers().to_vec())
+}
+
+// Puts shapes on another layer, returning them as moved
+#[tauri::command]
+fn move_shapes_to_layer(
+    ids: Vec<Uuid>,
+    layer: Uuid,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Vec<SceneShape>, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::move_to_layer(&scene, &ids, layer)?;
+    perform(&app, &mut scene, &history, operation, None)?;
+    ids.iter().map(|&id| scene.get(id).cloned()).collect()
+}
+
+#[tauri::command]
+fn list_groups(scene: State<'_, Mutex<Scene>>) -> Vec<Group> {
+    scene.lock().unwrap().groups().to_vec()
+}
+
+// Puts shapes `ids` and groups `groups` in a new group, which is nested in the group they are
+// all in, if any, and returns it
+#[tauri::command]
+fn group_shapes(
+    ids: Vec<Uuid>,
+    groups: Option<Vec<Uuid>>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Group, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let (operation, group) = Operation::group(&scene, &ids, &groups.unwrap_or_default())?;
+    perform(&app, &mut scene, &history, operation, None)?;
+    Ok(group)
+}
+
+// Dissolves a group into its parent, keeping its shapes and nested groups
+#[tauri::command]
+fn ungroup(
+    id: Uuid,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<(), SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::ungroup(&scene, id)?;
+    perform(&app, &mut scene, &history, operation, None)
+}
+
+// Drags every shape in a group and its nested groups by `dx`, `dy` internal pixels, returning
+// them as moved
+#[tauri::command]
+fn move_group(
+    id: Uuid,
+    dx: f64,
+    dy: f64,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Vec<SceneShape>, SceneError> {
+    check_finite(dx, "dx")?;
+    check_finite(dy, "dy")?;
+    let mut scene = scene.lock().unwrap();
+    let transform = Transform::translate(dx, dy);
+    let operation = Operation::edit_group(&scene, EditKind::Move, id, |shape| {
+        transform::apply(shape, &transform, flatten::DEFAULT_TOLERANCE, &Precision::default())
+    })?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(scene.descendants(id)?.into_iter().cloned().collect())
+}
+
+// Like `transform_shape`, for every shape in a group and its nested groups together: rotation,
+// scaling and mirroring are about the center of the group's bounding box
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn transform_group(
+    id: Uuid,
+    steps: Vec<TransformStep>,
+    tolerance: Option<f64>,
+    precision: Option<Precision>,
+    gesture: Option<String>,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Vec<SceneShape>, SceneError> {
+    let precision = precision.unwrap_or_default();
+    let tolerance = flatten_tolerance(tolerance)?;
+    let mut scene = scene.lock().unwrap();
+    let center = match group_bounding_box(&scene, id)? {
+        Some(bbox) => bbox.centroid(),
+        None => return Err(SceneError::EmptyGroup),
+    };
+    let transform = Transform::from_steps(&steps, center)?;
+    let operation = Operation::edit_group(&scene, EditKind::Transform, id, |shape| {
+        transform::apply(shape, &transform, tolerance, &precision)
+    })?;
+    perform(&app, &mut scene, &history, operation, gesture)?;
+    Ok(scene.descendants(id)?.into_iter().cloned().collect())
+}
+
+// Bounds of every shape in a group and its nested groups; `None` when none has points
+fn group_bounding_box(scene: &Scene, id: Uuid) -> Result<Option<Rectangle>, SceneError> {
+    Ok(scene
+        .descendants(id)?
+        .iter()
+        .filter_map(|item| item.shape.bounding_box())
+        .reduce(|a, b| a.union(&b)))
+}
+
+// Removes every shape on an unlocked layer, as one undoable step
+#[tauri::command]
+fn clear_scene(
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<(), SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let operation = Operation::clear(&scene);
+    perform(&app, &mut scene, &history, operation, None)
+}
+
+// Reverts the last step, emitting what changed; `None` when there is nothing to undo
+#[tauri::command]
+fn undo(
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Option<SceneChange>, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let mut history = history.lock().unwrap();
+    let change = history.undo(&mut scene)?;
+    emit_change(&app, &change);
+    Ok(change)
+}
+
+// Applies the last undone step again, emitting what changed; `None` when there is nothing to redo
+#[tauri::command]
+fn redo(
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Option<SceneChange>, SceneError> {
+    let mut scene = scene.lock().unwrap();
+    let mut history = history.lock().unwrap();
+    let change = history.redo(&mut scene)?;
+    emit_change(&app, &change);
+    Ok(change)
+}
+
+fn emit_change(app: &AppHandle, change: &Option<SceneChange>) {
+    if let Some(change) = change {
+        let _ = app.emit(SCENE_CHANGED, change);
+        let _ = app.emit(HISTORY_CHANGED, change.history);
     }
-    if target.height() < 0 {
-        return Err("Width cannot be negative!".to_string());
+}
+
+#[tauri::command]
+fn get_history(history: State<'_, Mutex<History>>) -> HistoryState {
+    history.lock().unwrap().state()
+}
+
+// Like `measure_shape`, for a shape in the scene
+#[tauri::command]
+fn measure_scene_shape(
+    id: Uuid,
+    precision: Option<Precision>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<ShapeMetrics, SceneError> {
+    let settings = *settings.lock().unwrap();
+    let target = scene.lock().unwrap().get(id)?.shape.clone();
+    let (target, unit) = convert_shape(target, None, unit, &settings)?;
+    Ok(target.measure(&precision.unwrap_or_default(), unit)?)
+}
+
+// Combined measurements of every shape in a group and its nested groups, in `unit`. The union
+// area counts overlaps once, with curves flattened to within `tolerance` internal pixels, while
+// the sum counts them once per shape. Open shapes add to the bounds but not to either area.
+#[tauri::command]
+fn measure_group(
+    id: Uuid,
+    tolerance: Option<f64>,
+    precision: Option<Precision>,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<GroupMetrics, SceneError> {
+    let settings = *settings.lock().unwrap();
+    let tolerance = flatten_tolerance(tolerance)?;
+    let targets: Vec<Shape> = scene
+        .lock()
+        .unwrap()
+        .descendants(id)?
+        .into_iter()
+        .map(|item| item.shape.clone())
+        .collect();
+    group_metrics(targets, tolerance, &precision.unwrap_or_default(), unit, &settings)
+}
+
+// Measurements of `targets`, given in internal pixels, as one group
+fn group_metrics(
+    targets: Vec<Shape>,
+    tolerance: f64,
+    precision: &Precision,
+    unit: Option<MeasurementUnit>,
+    settings: &UnitSettings,
+) -> Result<GroupMetrics, SceneError> {
+    if targets.is_empty() {
+        return Err(SceneError::EmptyGroup);
+    }
+
+    let mut shapes = Vec::with_capacity(targets.len());
+    let mut metrics = Vec::with_capacity(targets.len());
+    let mut unit = unit;
+    for target in targets {
+        let (target, target_unit) = convert_shape(target, None, unit, settings)?;
+        unit = Some(target_unit);
+        metrics.push(target.measure(precision, target_unit)?);
+        shapes.push(target);
+    }
+    let unit = unit.expect("groups measured have a shape");
+
+    // Only shapes that enclose an area take part in the union
+    let closed: Vec<Shape> = shapes
+        .into_iter()
+        .zip(&metrics)
+        .filter(|(_, metrics)| metrics.area > 0.0)
+        .map(|(shape, _)| shape)
+        .collect();
+    let tolerance = tolerance * settings.length_factor(MeasurementUnit::Pixel, unit);
+    let union = boolean::apply(BooleanOp::Union, &closed, &[], tolerance, precision, unit, 1.0)?;
+
+    let centroid = if union.area > 0.0 {
+        MultiPolygon { polygons: union.polygons }.centroid(precision)
+    } else {
+        // Weighted by length, so a long wall counts more than a short one
+        let total: f64 = metrics.iter().map(|metrics| metrics.perimeter).sum();
+        let count = metrics.len() as f64;
+        let weight = |m: &ShapeMetrics| if total > 0.0 { m.perimeter / total } else { 1.0 / count };
+        Point {
+            x: metrics.iter().map(|m| m.centroid.x * weight(m)).sum(),
+            y: metrics.iter().map(|m| m.centroid.y * weight(m)).sum(),
+        }
+    };
+    Ok(GroupMetrics {
+        union_area: union.area,
+        sum_area: precision.round(metrics.iter().map(|metrics| metrics.area).sum()),
+        centroid: Point {
+            x: precision.round(centroid.x),
+            y: precision.round(centroid.y),
+        },
+        bbox: metrics.iter().map(|metrics| metrics.bbox).reduce(|a, b| a.union(&b)).unwrap(),
+        shapes: metrics.len(),
+        unit,
+        area_unit: unit.squared_symbol(),
+    })
+}
+
+// Saves the scene's layers and shapes with `properties`, returning the properties as written with their save
+// timestamps filled in
+#[tauri::command]
+fn save_document(
+    path: PathBuf,
+    properties: DocumentProperties,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<DocumentProperties, DocumentError> {
+    let mut document = scene_document(properties, &scene);
+    document.save(&path)?;
+    Ok(document.properties)
+}
+
+// Replaces the scene with the document's shapes, which cannot be undone
+#[tauri::command]
+fn open_document(
+    path: PathBuf,
+    app: AppHandle,
+    scene: State<'_, Mutex<Scene>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<Document, DocumentError> {
+    let document = Document::open(&path)?;
+    let mut scene = scene.lock().unwrap();
+    let mut history = history.lock().unwrap();
+    *scene = document.scene.clone();
+    history.clear();
+    let _ = app.emit(HISTORY_CHANGED, history.state());
+    Ok(document)
+}
+
+// Writes the scene's visible layers to `path` as an SVG sized in the document's unit
+#[tauri::command]
+fn export_svg(
+    path: PathBuf,
+    properties: DocumentProperties,
+    options: Option<DrawingOptions>,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<(), DocumentError> {
+    let settings = *settings.lock().unwrap();
+    let document = scene_document(properties, &scene);
+    let drawing = Drawing::new(&document, &settings, &options.unwrap_or_default())?;
+    let svg = svg::render(&drawing, document.properties.metadata.title.as_deref());
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+// Writes the scene's visible layers to `path` as a PNG rendered on the CPU
+#[tauri::command]
+fn render_png(
+    path: PathBuf,
+    properties: DocumentProperties,
+    options: Option<RasterOptions>,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<(), DocumentError> {
+    let settings = *settings.lock().unwrap();
+    let options = options.unwrap_or_default();
+    let document = scene_document(properties, &scene);
+    let drawing = Drawing::new(&document, &settings, &options.drawing)?;
+    let png = raster::render_png(&drawing, &settings, &options)?;
+    std::fs::write(path, png)?;
+    Ok(())
+}
+
+// Writes the scene's visible layers to `path` as vector PDF pages at true physical scale
+#[tauri::command]
+fn export_pdf(
+    path: PathBuf,
+    properties: DocumentProperties,
+    options: Option<PdfOptions>,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<(), DocumentError> {
+    let settings = *settings.lock().unwrap();
+    let options = options.unwrap_or_default();
+    let document = scene_document(properties, &scene);
+    let drawing = Drawing::new(&document, &settings, &options.drawing)?;
+    let pdf = pdf::render(&drawing, &settings, &options, document.properties.metadata.title.as_deref())?;
+    std::fs::write(path, pdf)?;
+    Ok(())
+}
+
+// Reads the shapes of an SVG file, flattening curves to within `tolerance` internal pixels
+#[tauri::command]
+fn import_svg(
+    path: PathBuf,
+    tolerance: Option<f64>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<SvgImport, DocumentError> {
+    let tolerance = flatten_tolerance(tolerance)?;
+    let settings = *settings.lock().unwrap();
+    let text = std::fs::read_to_string(path)?;
+    svg_import::import(&text, &settings, tolerance)
+}
+
+// Writes the scene to `path` as an ASCII DXF file in the document's unit, with a DXF layer for
+// each scene layer
+#[tauri::command]
+fn export_dxf(
+    path: PathBuf,
+    properties: DocumentProperties,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<(), DocumentError> {
+    let settings = *settings.lock().unwrap();
+    std::fs::write(path, dxf::export(&scene_document(properties, &scene), &settings))?;
+    Ok(())
+}
+
+// Reads the shapes of an ASCII DXF file, flattening polyline arcs to within `tolerance` internal pixels
+#[tauri::command]
+fn import_dxf(
+    path: PathBuf,
+    tolerance: Option<f64>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<DxfImport, DocumentError> {
+    let tolerance = flatten_tolerance(tolerance)?;
+    let settings = *settings.lock().unwrap();
+    let text = std::fs::read_to_string(path)?;
+    dxf::import(&text, &settings, tolerance)
+}
+
+// Reads polygons from pasted GeoJSON or WKT text with coordinates in `unit`, defaulting to the current unit
+#[tauri::command]
+fn parse_geometry(
+    text: String,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<GeometryImport, DocumentError> {
+    let settings = *settings.lock().unwrap();
+    gis::parse(&text, unit.unwrap_or(settings.unit), &settings)
+}
+
+// Writes shapes as GeoJSON or WKT text for the clipboard, with coordinates in `unit`
+#[tauri::command]
+fn format_geometry(
+    shapes: Vec<Shape>,
+    format: GeometryFormat,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<String, DocumentError> {
+    let settings = *settings.lock().unwrap();
+    gis::format(&shapes, format, unit.unwrap_or(settings.unit), &settings)
+}
+
+// Reads polygons from a GeoJSON or WKT file
+#[tauri::command]
+fn import_geometry(
+    path: PathBuf,
+    unit: Option<MeasurementUnit>,
+    settings: State<'_, Mutex<UnitSettings>>,
+) -> Result<GeometryImport, DocumentError> {
+    let settings = *settings.lock().unwrap();
+    let text = std::fs::read_to_string(path)?;
+    gis::parse(&text, unit.unwrap_or(settings.unit), &settings)
+}
+
+// Writes the shapes on visible layers to `path` as GeoJSON or WKT in the document's unit
+#[tauri::command]
+fn export_geometry(
+    path: PathBuf,
+    properties: DocumentProperties,
+    format: GeometryFormat,
+    settings: State<'_, Mutex<UnitSettings>>,
+    scene: State<'_, Mutex<Scene>>,
+) -> Result<(), DocumentError> {
+    let settings = *settings.lock().unwrap();
+    let shapes: Vec<Shape> = scene.lock().unwrap().visible().into_iter().map(|item| item.shape.clone()).collect();
+    std::fs::write(path, gis::format(&shapes, format, properties.unit, &settings)?)?;
+    Ok(())
+}
+
+// A snapshot of the scene with `properties`, for saving and export
+fn scene_document(properties: DocumentProperties, scene: &Mutex<Scene>) -> Document {
+    Document {
+        scene: scene.lock().unwrap().clone(),
+        properties,
     }
-    let area = target.length() * target.height();
-    Ok(area as u32)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(generate_handler![greet, calc_area])
+        .manage(Mutex::new(UnitSettings::default()))
+        .manage(Mutex::new(Scene::default()))
+        .manage(Mutex::new(History::default()))
+        .invoke_handler(generate_handler![
+            greet,
+            calc_area,
+            measure_shape,
+            measure_path,
+            validate_polygon,
+            boolean_op,
+            transform_shape,
+            hit_test,
+            oriented_rectangle_to_polygon,
+            polygon_to_oriented_rectangle,
+            parse_path,
+            format_path,
+            flatten_path,
+            convert_units,
+            get_unit_settings,
+            set_unit_settings,
+            add_shape,
+            update_shape,
+            move_shape,
+            transform_scene_shape,
+            delete_shape,
+            get_shape,
+            list_shapes,
+            list_layers,
+            create_layer,
+            update_layer,
+            reorder_layer,
+            merge_layers,
+            move_shapes_to_layer,
+            list_groups,
+            group_shapes,
+            ungroup,
+            move_group,
+            transform_group,
+            clear_scene,
+            undo,
+            redo,
+            get_history,
+            measure_scene_shape,
+            measure_group,
+            save_document,
+            open_document,
+            export_svg,
+            import_svg,
+            render_png,
+            export_pdf,
+            export_dxf,
+            import_dxf,
+            parse_geometry,
+            format_geometry,
+            import_geometry,
+            export_geometry
+        ])
         .run(generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bezier::PathSegment;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn area(shape: Shape) -> Result<f64, GeometryError> {
+        shape.area(&Precision::default())
+    }
+
+    fn measure(shape: Shape) -> ShapeMetrics {
+        shape.measure(&Precision::default(), MeasurementUnit::Pixel).unwrap()
+    }
+
+    fn assert_point(actual: Point, expected: Point) {
+        assert!(
+            actual.distance(&expected) < 1e-9,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn rectangle_area_is_length_times_height() {
+        let rect = Rectangle { top_left: point(1.0, 2.0), bottom_right: point(5.5, 5.0) };
+        assert_eq!(area(Shape::Rectangle(rect)), Ok(13.5));
+    }
+
+    #[test]
+    fn rectangle_corners_may_be_swapped() {
+        let rect = Rectangle { top_left: point(5.5, 0.0), bottom_right: point(1.0, 3.0) };
+        assert_eq!(area(Shape::Rectangle(rect)), Ok(13.5));
+    }
+
+    #[test]
+    fn circle_area_is_pi_r_squared() {
+        let circle = Circle { center: point(3.0, 3.0), radius: 2.0 };
+        assert_eq!(area(Shape::Circle(circle)), Ok(4.0 * std::f64::consts::PI));
+    }
+
+    #[test]
+    fn polygon_area_ignores_winding() {
+        let ccw = vec![point(0.0, 0.0), point(4.0, 0.0), point(0.0, 3.0)];
+        let cw = vec![point(0.0, 0.0), point(0.0, 3.0), point(4.0, 0.0)];
+        assert_eq!(area(Shape::Polygon(Polygon::new(ccw))), Ok(6.0));
+        assert_eq!(area(Shape::Polygon(Polygon::new(cw))), Ok(6.0));
+    }
+
+    #[test]
+    fn rounds_to_the_requested_precision() {
+        let circle = Circle { center: point(0.0, 0.0), radius: 2.0 };
+        let precision = Precision { epsilon: 1e-9, decimals: Some(2) };
+        assert_eq!(Shape::Circle(circle).area(&precision), Ok(12.57));
+    }
+
+    #[test]
+    fn converts_shapes_between_units() {
+        let rect = Rectangle { top_left: point(0.0, 0.0), bottom_right: point(192.0, 96.0) };
+        let settings = UnitSettings::default();
+        let inch = Some(MeasurementUnit::Inch);
+        let (rect, unit) = convert_shape(Shape::Rectangle(rect), None, inch, &settings).unwrap();
+        assert_eq!(unit, MeasurementUnit::Inch);
+        assert_eq!(area(rect), Ok(2.0));
+    }
+
+    #[test]
+    fn rejects_non_finite_coordinates() {
+        let circle = Circle { center: point(0.0, f64::NAN), radius: 1.0 };
+        assert_eq!(
+            area(Shape::Circle(circle)),
+            Err(GeometryError::NonFinite { field: "center.y".to_string() })
+        );
+    }
+
+    #[test]
+    fn rejects_negative_extents() {
+        let circle = Circle { center: point(0.0, 0.0), radius: -1.0 };
+        assert_eq!(
+            area(Shape::Circle(circle)),
+            Err(GeometryError::NegativeExtent { field: "radius".to_string() })
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_areas() {
+        let rect = Rectangle { top_left: point(-1e300, 0.0), bottom_right: point(1e300, 1e10) };
+        assert_eq!(
+            area(Shape::Rectangle(rect)),
+            Err(GeometryError::Overflow { field: "area".to_string() })
+        );
+    }
+
+    #[test]
+    fn rejects_self_intersecting_polygons() {
+        let bow_tie = vec![point(0.0, 0.0), point(2.0, 2.0), point(2.0, 0.0), point(0.0, 2.0)];
+        assert_eq!(
+            area(Shape::Polygon(Polygon::new(bow_tie))),
+            Err(GeometryError::SelfIntersecting { field: "points".to_string() })
+        );
+    }
+
+    #[test]
+    fn rejects_polygons_with_too_few_points() {
+        let polygon = Polygon::new(vec![point(0.0, 0.0), point(1.0, 1.0)]);
+        assert_eq!(
+            area(Shape::Polygon(polygon)),
+            Err(GeometryError::TooFewPoints { field: "points".to_string(), required: 3, actual: 2 })
+        );
+    }
+
+    #[test]
+    fn measures_rectangles() {
+        let rect = Rectangle { top_left: point(4.0, 3.0), bottom_right: point(0.0, 0.0) };
+        let metrics = measure(Shape::Rectangle(rect));
+        assert_eq!((metrics.area, metrics.perimeter), (12.0, 14.0));
+        assert_point(metrics.centroid, point(2.0, 1.5));
+        assert_point(metrics.bbox.top_left, point(0.0, 0.0));
+        assert_point(metrics.bbox.bottom_right, point(4.0, 3.0));
+        assert_eq!((metrics.convex, metrics.self_intersecting), (None, None));
+    }
+
+    #[test]
+    fn measures_circles() {
+        let circle = Circle { center: point(1.0, 2.0), radius: 2.0 };
+        let metrics = measure(Shape::Circle(circle));
+        assert_eq!(metrics.perimeter, 4.0 * std::f64::consts::PI);
+        assert_point(metrics.centroid, point(1.0, 2.0));
+        assert_point(metrics.bbox.top_left, point(-1.0, 0.0));
+        assert_point(metrics.bbox.bottom_right, point(3.0, 4.0));
+    }
+
+    #[test]
+    fn measures_polygons() {
+        // An L made of a 4×1 bar and a 1×2 upright
+        let points = vec![
+            point(0.0, 0.0),
+            point(4.0, 0.0),
+            point(4.0, 1.0),
+            point(1.0, 1.0),
+            point(1.0, 3.0),
+            point(0.0, 3.0),
+        ];
+        let metrics = measure(Shape::Polygon(Polygon::new(points)));
+        assert_eq!((metrics.area, metrics.perimeter), (6.0, 14.0));
+        assert_point(metrics.centroid, point(1.5, 1.0));
+        assert_point(metrics.bbox.bottom_right, point(4.0, 3.0));
+        assert_eq!((metrics.convex, metrics.self_intersecting), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn flags_convex_and_self_intersecting_polygons() {
+        let square = vec![point(0.0, 0.0), point(2.0, 0.0), point(2.0, 2.0), point(0.0, 2.0)];
+        let metrics = measure(Shape::Polygon(Polygon::new(square)));
+        assert_eq!((metrics.convex, metrics.self_intersecting), (Some(true), Some(false)));
+        let bow_tie = vec![point(0.0, 0.0), point(2.0, 2.0), point(2.0, 0.0), point(0.0, 2.0)];
+        let metrics = measure(Shape::Polygon(Polygon::new(bow_tie)));
+        assert_eq!((metrics.convex, metrics.self_intersecting), (Some(false), Some(true)));
+    }
+
+    fn square(x: f64, y: f64, side: f64) -> Vec<Point> {
+        vec![point(x, y), point(x + side, y), point(x + side, y + side), point(x, y + side)]
+    }
+
+    #[test]
+    fn holes_reduce_area_and_add_perimeter() {
+        let polygon = Polygon { points: square(0.0, 0.0, 4.0), holes: vec![square(1.0, 1.0, 1.0)] };
+        let metrics = measure(Shape::Polygon(polygon));
+        assert_eq!((metrics.area, metrics.perimeter), (15.0, 20.0));
+        let centroid = (32.0 - 1.5) / 15.0;
+        assert_point(metrics.centroid, point(centroid, centroid));
+    }
+
+    #[test]
+    fn measures_multi_polygons_as_one_shape() {
+        let multi = MultiPolygon {
+            polygons: vec![Polygon::new(square(0.0, 0.0, 1.0)), Polygon::new(square(2.0, 0.0, 1.0))],
+        };
+        let metrics = measure(Shape::MultiPolygon(multi));
+        assert_eq!((metrics.area, metrics.perimeter), (2.0, 8.0));
+        assert_point(metrics.centroid, point(1.5, 0.5));
+        assert_point(metrics.bbox.bottom_right, point(3.0, 1.0));
+    }
+
+    #[test]
+    fn rejects_holes_outside_their_polygon() {
+        let polygon = Polygon { points: square(0.0, 0.0, 4.0), holes: vec![square(5.0, 5.0, 1.0)] };
+        assert_eq!(
+            area(Shape::Polygon(polygon)),
+            Err(GeometryError::HoleOutside { field: "holes[0]".to_string() })
+        );
+    }
+
+    #[test]
+    fn measures_oriented_rectangles() {
+        let rect = OrientedRectangle { center: point(1.0, 1.0), width: 4.0, height: 2.0, angle: 30.0 };
+        let metrics = measure(Shape::OrientedRectangle(rect));
+        assert_eq!((metrics.area, metrics.perimeter), (8.0, 12.0));
+        assert_point(metrics.centroid, point(1.0, 1.0));
+        // Half extents of the box are w/2·cos + h/2·sin and w/2·sin + h/2·cos
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        assert_point(metrics.bbox.bottom_right, point(1.0 + 2.0 * cos + sin, 1.0 + 2.0 * sin + cos));
+    }
+
+    #[test]
+    fn rejects_negative_oriented_rectangle_extents() {
+        let rect = OrientedRectangle { center: point(0.0, 0.0), width: -4.0, height: 2.0, angle: 0.0 };
+        assert_eq!(
+            area(Shape::OrientedRectangle(rect)),
+            Err(GeometryError::NegativeExtent { field: "width".to_string() })
+        );
+    }
+
+    #[test]
+    fn oriented_rectangles_round_trip_through_polygons() {
+        let rect = OrientedRectangle { center: point(3.0, -2.0), width: 4.0, height: 2.0, angle: 30.0 };
+        let polygon = oriented_rectangle_to_polygon(rect).unwrap();
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        assert_eq!(polygon.points.len(), 4);
+        assert_point(polygon.points[0], point(3.0 - 2.0 * cos + sin, -2.0 - 2.0 * sin - cos));
+
+        let read = polygon_to_oriented_rectangle(polygon, None).unwrap().unwrap();
+        assert_point(read.center, rect.center);
+        assert!((read.width - 4.0).abs() < 1e-9 && (read.height - 2.0).abs() < 1e-9);
+        assert!((read.angle - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_right_angled_quadrilaterals_convert_to_oriented_rectangles() {
+        // Clockwise, with the first edge pointing down-left: the angle folds into (-90, 90]
+        let clockwise = Polygon::new(vec![point(2.0, 2.0), point(0.0, 0.0), point(-1.0, 1.0), point(1.0, 3.0)]);
+        let read = polygon_to_oriented_rectangle(clockwise, None).unwrap().unwrap();
+        assert!((read.angle - 45.0).abs() < 1e-9, "angle {}", read.angle);
+        assert_point(read.center, point(0.5, 1.5));
+
+        let parallelogram = Polygon::new(vec![point(0.0, 0.0), point(2.0, 0.0), point(3.0, 1.0), point(1.0, 1.0)]);
+        assert!(polygon_to_oriented_rectangle(parallelogram, None).unwrap().is_none());
+        let triangle = Polygon::new(vec![point(0.0, 0.0), point(2.0, 0.0), point(0.0, 1.0)]);
+        assert!(polygon_to_oriented_rectangle(triangle, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn hit_tests_rotated_rectangles() {
+        let rect = OrientedRectangle { center: point(0.0, 0.0), width: 4.0, height: 0.5, angle: 45.0 };
+        assert_eq!(hit_test(Shape::OrientedRectangle(rect), point(1.0, 1.0)), Ok(true));
+        assert_eq!(hit_test(Shape::OrientedRectangle(rect), point(1.0, -1.0)), Ok(false));
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn measures_ellipses() {
+        let ellipse = Ellipse { center: point(-1.0, 1.0), rx: 2.0, ry: 1.0, angle: 45.0 };
+        let metrics = measure(Shape::Ellipse(ellipse));
+        assert_close(metrics.area, 2.0 * PI, 1e-9);
+        // Ramanujan's approximation is within 1e-4 of the true 9.68845 here
+        assert_close(metrics.perimeter, 9.688448, 1e-4);
+        assert_point(metrics.centroid, point(-1.0, 1.0));
+        // A circle is the special case where the approximation is exact
+        let circle = Ellipse { center: point(0.0, 0.0), rx: 3.0, ry: 3.0, angle: 10.0 };
+        assert_close(measure(Shape::Ellipse(circle)).perimeter, 6.0 * PI, 1e-9);
+    }
+
+    #[test]
+    fn measures_arcs() {
+        // Quarter disc, whose centroid is 4r/3π from both edges
+        let sector = Arc {
+            center: point(0.0, 0.0),
+            radius: 2.0,
+            start_angle: 0.0,
+            sweep_angle: 90.0,
+            closure: ArcClosure::Sector,
+        };
+        let metrics = measure(Shape::Arc(sector));
+        let offset = 8.0 / (3.0 * PI);
+        assert_close(metrics.area, PI, 1e-9);
+        assert_close(metrics.perimeter, PI + 4.0, 1e-9);
+        assert_point(metrics.centroid, point(offset, offset));
+
+        // Half disc closed by its diameter
+        let chord = Arc { radius: 1.0, sweep_angle: 180.0, closure: ArcClosure::Chord, ..sector };
+        let metrics = measure(Shape::Arc(chord));
+        assert_close(metrics.area, PI / 2.0, 1e-9);
+        assert_close(metrics.perimeter, PI + 2.0, 1e-9);
+        assert_point(metrics.centroid, point(0.0, 4.0 / (3.0 * PI)));
+
+        // The curve alone, centered 2r/π from the center
+        let open = Arc { closure: ArcClosure::Open, ..chord };
+        let metrics = measure(Shape::Arc(open));
+        assert_eq!(metrics.area, 0.0);
+        assert_close(metrics.perimeter, PI, 1e-9);
+        assert_point(metrics.centroid, point(0.0, 2.0 / PI));
+        assert_eq!(area(Shape::Arc(open)), Err(GeometryError::OpenShape { field: "closure".to_string() }));
+    }
+
+    #[test]
+    fn rejects_invalid_ellipses_and_arcs() {
+        let ellipse = Ellipse { center: point(0.0, 0.0), rx: 1.0, ry: -2.0, angle: 0.0 };
+        assert_eq!(
+            area(Shape::Ellipse(ellipse)),
+            Err(GeometryError::NegativeExtent { field: "ry".to_string() })
+        );
+        let arc = Arc {
+            center: point(0.0, 0.0),
+            radius: 1.0,
+            start_angle: f64::NAN,
+            sweep_angle: 90.0,
+            closure: ArcClosure::Sector,
+        };
+        assert_eq!(
+            area(Shape::Arc(arc)),
+            Err(GeometryError::NonFinite { field: "start_angle".to_string() })
+        );
+    }
+
+    #[test]
+    fn measures_open_shapes() {
+        let segment = Segment { start: point(0.0, 0.0), end: point(3.0, 4.0) };
+        let metrics = measure(Shape::Segment(segment));
+        assert_eq!((metrics.area, metrics.perimeter), (0.0, 5.0));
+        assert_point(metrics.centroid, point(1.5, 2.0));
+
+        // Midpoints of the segments weighted by their lengths
+        let polyline = Polyline { points: vec![point(0.0, 0.0), point(2.0, 0.0), point(2.0, 2.0)] };
+        let metrics = measure(Shape::Polyline(polyline.clone()));
+        assert_eq!((metrics.area, metrics.perimeter), (0.0, 4.0));
+        assert_point(metrics.centroid, point(1.5, 0.5));
+        assert_eq!(area(Shape::Polyline(polyline)), Err(GeometryError::OpenShape { field: "type".to_string() }));
+    }
+
+    #[test]
+    fn measures_segment_lengths_angles_and_turns() {
+        let polyline = Polyline { points: vec![point(0.0, 0.0), point(2.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)] };
+        let path = Shape::Polyline(polyline).measure_path(&Precision::default(), MeasurementUnit::Pixel).unwrap();
+        assert!(!path.closed);
+        assert_close(path.length, 4.0 + 8f64.sqrt(), 1e-9);
+        let angles: Vec<f64> = path.segments.iter().map(|segment| segment.angle).collect();
+        assert_eq!(angles, [0.0, 90.0, -45.0]);
+        let turns: Vec<Option<f64>> = path.segments.iter().map(|segment| segment.turn).collect();
+        assert_eq!(turns, [None, Some(90.0), Some(-135.0)]);
+
+        // Closed shapes include the edge back to the first vertex
+        let square = Polygon::new(square(0.0, 0.0, 1.0));
+        let path = Shape::Polygon(square).measure_path(&Precision::default(), MeasurementUnit::Pixel).unwrap();
+        assert!(path.closed);
+        assert_eq!((path.length, path.segments.len()), (4.0, 4));
+    }
+
+    #[test]
+    fn rejects_polylines_with_too_few_points() {
+        let polyline = Polyline { points: vec![point(0.0, 0.0)] };
+        assert_eq!(
+            Shape::Polyline(polyline).measure(&Precision::default(), MeasurementUnit::Pixel).unwrap_err(),
+            GeometryError::TooFewPoints { field: "points".to_string(), required: 2, actual: 1 }
+        );
+        let circle = Circle { center: point(0.0, 0.0), radius: 1.0 };
+        assert_eq!(
+            Shape::Circle(circle).measure_path(&Precision::default(), MeasurementUnit::Pixel).unwrap_err(),
+            GeometryError::NotStraight { field: "type".to_string() }
+        );
+    }
+
+    #[test]
+    fn measures_paths() {
+        // The parabola y = 2x - x² closed by the X axis: area 4/3, centroid 2/5 of its height up,
+        // and a curve of length √5 + asinh(2)/2. The centroid comes from a flattened outline.
+        let path = Path {
+            start: point(0.0, 0.0),
+            segments: vec![
+                PathSegment::Line { to: point(2.0, 0.0) },
+                PathSegment::Quadratic { control: point(1.0, 2.0), to: point(0.0, 0.0) },
+            ],
+            closed: true,
+        };
+        let metrics = measure(Shape::Path(path.clone()));
+        assert_close(metrics.area, 4.0 / 3.0, 1e-9);
+        assert_close(metrics.perimeter, 2.0 + 5f64.sqrt() + 2f64.asinh() / 2.0, 1e-6);
+        assert_close(metrics.centroid.x, 1.0, 1e-4);
+        assert_close(metrics.centroid.y, 0.4, 1e-4);
+        assert_point(metrics.bbox.bottom_right, point(2.0, 1.0));
+
+        // Open, the same curve only has a length
+        let open = Path { closed: false, ..path };
+        let metrics = measure(Shape::Path(open.clone()));
+        assert_eq!(metrics.area, 0.0);
+        assert_close(metrics.perimeter, 2.0 + 5f64.sqrt() + 2f64.asinh() / 2.0, 1e-6);
+        assert_eq!(area(Shape::Path(open)), Err(GeometryError::OpenShape { field: "closed".to_string() }));
+    }
+
+    fn rectangle(x: f64, y: f64, width: f64, height: f64) -> Shape {
+        Shape::Rectangle(Rectangle {
+            top_left: point(x, y),
+            bottom_right: point(x + width, y + height),
+        })
+    }
+
+    fn measure_as_group(targets: Vec<Shape>, unit: MeasurementUnit) -> Result<GroupMetrics, SceneError> {
+        let settings = UnitSettings::default();
+        group_metrics(targets, flatten::DEFAULT_TOLERANCE, &Precision::default(), Some(unit), &settings)
+    }
+
+    #[test]
+    fn group_union_counts_overlaps_once() {
+        let targets = vec![rectangle(0.0, 0.0, 10.0, 10.0), rectangle(5.0, 0.0, 10.0, 10.0)];
+        let metrics = measure_as_group(targets, MeasurementUnit::Pixel).unwrap();
+        assert_eq!((metrics.union_area, metrics.sum_area, metrics.shapes), (150.0, 200.0, 2));
+        assert_point(metrics.centroid, point(7.5, 5.0));
+        assert_point(metrics.bbox.top_left, point(0.0, 0.0));
+        assert_point(metrics.bbox.bottom_right, point(15.0, 10.0));
+    }
+
+    #[test]
+    fn group_metrics_use_the_requested_unit() {
+        // 96 pixels are an inch at the default DPI
+        let targets = vec![rectangle(0.0, 0.0, 96.0, 96.0), rectangle(96.0, 0.0, 96.0, 96.0)];
+        let metrics = measure_as_group(targets, MeasurementUnit::Inch).unwrap();
+        assert_eq!((metrics.union_area, metrics.sum_area), (2.0, 2.0));
+        assert_eq!((metrics.unit, metrics.area_unit.as_str()), (MeasurementUnit::Inch, "in²"));
+        assert_point(metrics.centroid, point(1.0, 0.5));
+        assert_point(metrics.bbox.bottom_right, point(2.0, 1.0));
+    }
+
+    #[test]
+    fn open_shapes_bound_a_group_without_area() {
+        let targets = vec![
+            rectangle(0.0, 0.0, 10.0, 10.0),
+            Shape::Segment(Segment { start: point(20.0, 0.0), end: point(30.0, 0.0) }),
+        ];
+        let metrics = measure_as_group(targets, MeasurementUnit::Pixel).unwrap();
+        assert_eq!((metrics.union_area, metrics.sum_area, metrics.shapes), (100.0, 100.0, 2));
+        assert_point(metrics.centroid, point(5.0, 5.0));
+        assert_point(metrics.bbox.bottom_right, point(30.0, 10.0));
+
+        // With no area, the centroid is of the outlines, weighted by their length
+        let targets = vec![
+            Shape::Segment(Segment { start: point(0.0, 0.0), end: point(30.0, 0.0) }),
+            Shape::Segment(Segment { start: point(0.0, 10.0), end: point(10.0, 10.0) }),
+        ];
+        let metrics = measure_as_group(targets, MeasurementUnit::Pixel).unwrap();
+        assert_eq!(metrics.union_area, 0.0);
+        assert_point(metrics.centroid, point(12.5, 2.5));
+    }
+
+    #[test]
+    fn empty_groups_have_no_metrics() {
+        assert!(matches!(measure_as_group(Vec::new(), MeasurementUnit::Pixel), Err(SceneError::EmptyGroup)));
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::drawing::{format_number, Anchor, Baseline, Color, Drawing, DrawingOptions, Primitive, Style};
+use crate::error::DocumentError;
+use crate::units::UnitSettings;
+use crate::Point;
+
+const POINTS_PER_INCH: f64 = 72.0;
+const POINTS_PER_MM: f64 = POINTS_PER_INCH / 25.4;
+const TITLE_BLOCK_HEIGHT_MM: f64 = 14.0;
+
+// Refuses to silently produce an unprintable stack of paper
+const MAX_PAGES: usize = 500;
+
+// Bezier control distance for approximating a quarter circle
+const KAPPA: f64 = 0.552_284_749_8;
+
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PageSize {
+    A5,
+    A4,
+    A3,
+    Letter,
+    Legal,
+    Tabloid,
+    Custom { width_mm: f64, height_mm: f64 },
+}
+
+impl PageSize {
+    // Portrait width and height in millimeters
+    fn millimeters(&self) -> (f64, f64) {
+        match *self {
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A3 => (297.0, 420.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+            PageSize::Tabloid => (279.4, 431.8),
+            PageSize::Custom { width_mm, height_mm } => (width_mm, height_mm),
+        }
+    }
+}
+
+#[derive(Deserialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(default)]
+pub(crate) struct PdfOptions {
+    pub page_size: PageSize,
+    pub orientation: Orientation,
+    // Drawing scale on paper: 1 prints at full size, 0.5 at 1:2
+    pub scale: f64,
+    pub margin_mm: f64,
+    pub title_block: bool,
+    #[serde(flatten)]
+    pub drawing: DrawingOptions,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        PdfOptions {
+            page_size: PageSize::A4,
+            orientation: Orientation::Portrait,
+            scale: 1.0,
+            margin_mm: 10.0,
+            title_block: true,
+            drawing: DrawingOptions::default(),
+        }
+    }
+}
+
+// Writes the drawing as vector PDF pages at true physical size times `options.scale`.
+// Drawings larger than the printable area are tiled left to right, top to bottom.
+pub(crate) fn render(
+    drawing: &Drawing,
+    settings: &UnitSettings,
+    options: &PdfOptions,
+    title: Option<&str>,
+) -> Result<Vec<u8>, DocumentError> {
+    let (mut page_width, mut page_height) = options.page_size.millimeters();
+    if options.orientation == Orientation::Landscape {
+        std::mem::swap(&mut page_width, &mut page_height);
+    }
+    let (page_width, page_height) = (page_width * POINTS_PER_MM, page_height * POINTS_PER_MM);
+    let margin = options.margin_mm * POINTS_PER_MM;
+    let title_height = if options.title_block { TITLE_BLOCK_HEIGHT_MM * POINTS_PER_MM } else { 0.0 };
+    let area = Area {
+        x: margin,
+        y: margin + title_height,
+        width: page_width - 2.0 * margin,
+        height: page_height - 2.0 * margin - title_height,
+    };
+    let valid = |value: f64| value.is_finite() && value > 0.0;
+    if !valid(options.scale) || !valid(area.width) || !valid(area.height) || options.margin_mm < 0.0 {
+        return Err(DocumentError::Render {
+            reason: "the page has no printable area at these margins and scale".to_string(),
+        });
+    }
+
+    // Internal pixels are screen pixels, which span 1/dpi inch
+    let points_per_pixel = POINTS_PER_INCH / settings.dpi * options.scale;
+    let drawing_width = drawing.width() * points_per_pixel;
+    let drawing_height = drawing.height() * points_per_pixel;
+    // Small tolerance so a drawing that fits exactly does not spill onto a sliver of a page
+    let columns = ((drawing_width / area.width) - 1e-9).ceil().max(1.0) as usize;
+    let rows = ((drawing_height / area.height) - 1e-9).ceil().max(1.0) as usize;
+    // Huge drawings at a large scale can need more pages than `usize` counts
+    let page_count = match columns.checked_mul(rows) {
+        Some(count) if count <= MAX_PAGES => count,
+        count => {
+            return Err(DocumentError::Render {
+                reason: format!(
+                    "the drawing would need {} pages; use a smaller scale or larger paper",
+                    count.map_or("too many".to_string(), |count| count.to_string())
+                ),
+            });
+        }
+    };
+
+    let mut writer = PdfWriter::default();
+    let scale_label = scale_label(options.scale);
+    for row in 0..rows {
+        for column in 0..columns {
+            // Map internal coordinates so this tile's part of the drawing lands in the area;
+            // a drawing that fits on one page is centered instead
+            let (center_x, center_y) = if page_count == 1 {
+                ((area.width - drawing_width) / 2.0, (area.height - drawing_height) / 2.0)
+            } else {
+                (0.0, 0.0)
+            };
+            let tx = area.x + center_x - column as f64 * area.width
+                - drawing.bounds.top_left.x * points_per_pixel;
+            let ty = area.y + area.height - center_y + row as f64 * area.height
+                - drawing.bounds.bottom_right.y * points_per_pixel;
+
+            let mut content = String::new();
+            let _ = writeln!(
+                content,
+                "q {} {} {} {} re W n",
+                num(area.x),
+                num(area.y),
+                num(area.width),
+                num(area.height)
+            );
+            let _ = writeln!(
+                content,
+                "{k} 0 0 {k} {} {} cm",
+                num(tx),
+                num(ty),
+                k = format_number(points_per_pixel, 6)
+            );
+            for primitive in drawing.guides.iter().chain(&drawing.shapes) {
+                writer.primitive(&mut content, primitive);
+            }
+            content.push_str("Q\n");
+
+            if options.title_block {
+                let page_label = if page_count == 1 {
+                    "Page 1 of 1".to_string()
+                } else {
+                    format!(
+                        "Page {} of {} (row {}, column {})",
+                        row * columns + column + 1,
+                        page_count,
+                        row + 1,
+                        column + 1
+                    )
+                };
+                title_block(
+                    &mut content,
+                    &Area { x: margin, y: margin, width: area.width, height: title_height },
+                    title.unwrap_or("Untitled drawing"),
+                    &format!("Unit: {}    Scale {}    {}", drawing.unit, scale_label, page_label),
+                );
+            }
+            writer.add_page(page_width, page_height, content);
+        }
+    }
+    Ok(writer.finish(title))
+}
+
+struct Area {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+// `1:1`, `1:2` for reductions, `2:1` for enlargements
+fn scale_label(scale: f64) -> String {
+    if scale >= 1.0 {
+        format!("{}:1", format_number(scale, 3))
+    } else {
+        format!("1:{}", format_number(1.0 / scale, 3))
+    }
+}
+
+fn title_block(content: &mut String, block: &Area, title: &str, details: &str) {
+    let _ = writeln!(
+        content,
+        "q 0 0 0 RG 0.8 w {} {} {} {} re S",
+        num(block.x),
+        num(block.y),
+        num(block.width),
+        num(block.height)
+    );
+    let padding = 3.0 * POINTS_PER_MM;
+    let _ = writeln!(
+        content,
+        "0 0 0 rg BT /F2 11 Tf {} {} Td ({}) Tj ET",
+        num(block.x + padding),
+        num(block.y + block.height - padding - 9.0),
+        pdf_string(title)
+    );
+    let _ = writeln!(
+        content,
+        "BT /F1 9 Tf {} {} Td ({}) Tj ET Q",
+        num(block.x + padding),
+        num(block.y + padding),
+        pdf_string(details)
+    );
+}
+
+fn num(value: f64) -> String {
+    format_number(value, 3)
+}
+
+// Latin-1 covers the WinAnsi characters the labels use (including `²`); others become `?`
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for character in text.chars() {
+        match character {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            ' '..='~' => escaped.push(character),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(escaped, "\\{:03o}", character as u32);
+            }
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+// Helvetica advance widths for printable ASCII, in 1/1000 em
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+fn text_width(text: &str, size: f64) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 32] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f64 / 1000.0 * size
+}
+
+#[derive(Default)]
+struct PdfWriter {
+    // Each entry is a complete object body, numbered from 1 in order
+    objects: Vec<String>,
+    pages: Vec<usize>,
+    // Graphics states for fill/stroke opacity, keyed by alpha in thousandths
+    opacities: BTreeMap<u32, String>,
+}
+
+impl PdfWriter {
+    fn add_object(&mut self, body: String) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    fn opacity(&mut self, alpha: f64) -> String {
+        let key = (alpha.clamp(0.0, 1.0) * 1000.0).round() as u32;
+        let next = self.opacities.len();
+        self.opacities
+            .entry(key)
+            .or_insert_with(|| format!("GS{}", next))
+            .clone()
+    }
+
+    fn primitive(&mut self, content: &mut String, primitive: &Primitive) {
+        match primitive {
+            Primitive::Line { from, to, style } => {
+                let path = format!("{} {} m {} {} l", num(from.x), num(from.y), num(to.x), num(to.y));
+                self.paint(content, &path, &Style { fill: None, ..*style });
+            }
+            Primitive::Polygon { points, holes, style } => {
+                let mut path = String::new();
+                for ring in std::iter::once(points).chain(holes) {
+                    for (i, p) in ring.iter().enumerate() {
+                        let _ = write!(path, "{} {} {} ", num(p.x), num(p.y), if i == 0 { "m" } else { "l" });
+                    }
+                    path.push_str("h ");
+                }
+                self.paint(content, path.trim_end(), style);
+            }
+            Primitive::Circle { center, radius, style } => {
+                self.paint(content, &circle_path(center, *radius), style);
+            }
+            Primitive::Text { position, text, size, color, anchor, baseline } => {
+                let width = text_width(text, *size);
+                let x = match anchor {
+                    Anchor::Start => position.x,
+                    Anchor::Middle => position.x - width / 2.0,
+                    Anchor::End => position.x - width,
+                };
+                // Helvetica's cap height is 0.718 em and its descender 0.207 em
+                let y = match baseline {
+                    Baseline::Top => position.y - 0.718 * size,
+                    Baseline::Middle => position.y - 0.359 * size,
+                    Baseline::Bottom => position.y + 0.207 * size,
+                };
+                let state = self.opacity(color.a);
+                let _ = writeln!(
+                    content,
+                    "q /{} gs {} rg BT /F1 {} Tf {} {} Td ({}) Tj ET Q",
+                    state,
+                    rgb(color),
+                    num(*size),
+                    num(x),
+                    num(y),
+                    pdf_string(text)
+                );
+            }
+        }
+    }
+
+    fn paint(&mut self, content: &mut String, path: &str, style: &Style) {
+        if let Some(fill) = style.fill {
+            let state = self.opacity(fill.a);
+            // Even-odd fill so holes stay empty whichever way their rings are wound
+            let _ = writeln!(content, "q /{} gs {} rg {} f* Q", state, rgb(&fill), path);
+        }
+        if let Some(stroke) = style.stroke {
+            let state = self.opacity(stroke.a);
+            let _ = writeln!(
+                content,
+                "q /{} gs {} RG {} w {} S Q",
+                state,
+                rgb(&stroke),
+                num(style.stroke_width),
+                path
+            );
+        }
+    }
+
+    fn add_page(&mut self, width: f64, height: f64, content: String) {
+        let stream = self.add_object(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+        // The pages tree and resources are numbered once all pages are known, see `finish`
+        let page = self.add_object(format!(
+            "<< /Type /Page /Parent {{pages}} /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources {{resources}} >>",
+            num(width),
+            num(height),
+            stream
+        ));
+        self.pages.push(page);
+    }
+
+    fn finish(mut self, title: Option<&str>) -> Vec<u8> {
+        let regular = self.add_object(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        );
+        let bold = self.add_object(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+        let mut states = String::new();
+        for (alpha, name) in &self.opacities {
+            let alpha = format_number(*alpha as f64 / 1000.0, 3);
+            let _ = write!(states, " /{} << /Type /ExtGState /ca {} /CA {} >>", name, alpha, alpha);
+        }
+        let resources = format!(
+            "<< /Font << /F1 {} 0 R /F2 {} 0 R >> /ExtGState <<{} >> >>",
+            regular, bold, states
+        );
+        let pages_id = self.objects.len() + 1;
+        let kids: Vec<String> = self.pages.iter().map(|page| format!("{} 0 R", page)).collect();
+        self.add_object(format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            self.pages.len()
+        ));
+        for &page in &self.pages {
+            let body = &mut self.objects[page - 1];
+            *body = body
+                .replace("{pages}", &format!("{} 0 R", pages_id))
+                .replace("{resources}", &resources);
+        }
+        let catalog = self.add_object(format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id));
+        let info = self.add_object(format!(
+            "<< /Title ({}) /Producer (hello-tauri-world) >>",
+            pdf_string(title.unwrap_or("Untitled drawing"))
+        ));
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, body).as_bytes());
+        }
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            catalog,
+            info,
+            xref
+        );
+        pdf.extend_from_slice(trailer.as_bytes());
+        pdf
+    }
+}
+
+fn rgb(color: &Color) -> String {
+    format!(
+        "{} {} {}",
+        format_number(color.r as f64 / 255.0, 3),
+        format_number(color.g as f64 / 255.0, 3),
+        format_number(color.b as f64 / 255.0, 3)
+    )
+}
+
+// Four cubic Bezier quarter arcs, counter-clockwise from the rightmost point
+fn circle_path(center: &Point, radius: f64) -> String {
+    let (cx, cy, r) = (center.x, center.y, radius);
+    let k = KAPPA * r;
+    format!(
+        "{} {} m {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c h",
+        num(cx + r), num(cy),
+        num(cx + r), num(cy + k), num(cx + k), num(cy + r), num(cx), num(cy + r),
+        num(cx - k), num(cy + r), num(cx - r), num(cy + k), num(cx - r), num(cy),
+        num(cx - r), num(cy - k), num(cx - k), num(cy - r), num(cx), num(cy - r),
+        num(cx + k), num(cy - r), num(cx + r), num(cy - k), num(cx + r), num(cy),
+    )
+}
@@ -0,0 +1,157 @@
+use serde::Deserialize;
+
+use crate::error::GeometryError;
+
+// Largest `epsilon`; anything coarser would snap ordinary coordinates to zero
+const MAX_EPSILON: f64 = 1.0;
+// Largest `decimals`; an f64 holds about 15 significant digits, and far larger values make
+// the rounding factor infinite
+const MAX_DECIMALS: u32 = 15;
+
+// Rounding/epsilon policy applied to floating-point geometry results
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(try_from = "PrecisionInput")]
+pub(crate) struct Precision {
+    // Magnitudes below this are treated as exactly zero
+    pub epsilon: f64,
+    // Decimal places results are rounded to, `None` keeps full precision
+    pub decimals: Option<u32>,
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Precision {
+            epsilon: 1e-9,
+            decimals: None,
+        }
+    }
+}
+
+// `Precision` as sent by the frontend, checked before use
+#[derive(Deserialize)]
+#[serde(default)]
+struct PrecisionInput {
+    epsilon: f64,
+    decimals: Option<u32>,
+}
+
+impl Default for PrecisionInput {
+    fn default() -> Self {
+        let Precision { epsilon, decimals } = Precision::default();
+        PrecisionInput { epsilon, decimals }
+    }
+}
+
+impl TryFrom<PrecisionInput> for Precision {
+    type Error = GeometryError;
+
+    // A negative or NaN epsilon would silently turn off snapping and degeneracy checks
+    fn try_from(input: PrecisionInput) -> Result<Precision, GeometryError> {
+        if !(0.0..=MAX_EPSILON).contains(&input.epsilon) {
+            return Err(GeometryError::OutOfRange {
+                field: "precision.epsilon".to_string(),
+                min: 0.0,
+                max: MAX_EPSILON,
+            });
+        }
+        if input.decimals.is_some_and(|decimals| decimals > MAX_DECIMALS) {
+            return Err(GeometryError::OutOfRange {
+                field: "precision.decimals".to_string(),
+                min: 0.0,
+                max: MAX_DECIMALS as f64,
+            });
+        }
+        Ok(Precision {
+            epsilon: input.epsilon,
+            decimals: input.decimals,
+        })
+    }
+}
+
+impl Precision {
+    pub fn is_zero(&self, value: f64) -> bool {
+        value.abs() < self.epsilon
+    }
+
+    // Snaps values within epsilon of zero to zero
+    pub fn snap(&self, value: f64) -> f64 {
+        if self.is_zero(value) {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    pub fn round(&self, value: f64) -> f64 {
+        let value = self.snap(value);
+        match self.decimals {
+            Some(decimals) => {
+                let factor = 10f64.powi(decimals as i32);
+                (value * factor).round() / factor
+            }
+            None => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precision(epsilon: f64, decimals: Option<u32>) -> Result<Precision, GeometryError> {
+        Precision::try_from(PrecisionInput { epsilon, decimals })
+    }
+
+    #[test]
+    fn accepts_the_range_boundaries() {
+        assert!(precision(0.0, Some(0)).is_ok());
+        assert!(precision(MAX_EPSILON, Some(MAX_DECIMALS)).is_ok());
+        assert!(precision(1e-9, None).is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_epsilon() {
+        let error = GeometryError::OutOfRange {
+            field: "precision.epsilon".to_string(),
+            min: 0.0,
+            max: MAX_EPSILON,
+        };
+        assert_eq!(precision(-1e-12, None).unwrap_err(), error);
+        assert_eq!(precision(1.5, None).unwrap_err(), error);
+    }
+
+    #[test]
+    fn rejects_non_finite_epsilon() {
+        for epsilon in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(precision(epsilon, None).is_err(), "accepted {}", epsilon);
+        }
+    }
+
+    #[test]
+    fn rejects_too_many_decimals() {
+        assert_eq!(
+            precision(1e-9, Some(MAX_DECIMALS + 1)).unwrap_err(),
+            GeometryError::OutOfRange {
+                field: "precision.decimals".to_string(),
+                min: 0.0,
+                max: 15.0,
+            }
+        );
+        assert!(precision(1e-9, Some(u32::MAX)).is_err());
+    }
+
+    #[test]
+    fn deserializes_missing_fields_as_defaults() {
+        let precision: Precision = serde_json::from_str(r#"{"decimals":2}"#).unwrap();
+        assert_eq!(precision.epsilon, Precision::default().epsilon);
+        assert_eq!(precision.decimals, Some(2));
+        assert!(serde_json::from_str::<Precision>(r#"{"epsilon":-1}"#).is_err());
+    }
+
+    #[test]
+    fn rounds_to_the_requested_decimals() {
+        let precision = precision(1e-9, Some(2)).unwrap();
+        assert_eq!(precision.round(12.566), 12.57);
+        assert_eq!(precision.round(1e-12), 0.0);
+    }
+}
@@ -0,0 +1,427 @@
+use serde::Deserialize;
+use tiny_skia::{
+    Color as SkiaColor, FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform,
+};
+
+use crate::drawing::{Anchor, Baseline, Color, Drawing, DrawingOptions, Primitive, Style};
+use crate::error::DocumentError;
+use crate::units::UnitSettings;
+use crate::Point;
+
+// Keeps a single render well below a gigabyte of pixel memory
+const MAX_DIMENSION: u32 = 16384;
+
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(default)]
+pub(crate) struct RasterOptions {
+    // Output resolution; at the screen DPI the image matches the canvas pixel for pixel
+    pub dpi: f64,
+    // When given, the drawing is scaled to fit and centered instead of using `dpi` for scale
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(flatten)]
+    pub drawing: DrawingOptions,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        RasterOptions {
+            dpi: crate::units::DEFAULT_DPI,
+            width: None,
+            height: None,
+            drawing: DrawingOptions::default(),
+        }
+    }
+}
+
+// Paints the drawing into a PNG on the CPU, on a white background
+pub(crate) fn render_png(
+    drawing: &Drawing,
+    settings: &UnitSettings,
+    options: &RasterOptions,
+) -> Result<Vec<u8>, DocumentError> {
+    if !(options.dpi.is_finite() && options.dpi > 0.0) {
+        return Err(DocumentError::Render {
+            reason: "DPI must be a positive number".to_string(),
+        });
+    }
+    let (width, height) = (drawing.width(), drawing.height());
+    let fit = |size: Option<u32>, extent: f64| size.map(|size| size as f64 / extent);
+    let scale = match (fit(options.width, width), fit(options.height, height)) {
+        (Some(x), Some(y)) => x.min(y),
+        (Some(x), None) => x,
+        (None, Some(y)) => y,
+        // Internal pixels are screen pixels at the configured screen density
+        (None, None) => options.dpi / settings.dpi,
+    };
+    let image_width = options.width.unwrap_or((width * scale).ceil() as u32);
+    let image_height = options.height.unwrap_or((height * scale).ceil() as u32);
+    if image_width == 0 || image_height == 0 || image_width > MAX_DIMENSION || image_height > MAX_DIMENSION {
+        return Err(DocumentError::Render {
+            reason: format!(
+                "a {}x{} image is outside the supported 1..={} pixel range",
+                image_width, image_height, MAX_DIMENSION
+            ),
+        });
+    }
+
+    let mut pixmap = Pixmap::new(image_width, image_height).ok_or_else(|| DocumentError::Render {
+        reason: "could not allocate the image".to_string(),
+    })?;
+    pixmap.fill(SkiaColor::WHITE);
+
+    // Center the drawing and flip it so Y points up
+    let offset_x = (image_width as f64 - width * scale) / 2.0;
+    let offset_y = (image_height as f64 - height * scale) / 2.0;
+    let painter = Painter {
+        scale,
+        origin: Point {
+            x: drawing.bounds.top_left.x - offset_x / scale,
+            y: drawing.bounds.bottom_right.y + offset_y / scale,
+        },
+    };
+    for primitive in drawing.guides.iter().chain(&drawing.shapes) {
+        painter.paint(&mut pixmap, primitive);
+    }
+
+    let png = pixmap.encode_png().map_err(|err| DocumentError::Render {
+        reason: err.to_string(),
+    })?;
+    Ok(with_physical_size(png, options.dpi))
+}
+
+struct Painter {
+    // Output pixels per internal pixel
+    scale: f64,
+    // Internal coordinates of the image's top-left corner
+    origin: Point,
+}
+
+impl Painter {
+    fn map(&self, p: &Point) -> (f32, f32) {
+        (
+            ((p.x - self.origin.x) * self.scale) as f32,
+            ((self.origin.y - p.y) * self.scale) as f32,
+        )
+    }
+
+    fn paint(&self, pixmap: &mut Pixmap, primitive: &Primitive) {
+        match primitive {
+            Primitive::Line { from, to, style } => {
+                let mut path = PathBuilder::new();
+                let (x, y) = self.map(from);
+                path.move_to(x, y);
+                let (x, y) = self.map(to);
+                path.line_to(x, y);
+                if let Some(path) = path.finish() {
+                    self.draw(pixmap, &path, style);
+                }
+            }
+            Primitive::Polygon { points, holes, style } => {
+                let mut path = PathBuilder::new();
+                for ring in std::iter::once(points).chain(holes) {
+                    for (i, point) in ring.iter().enumerate() {
+                        let (x, y) = self.map(point);
+                        if i == 0 {
+                            path.move_to(x, y);
+                        } else {
+                            path.line_to(x, y);
+                        }
+                    }
+                    path.close();
+                }
+                if let Some(path) = path.finish() {
+                    self.draw(pixmap, &path, style);
+                }
+            }
+            Primitive::Circle { center, radius, style } => {
+                let (x, y) = self.map(center);
+                if let Some(path) = PathBuilder::from_circle(x, y, (radius * self.scale) as f32) {
+                    self.draw(pixmap, &path, style);
+                }
+            }
+            Primitive::Text { position, text, size, color, anchor, baseline } => {
+                self.text(pixmap, position, text, *size, *color, *anchor, *baseline);
+            }
+        }
+    }
+
+    fn draw(&self, pixmap: &mut Pixmap, path: &tiny_skia::Path, style: &Style) {
+        if let Some(fill) = style.fill {
+            // Even-odd so holes stay empty whichever way their rings are wound
+            pixmap.fill_path(path, &paint(fill), FillRule::EvenOdd, Transform::identity(), None);
+        }
+        if let Some(color) = style.stroke {
+            let stroke = Stroke {
+                width: (style.stroke_width * self.scale) as f32,
+                ..Stroke::default()
+            };
+            pixmap.stroke_path(path, &paint(color), &stroke, Transform::identity(), None);
+        }
+    }
+
+    // Draws text with the built-in stroke font; `size` is the em size in internal pixels
+    #[allow(clippy::too_many_arguments)]
+    fn text(
+        &self,
+        pixmap: &mut Pixmap,
+        position: &Point,
+        text: &str,
+        size: f64,
+        color: Color,
+        anchor: Anchor,
+        baseline: Baseline,
+    ) {
+        // Glyphs are drawn on a grid with a cap height of 6 units, at 70% of the em size
+        let unit = size * 0.7 / CAP_HEIGHT;
+        let count = text.chars().count() as f64;
+        let text_width = (count * ADVANCE - (ADVANCE - GLYPH_WIDTH)) * unit;
+        let start_x = match anchor {
+            Anchor::Start => position.x,
+            Anchor::Middle => position.x - text_width / 2.0,
+            Anchor::End => position.x - text_width,
+        };
+        let baseline_y = match baseline {
+            Baseline::Top => position.y - CAP_HEIGHT * unit,
+            Baseline::Middle => position.y - CAP_HEIGHT / 2.0 * unit,
+            Baseline::Bottom => position.y + DESCENDER * unit,
+        };
+
+        let mut path = PathBuilder::new();
+        for (i, character) in text.chars().enumerate() {
+            let origin_x = start_x + i as f64 * ADVANCE * unit;
+            for stroke in glyph(character) {
+                for (j, (gx, gy)) in stroke.iter().enumerate() {
+                    let (x, y) = self.map(&Point {
+                        x: origin_x + gx * unit,
+                        y: baseline_y + gy * unit,
+                    });
+                    if j == 0 {
+                        path.move_to(x, y);
+                    } else {
+                        path.line_to(x, y);
+                    }
+                }
+            }
+        }
+        if let Some(path) = path.finish() {
+            let stroke = Stroke {
+                width: (size / 10.0 * self.scale) as f32,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Round,
+                ..Stroke::default()
+            };
+            pixmap.stroke_path(&path, &paint(color), &stroke, Transform::identity(), None);
+        }
+    }
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(color.r, color.g, color.b, (color.a * 255.0).round() as u8);
+    paint.anti_alias = true;
+    paint
+}
+
+// Inserts a pHYs chunk after IHDR so viewers and printers know the physical size
+fn with_physical_size(png: Vec<u8>, dpi: f64) -> Vec<u8> {
+    // 8-byte signature, then IHDR: 4 length + 4 type + 13 data + 4 CRC
+    const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+    if png.len() < IHDR_END {
+        return png;
+    }
+    let pixels_per_meter = (dpi / 0.0254).round() as u32;
+    let mut chunk = Vec::with_capacity(21);
+    chunk.extend_from_slice(&9u32.to_be_bytes());
+    chunk.extend_from_slice(b"pHYs");
+    chunk.extend_from_slice(&pixels_per_meter.to_be_bytes());
+    chunk.extend_from_slice(&pixels_per_meter.to_be_bytes());
+    // Unit specifier: meters
+    chunk.push(1);
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+
+    let mut output = Vec::with_capacity(png.len() + chunk.len());
+    output.extend_from_slice(&png[..IHDR_END]);
+    output.extend_from_slice(&chunk);
+    output.extend_from_slice(&png[IHDR_END..]);
+    output
+}
+
+// CRC-32 as used by PNG chunks (ISO 3309, reflected polynomial 0xEDB88320)
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// Stroke font metrics, in glyph grid units
+const CAP_HEIGHT: f64 = 6.0;
+const DESCENDER: f64 = 2.0;
+const GLYPH_WIDTH: f64 = 4.0;
+const ADVANCE: f64 = 5.5;
+
+// Polylines making up a glyph, baseline at y = 0. Covers the characters the drawing's
+// labels use; anything else is drawn as a crossed box.
+fn glyph(character: char) -> &'static [&'static [(f64, f64)]] {
+    match character {
+        ' ' => &[],
+        '0' => &[&[(0.0, 0.0), (4.0, 0.0), (4.0, 6.0), (0.0, 6.0), (0.0, 0.0)]],
+        '1' => &[&[(1.0, 5.0), (2.0, 6.0), (2.0, 0.0)], &[(1.0, 0.0), (3.0, 0.0)]],
+        '2' => &[&[(0.0, 6.0), (4.0, 6.0), (4.0, 3.0), (0.0, 3.0), (0.0, 0.0), (4.0, 0.0)]],
+        '3' => &[&[(0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)], &[(1.0, 3.0), (4.0, 3.0)]],
+        '4' => &[&[(0.0, 6.0), (0.0, 3.0), (4.0, 3.0)], &[(4.0, 6.0), (4.0, 0.0)]],
+        '5' => &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0), (0.0, 0.0)]],
+        '6' => &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]],
+        '7' => &[&[(0.0, 6.0), (4.0, 6.0), (1.5, 0.0)]],
+        '8' => &[&[(0.0, 0.0), (4.0, 0.0), (4.0, 6.0), (0.0, 6.0), (0.0, 0.0)], &[(0.0, 3.0), (4.0, 3.0)]],
+        '9' => &[&[(0.0, 0.0), (4.0, 0.0), (4.0, 6.0), (0.0, 6.0), (0.0, 3.0), (4.0, 3.0)]],
+        '-' => &[&[(0.5, 3.0), (3.5, 3.0)]],
+        '+' => &[&[(0.5, 3.0), (3.5, 3.0)], &[(2.0, 1.5), (2.0, 4.5)]],
+        '.' => &[&[(2.0, 0.0), (2.0, 0.2)]],
+        ',' => &[&[(2.0, 0.5), (1.5, -1.0)]],
+        ':' => &[&[(2.0, 0.0), (2.0, 0.2)], &[(2.0, 3.8), (2.0, 4.0)]],
+        '(' => &[&[(3.0, 7.0), (1.5, 5.0), (1.5, 1.0), (3.0, -1.0)]],
+        ')' => &[&[(1.0, 7.0), (2.5, 5.0), (2.5, 1.0), (1.0, -1.0)]],
+        '²' => &[&[(0.5, 7.0), (3.0, 7.0), (3.0, 5.5), (0.5, 5.5), (0.5, 4.0), (3.0, 4.0)]],
+        '×' => &[&[(0.5, 1.0), (3.5, 4.0)], &[(0.5, 4.0), (3.5, 1.0)]],
+        '°' => &[&[(1.0, 6.0), (3.0, 6.0), (3.0, 4.5), (1.0, 4.5), (1.0, 6.0)]],
+        'a' => &[&[(0.0, 4.0), (4.0, 4.0), (4.0, 0.0), (0.0, 0.0), (0.0, 2.0), (4.0, 2.0)]],
+        'c' => &[&[(4.0, 4.0), (0.0, 4.0), (0.0, 0.0), (4.0, 0.0)]],
+        'd' => &[&[(4.0, 6.0), (4.0, 0.0), (0.0, 0.0), (0.0, 4.0), (4.0, 4.0)]],
+        'e' => &[&[(4.0, 0.0), (0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 2.0), (0.0, 2.0)]],
+        'f' => &[&[(4.0, 6.0), (1.5, 6.0), (1.5, 0.0)], &[(0.0, 4.0), (3.5, 4.0)]],
+        'g' => &[&[(4.0, 1.0), (0.0, 1.0), (0.0, 4.0), (4.0, 4.0), (4.0, -2.0), (0.0, -2.0)]],
+        'h' => &[&[(0.0, 6.0), (0.0, 0.0)], &[(0.0, 4.0), (4.0, 4.0), (4.0, 0.0)]],
+        'i' => &[&[(2.0, 0.0), (2.0, 4.0)], &[(2.0, 5.5), (2.0, 6.0)]],
+        'm' => &[&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0)], &[(2.0, 4.0), (2.0, 0.0)]],
+        'n' => &[&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0)]],
+        'o' => &[&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)]],
+        'p' => &[&[(0.0, -2.0), (0.0, 4.0), (4.0, 4.0), (4.0, 1.0), (0.0, 1.0)]],
+        'r' => &[&[(0.0, 0.0), (0.0, 4.0)], &[(0.0, 3.0), (1.0, 4.0), (4.0, 4.0)]],
+        's' => &[&[(4.0, 4.0), (0.0, 4.0), (0.0, 2.0), (4.0, 2.0), (4.0, 0.0), (0.0, 0.0)]],
+        't' => &[&[(1.5, 6.0), (1.5, 0.0), (4.0, 0.0)], &[(0.0, 4.0), (3.5, 4.0)]],
+        'u' => &[&[(0.0, 4.0), (0.0, 0.0), (4.0, 0.0), (4.0, 4.0)]],
+        'x' => &[&[(0.0, 0.0), (4.0, 4.0)], &[(0.0, 4.0), (4.0, 0.0)]],
+        'C' => &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 0.0), (4.0, 0.0)]],
+        'L' => &[&[(0.0, 6.0), (0.0, 0.0), (4.0, 0.0)]],
+        'R' => &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 3.0), (0.0, 3.0)], &[(1.5, 3.0), (4.0, 0.0)]],
+        'S' => &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0), (0.0, 0.0)]],
+        _ => &[
+            &[(0.0, 0.0), (4.0, 0.0), (4.0, 6.0), (0.0, 6.0), (0.0, 0.0)],
+            &[(0.0, 0.0), (4.0, 6.0)],
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{Document, DocumentProperties, GridSettings, Metadata};
+    use crate::scene::{Scene, SceneShape};
+    use crate::units::MeasurementUnit;
+    use crate::{Rectangle, Shape};
+
+    // Where the pHYs chunk starts, right after IHDR
+    const PHYS_START: usize = 8 + 4 + 4 + 13 + 4;
+
+    fn drawing() -> Drawing {
+        let mut scene = Scene::default();
+        let layer = scene.layers()[0].id;
+        let rect = Rectangle {
+            top_left: Point { x: 0.0, y: 0.0 },
+            bottom_right: Point { x: 200.0, y: 100.0 },
+        };
+        scene.insert(0, SceneShape::new(Shape::Rectangle(rect), layer));
+        let document = Document {
+            scene,
+            properties: DocumentProperties {
+                grid: GridSettings {
+                    grid_size: 20.0,
+                    unit: MeasurementUnit::Pixel,
+                    show_grid: true,
+                    snap_to_grid: false,
+                    conversion_factor: 1.0,
+                },
+                unit: MeasurementUnit::Pixel,
+                metadata: Metadata::default(),
+            },
+        };
+        let options = DrawingOptions { margin: 10.0, ..DrawingOptions::default() };
+        Drawing::new(&document, &UnitSettings::default(), &options).unwrap()
+    }
+
+    fn render(options: RasterOptions) -> Result<Vec<u8>, DocumentError> {
+        render_png(&drawing(), &UnitSettings::default(), &options)
+    }
+
+    fn be_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    // Width and height from IHDR, and pixels per meter from pHYs
+    fn header(png: &[u8]) -> (u32, u32, u32) {
+        assert_eq!(&png[PHYS_START..PHYS_START + 8], b"\0\0\0\x09pHYs");
+        let data = &png[PHYS_START + 4..PHYS_START + 17];
+        assert_eq!(be_u32(data, 4), be_u32(data, 8));
+        assert_eq!(data[12], 1, "pixels per meter");
+        assert_eq!(be_u32(png, PHYS_START + 17), crc32(data));
+        (be_u32(png, 16), be_u32(png, 20), be_u32(data, 4))
+    }
+
+    #[test]
+    fn renders_at_the_requested_dpi() {
+        let drawing = drawing();
+        let (width, height) = (drawing.width().ceil() as u32, drawing.height().ceil() as u32);
+        let png = render(RasterOptions::default()).unwrap();
+        assert_eq!(header(&png), (width, height, 3780));
+
+        let png = render(RasterOptions { dpi: 192.0, ..RasterOptions::default() }).unwrap();
+        assert_eq!(header(&png), (2 * width, 2 * height, 7559));
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (2 * width, 2 * height));
+    }
+
+    #[test]
+    fn fits_the_drawing_into_a_requested_size() {
+        let drawing = drawing();
+        let png = render(RasterOptions { width: Some(110), ..RasterOptions::default() }).unwrap();
+        let (width, height, _) = header(&png);
+        assert_eq!(width, 110);
+        assert_eq!(height, (drawing.height() * 110.0 / drawing.width()).ceil() as u32);
+
+        // Both given, the drawing is scaled to fit and centered in exactly that size
+        let square = RasterOptions { width: Some(300), height: Some(300), ..RasterOptions::default() };
+        let png = render(square).unwrap();
+        let (width, height, _) = header(&png);
+        assert_eq!((width, height), (300, 300));
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        let corner = pixmap.pixel(0, 0).unwrap();
+        assert_eq!((corner.red(), corner.green(), corner.blue()), (255, 255, 255));
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for dpi in [0.0, -1.0, f64::NAN] {
+            let options = RasterOptions { dpi, ..RasterOptions::default() };
+            assert!(matches!(render(options), Err(DocumentError::Render { .. })), "{}", dpi);
+        }
+        let too_large = RasterOptions { width: Some(MAX_DIMENSION + 1), ..RasterOptions::default() };
+        assert!(matches!(render(too_large), Err(DocumentError::Render { .. })));
+        let empty = RasterOptions { height: Some(0), ..RasterOptions::default() };
+        assert!(matches!(render(empty), Err(DocumentError::Render { .. })));
+    }
+
+    #[test]
+    fn computes_png_checksums() {
+        // The check value of CRC-32/ISO-HDLC
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
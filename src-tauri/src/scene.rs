@@ -0,0 +1,470 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::SceneError;
+use crate::Shape;
+
+// Color of new layers unless another is given
+pub(crate) const DEFAULT_LAYER_COLOR: &str = "#000000";
+
+// A shape in the scene, with an id that stays the same across edits, saves and reloads
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct SceneShape {
+    pub id: Uuid,
+    pub layer: Uuid,
+    // The innermost group holding the shape, if any
+    pub group: Option<Uuid>,
+    pub shape: Shape,
+}
+
+impl SceneShape {
+    // Gives `shape` a new id
+    pub fn new(shape: Shape, layer: Uuid) -> SceneShape {
+        SceneShape { id: Uuid::new_v4(), layer, group: None, shape }
+    }
+}
+
+// A named set of shapes, e.g. walls or annotations, that is shown, locked and stacked as one
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct Layer {
+    pub id: Uuid,
+    pub name: String,
+    // `#rrggbb`
+    pub color: String,
+    pub visible: bool,
+    // Shapes on a locked layer cannot be added, edited, moved or deleted
+    pub locked: bool,
+}
+
+impl Layer {
+    pub fn new(name: String, color: String) -> Layer {
+        Layer {
+            id: Uuid::new_v4(),
+            name,
+            color,
+            visible: true,
+            locked: false,
+        }
+    }
+
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        parse_color(&self.color).unwrap_or((0, 0, 0))
+    }
+}
+
+// A node of the scene tree that moves, transforms and measures its shapes and nested groups as
+// one. Members may sit on different layers.
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct Group {
+    pub id: Uuid,
+    // The group this one is nested in, if any
+    pub parent: Option<Uuid>,
+}
+
+impl Group {
+    pub fn new(parent: Option<Uuid>) -> Group {
+        Group { id: Uuid::new_v4(), parent }
+    }
+}
+
+// Reads a `#rrggbb` color
+pub(crate) fn parse_color(color: &str) -> Option<(u8, u8, u8)> {
+    // `from_str_radix` would also take a sign, as in `#+1+2+3`
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()))?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+// The layers, groups and shapes of the open drawing. Layers stack bottom first, and shapes paint
+// in layer order, then in the order they were added. Groups only nest shapes and each other and
+// do not affect painting. The app keeps one scene in managed state so
+// drawing, measurement, persistence and export all work from the same shapes.
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub(crate) struct Scene {
+    layers: Vec<Layer>,
+    groups: Vec<Group>,
+    shapes: Vec<SceneShape>,
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene {
+            layers: vec![Layer::new("Layer 1".to_string(), DEFAULT_LAYER_COLOR.to_string())],
+            groups: Vec::new(),
+            shapes: Vec::new(),
+        }
+    }
+}
+
+impl Scene {
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    // Every shape in the order it was added, regardless of layer
+    pub fn shapes(&self) -> &[SceneShape] {
+        &self.shapes
+    }
+
+    // Every shape in painting order, bottom first
+    pub fn ordered(&self) -> Vec<&SceneShape> {
+        let mut ordered: Vec<&SceneShape> = self.shapes.iter().collect();
+        ordered.sort_by_key(|item| self.layer_index(item.layer).unwrap_or(usize::MAX));
+        ordered
+    }
+
+    // The shapes on visible layers in painting order, as exported
+    pub fn visible(&self) -> Vec<&SceneShape> {
+        self.ordered()
+            .into_iter()
+            .filter(|item| self.layer(item.layer).is_ok_and(|layer| layer.visible))
+            .collect()
+    }
+
+    // Why a loaded scene cannot be edited safely, e.g. a shape on a layer that does not exist
+    pub fn problem(&self) -> Option<String> {
+        if self.layers.is_empty() {
+            return Some("a drawing needs at least one layer".to_string());
+        }
+        let mut seen = HashSet::new();
+        if let Some(layer) = self.layers.iter().find(|layer| !seen.insert(layer.id)) {
+            return Some(format!("layer id {} is used more than once", layer.id));
+        }
+        if let Some(layer) = self.layers.iter().find(|layer| parse_color(&layer.color).is_none()) {
+            return Some(format!("layer '{}' has invalid color '{}'", layer.name, layer.color));
+        }
+        let mut seen = HashSet::new();
+        if let Some(group) = self.groups.iter().find(|group| !seen.insert(group.id)) {
+            return Some(format!("group id {} is used more than once", group.id));
+        }
+        if let Some(group) = self.groups.iter().find(|group| group.parent.is_some_and(|id| self.group(id).is_err())) {
+            return Some(format!("group {} is in missing group {}", group.id, group.parent.unwrap()));
+        }
+        // Following parents from any group must end at the top within as many steps as there are groups
+        if let Some(group) = self.groups.iter().find(|group| {
+            std::iter::successors(group.parent, |&id| self.group(id).ok().and_then(|group| group.parent))
+                .nth(self.groups.len())
+                .is_some()
+        }) {
+            return Some(format!("group {} is nested in itself", group.id));
+        }
+        let mut seen = HashSet::new();
+        if let Some(item) = self.shapes.iter().find(|item| !seen.insert(item.id)) {
+            return Some(format!("shape id {} is used more than once", item.id));
+        }
+        if let Some(item) = self.shapes.iter().find(|item| item.group.is_some_and(|id| self.group(id).is_err())) {
+            return Some(format!("shape {} is in missing group {}", item.id, item.group.unwrap()));
+        }
+        self.shapes
+            .iter()
+            .find(|item| self.layer(item.layer).is_err())
+            .map(|item| format!("shape {} is on missing layer {}", item.id, item.layer))
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<&SceneShape, SceneError> {
+        Ok(&self.shapes[self.index(id)?])
+    }
+
+    // Puts `item` at `index` among all shapes, or last if the scene has fewer shapes
+    pub fn insert(&mut self, index: usize, item: SceneShape) {
+        self.shapes.insert(index.min(self.shapes.len()), item);
+    }
+
+    // Replaces the geometry of a shape, keeping its id and place in the painting order
+    pub fn update(&mut self, id: Uuid, shape: Shape) -> Result<SceneShape, SceneError> {
+        let index = self.index(id)?;
+        self.shapes[index].shape = shape;
+        Ok(self.shapes[index].clone())
+    }
+
+    pub fn set_layer(&mut self, id: Uuid, layer: Uuid) -> Result<SceneShape, SceneError> {
+        let index = self.index(id)?;
+        self.shapes[index].layer = layer;
+        Ok(self.shapes[index].clone())
+    }
+
+    // Moves a shape into `group`, or out of every group when `None`
+    pub fn set_group(&mut self, id: Uuid, group: Option<Uuid>) -> Result<SceneShape, SceneError> {
+        let index = self.index(id)?;
+        self.shapes[index].group = group;
+        Ok(self.shapes[index].clone())
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Result<SceneShape, SceneError> {
+        let index = self.index(id)?;
+        Ok(self.shapes.remove(index))
+    }
+
+    pub fn index(&self, id: Uuid) -> Result<usize, SceneError> {
+        self.shapes
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or(SceneError::NotFound { id })
+    }
+
+    pub fn layer(&self, id: Uuid) -> Result<&Layer, SceneError> {
+        Ok(&self.layers[self.layer_index(id)?])
+    }
+
+    // The layer, unless it is locked
+    pub fn unlocked_layer(&self, id: Uuid) -> Result<&Layer, SceneError> {
+        let layer = self.layer(id)?;
+        if layer.locked {
+            return Err(SceneError::LayerLocked { name: layer.name.clone() });
+        }
+        Ok(layer)
+    }
+
+    // Puts `layer` at `index` in the stack, or on top if there are fewer layers
+    pub fn insert_layer(&mut self, index: usize, layer: Layer) {
+        self.layers.insert(index.min(self.layers.len()), layer);
+    }
+
+    // Replaces the name, color, visibility and lock of the layer with the same id
+    pub fn update_layer(&mut self, layer: Layer) -> Result<Layer, SceneError> {
+        let index = self.layer_index(layer.id)?;
+        self.layers[index] = layer.clone();
+        Ok(layer)
+    }
+
+    // Moves a layer to `index` in the stack, counted after taking it out
+    pub fn move_layer(&mut self, id: Uuid, index: usize) -> Result<(), SceneError> {
+        let layer = self.remove_layer(id)?;
+        self.insert_layer(index, layer);
+        Ok(())
+    }
+
+    pub fn remove_layer(&mut self, id: Uuid) -> Result<Layer, SceneError> {
+        let index = self.layer_index(id)?;
+        Ok(self.layers.remove(index))
+    }
+
+    pub fn layer_index(&self, id: Uuid) -> Result<usize, SceneError> {
+        self.layers
+            .iter()
+            .position(|layer| layer.id == id)
+            .ok_or(SceneError::LayerNotFound { id })
+    }
+
+    pub fn group(&self, id: Uuid) -> Result<&Group, SceneError> {
+        Ok(&self.groups[self.group_index(id)?])
+    }
+
+    // The shapes and groups directly inside a group
+    pub fn members(&self, id: Uuid) -> (Vec<&SceneShape>, Vec<&Group>) {
+        (
+            self.shapes.iter().filter(|item| item.group == Some(id)).collect(),
+            self.groups.iter().filter(|group| group.parent == Some(id)).collect(),
+        )
+    }
+
+    // Whether `id` is `ancestor` or nested in it at any depth
+    pub fn is_within(&self, id: Uuid, ancestor: Uuid) -> bool {
+        std::iter::successors(Some(id), |&id| self.group(id).ok().and_then(|group| group.parent))
+            .any(|id| id == ancestor)
+    }
+
+    // Every shape in a group and its nested groups, in painting order
+    pub fn descendants(&self, id: Uuid) -> Result<Vec<&SceneShape>, SceneError> {
+        self.group(id)?;
+        Ok(self
+            .ordered()
+            .into_iter()
+            .filter(|item| item.group.is_some_and(|group| self.is_within(group, id)))
+            .collect())
+    }
+
+    // Puts `group` at `index` among all groups, or last if there are fewer
+    pub fn insert_group(&mut self, index: usize, group: Group) {
+        self.groups.insert(index.min(self.groups.len()), group);
+    }
+
+    // Nests a group in `parent`, or at the top when `None`
+    pub fn set_parent(&mut self, id: Uuid, parent: Option<Uuid>) -> Result<(), SceneError> {
+        let index = self.group_index(id)?;
+        self.groups[index].parent = parent;
+        Ok(())
+    }
+
+    pub fn remove_group(&mut self, id: Uuid) -> Result<Group, SceneError> {
+        let index = self.group_index(id)?;
+        Ok(self.groups.remove(index))
+    }
+
+    pub fn group_index(&self, id: Uuid) -> Result<usize, SceneError> {
+        self.groups
+            .iter()
+            .position(|group| group.id == id)
+            .ok_or(SceneError::GroupNotFound { id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, Rectangle};
+
+    fn square(x: f64) -> Shape {
+        Shape::Rectangle(Rectangle {
+            top_left: Point { x, y: 0.0 },
+            bottom_right: Point { x: x + 10.0, y: 10.0 },
+        })
+    }
+
+    // Layers "Layer 1" and "Walls" above it, with shapes added alternately to each
+    fn two_layers() -> (Scene, Uuid, Uuid, Vec<Uuid>) {
+        let mut scene = Scene::default();
+        let base = scene.layers()[0].id;
+        let walls = Layer::new("Walls".to_string(), "#ff0000".to_string());
+        let walls_id = walls.id;
+        scene.insert_layer(1, walls);
+        let mut ids = Vec::new();
+        for (i, layer) in [walls_id, base, walls_id, base].into_iter().enumerate() {
+            let item = SceneShape::new(square(i as f64 * 20.0), layer);
+            ids.push(item.id);
+            scene.insert(i, item);
+        }
+        (scene, base, walls_id, ids)
+    }
+
+    fn ids(items: Vec<&SceneShape>) -> Vec<Uuid> {
+        items.into_iter().map(|item| item.id).collect()
+    }
+
+    #[test]
+    fn paints_by_layer_then_by_order_added() {
+        let (mut scene, base, walls, s) = two_layers();
+        assert_eq!(ids(scene.ordered()), [s[1], s[3], s[0], s[2]]);
+
+        scene.move_layer(walls, 0).unwrap();
+        assert_eq!(scene.layers()[0].id, walls);
+        assert_eq!(ids(scene.ordered()), [s[0], s[2], s[1], s[3]]);
+
+        // Indexes past the top put the layer on top
+        scene.move_layer(walls, 10).unwrap();
+        assert_eq!(scene.layer_index(walls).unwrap(), 1);
+        assert_eq!(scene.layer_index(base).unwrap(), 0);
+    }
+
+    #[test]
+    fn hidden_layers_are_not_visible() {
+        let (mut scene, _, walls, s) = two_layers();
+        let mut layer = scene.layer(walls).unwrap().clone();
+        layer.visible = false;
+        scene.update_layer(layer).unwrap();
+        assert_eq!(ids(scene.visible()), [s[1], s[3]]);
+        assert_eq!(scene.ordered().len(), 4);
+    }
+
+    #[test]
+    fn locked_layers_are_refused() {
+        let (mut scene, base, walls, _) = two_layers();
+        let mut layer = scene.layer(walls).unwrap().clone();
+        layer.locked = true;
+        scene.update_layer(layer).unwrap();
+        assert!(scene.unlocked_layer(base).is_ok());
+        match scene.unlocked_layer(walls) {
+            Err(SceneError::LayerLocked { name }) => assert_eq!(name, "Walls"),
+            result => panic!("expected a locked layer, got {:?}", result),
+        }
+        let missing = Uuid::new_v4();
+        assert!(matches!(scene.unlocked_layer(missing), Err(SceneError::LayerNotFound { id }) if id == missing));
+    }
+
+    #[test]
+    fn reads_layer_colors() {
+        assert_eq!(parse_color("#336699"), Some((0x33, 0x66, 0x99)));
+        assert_eq!(parse_color("#FFffFF"), Some((255, 255, 255)));
+        for color in ["336699", "#369", "#33669g", "#3366990", "#+1+2+3", "#ééé"] {
+            assert_eq!(parse_color(color), None, "{}", color);
+        }
+    }
+
+    #[test]
+    fn finds_problems_with_layers() {
+        let (scene, _, walls, s) = two_layers();
+        assert_eq!(scene.problem(), None);
+
+        let mut missing = scene.clone();
+        missing.remove_layer(walls).unwrap();
+        let problem = missing.problem().unwrap();
+        assert!(problem.contains(&s[0].to_string()) && problem.contains("missing layer"), "{}", problem);
+
+        let mut duplicate = scene.clone();
+        duplicate.insert_layer(2, scene.layer(walls).unwrap().clone());
+        assert!(duplicate.problem().unwrap().contains("used more than once"));
+
+        let mut colored = scene.clone();
+        let mut layer = scene.layer(walls).unwrap().clone();
+        layer.color = "red".to_string();
+        colored.update_layer(layer).unwrap();
+        assert_eq!(colored.problem().unwrap(), "layer 'Walls' has invalid color 'red'");
+
+        let empty = Scene { layers: Vec::new(), groups: Vec::new(), shapes: Vec::new() };
+        assert!(empty.problem().is_some());
+    }
+
+    // `inner` nested in `outer`, with shapes in `inner`, in `outer` and in neither
+    fn nested() -> (Scene, Uuid, Uuid, Vec<Uuid>) {
+        let mut scene = Scene::default();
+        let layer = scene.layers()[0].id;
+        let outer = Group::new(None);
+        let inner = Group::new(Some(outer.id));
+        let (outer_id, inner_id) = (outer.id, inner.id);
+        scene.insert_group(0, outer);
+        scene.insert_group(1, inner);
+        let mut ids = Vec::new();
+        for (i, group) in [Some(inner_id), Some(outer_id), None].into_iter().enumerate() {
+            let item = SceneShape { group, ..SceneShape::new(square(i as f64 * 20.0), layer) };
+            ids.push(item.id);
+            scene.insert(i, item);
+        }
+        (scene, outer_id, inner_id, ids)
+    }
+
+    #[test]
+    fn walks_nested_groups() {
+        let (scene, outer, inner, s) = nested();
+        let (shapes, groups) = scene.members(outer);
+        assert_eq!(ids(shapes), [s[1]]);
+        assert_eq!(groups.iter().map(|group| group.id).collect::<Vec<_>>(), [inner]);
+        assert!(scene.is_within(inner, outer));
+        assert!(scene.is_within(outer, outer));
+        assert!(!scene.is_within(outer, inner));
+        assert_eq!(ids(scene.descendants(outer).unwrap()), [s[0], s[1]]);
+        assert_eq!(ids(scene.descendants(inner).unwrap()), [s[0]]);
+        let missing = Uuid::new_v4();
+        assert!(matches!(scene.descendants(missing), Err(SceneError::GroupNotFound { id }) if id == missing));
+    }
+
+    #[test]
+    fn finds_problems_with_groups() {
+        let (scene, outer, inner, s) = nested();
+        assert_eq!(scene.problem(), None);
+
+        let mut cycle = scene.clone();
+        cycle.set_parent(outer, Some(inner)).unwrap();
+        assert!(cycle.problem().unwrap().contains("nested in itself"));
+
+        let mut own_parent = scene.clone();
+        own_parent.set_parent(outer, Some(outer)).unwrap();
+        assert!(own_parent.problem().unwrap().contains("nested in itself"));
+
+        let mut orphaned = scene.clone();
+        orphaned.remove_group(outer).unwrap();
+        assert_eq!(orphaned.problem().unwrap(), format!("group {} is in missing group {}", inner, outer));
+
+        let mut stray = scene.clone();
+        stray.remove_group(inner).unwrap();
+        stray.set_parent(outer, None).unwrap();
+        assert_eq!(stray.problem().unwrap(), format!("shape {} is in missing group {}", s[0], inner));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::Write;
+
+use crate::drawing::{format_number, Anchor, Baseline, Drawing, Primitive, Style};
+use crate::Point;
+
+// Decimal places kept for coordinates, far below print resolution in any unit
+const DECIMALS: usize = 6;
+
+// Class of the group holding the grid and axes, which `svg_import` skips
+pub(crate) const GUIDES_CLASS: &str = "guides";
+
+// Writes the drawing as a standalone SVG 1.1 file sized in the drawing's real-world unit.
+// SVG's Y axis points down, so points are mirrored to keep the drawing's Y-up orientation.
+pub(crate) fn render(drawing: &Drawing, title: Option<&str>) -> String {
+    let scale = 1.0 / drawing.pixels_per_unit;
+    let unit = drawing.unit.symbol();
+    let num = |value: f64| format_number(value * scale, DECIMALS);
+
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{w}{unit}\" height=\"{h}{unit}\" viewBox=\"{x} {y} {w} {h}\">",
+        w = num(drawing.width()),
+        h = num(drawing.height()),
+        x = num(drawing.bounds.top_left.x),
+        y = num(-drawing.bounds.bottom_right.y),
+        unit = unit,
+    );
+    if let Some(title) = title {
+        let _ = writeln!(svg, "  <title>{}</title>", escape(title));
+    }
+
+    if !drawing.guides.is_empty() {
+        let _ = writeln!(svg, "  <g class=\"{}\">", GUIDES_CLASS);
+        write_primitives(&mut svg, &drawing.guides, scale);
+        svg.push_str("  </g>\n");
+    }
+    write_primitives(&mut svg, &drawing.shapes, scale);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_primitives(svg: &mut String, primitives: &[Primitive], scale: f64) {
+    let num = |value: f64| format_number(value * scale, DECIMALS);
+    let point = |p: &Point| format!("{},{}", num(p.x), num(-p.y));
+    for primitive in primitives {
+        match primitive {
+            Primitive::Line { from, to, style } => {
+                let _ = writeln!(
+                    svg,
+                    "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{}/>",
+                    num(from.x),
+                    num(-from.y),
+                    num(to.x),
+                    num(-to.y),
+                    style_attributes(style, scale)
+                );
+            }
+            Primitive::Polygon { points, holes, style } if holes.is_empty() => {
+                let points: Vec<String> = points.iter().map(point).collect();
+                let _ = writeln!(
+                    svg,
+                    "  <polygon points=\"{}\"{}/>",
+                    points.join(" "),
+                    style_attributes(style, scale)
+                );
+            }
+            // One subpath per ring; `svg_import` reads rings nested by the even-odd rule back as holes
+            Primitive::Polygon { points, holes, style } => {
+                let mut data = String::new();
+                for ring in std::iter::once(points).chain(holes) {
+                    let ring: Vec<String> = ring.iter().map(point).collect();
+                    let _ = write!(data, "M{}Z", ring.join(" L"));
+                }
+                let _ = writeln!(
+                    svg,
+                    "  <path d=\"{}\" fill-rule=\"evenodd\"{}/>",
+                    data,
+                    style_attributes(style, scale)
+                );
+            }
+            Primitive::Circle { center, radius, style } => {
+                let _ = writeln!(
+                    svg,
+                    "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\"{}/>",
+                    num(center.x),
+                    num(-center.y),
+                    num(*radius),
+                    style_attributes(style, scale)
+                );
+            }
+            Primitive::Text { position, text, size, color, anchor, baseline } => {
+                let anchor = match anchor {
+                    Anchor::Start => "start",
+                    Anchor::Middle => "middle",
+                    Anchor::End => "end",
+                };
+                let baseline = match baseline {
+                    Baseline::Top => "hanging",
+                    Baseline::Middle => "central",
+                    Baseline::Bottom => "text-after-edge",
+                };
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{}\" y=\"{}\" font-family=\"Arial, sans-serif\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\" dominant-baseline=\"{}\">{}</text>",
+                    num(position.x),
+                    num(-position.y),
+                    num(*size),
+                    color.hex(),
+                    anchor,
+                    baseline,
+                    escape(text)
+                );
+            }
+        }
+    }
+}
+
+fn style_attributes(style: &Style, scale: f64) -> String {
+    let mut attributes = String::new();
+    match style.fill {
+        Some(fill) => {
+            let _ = write!(attributes, " fill=\"{}\"", fill.hex());
+            if fill.a < 1.0 {
+                let _ = write!(attributes, " fill-opacity=\"{}\"", format_number(fill.a, 3));
+            }
+        }
+        None => attributes.push_str(" fill=\"none\""),
+    }
+    if let Some(stroke) = style.stroke {
+        let _ = write!(
+            attributes,
+            " stroke=\"{}\" stroke-width=\"{}\"",
+            stroke.hex(),
+            format_number(style.stroke_width * scale, DECIMALS)
+        );
+        if stroke.a < 1.0 {
+            let _ = write!(attributes, " stroke-opacity=\"{}\"", format_number(stroke.a, 3));
+        }
+    }
+    attributes
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,893 @@
+use std::f64::consts::PI;
+
+use roxmltree::{Document as XmlDocument, Node};
+use serde::Serialize;
+
+use crate::error::DocumentError;
+use crate::flatten;
+use crate::precision::Precision;
+use crate::svg;
+use crate::transform::{map_ellipse, Transform};
+use crate::units::{MeasurementUnit, UnitSettings};
+use crate::{nest_rings, Circle, Ellipse, OrientedRectangle, Point, Polygon, Polyline, Rectangle, Segment, Shape};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+// Elements that hold resources or metadata rather than drawn content
+const NON_RENDERED: &[&str] = &[
+    "defs",
+    "clipPath",
+    "mask",
+    "pattern",
+    "symbol",
+    "marker",
+    "linearGradient",
+    "radialGradient",
+    "filter",
+    "style",
+    "script",
+    "metadata",
+    "title",
+    "desc",
+];
+
+#[derive(Serialize,Debug)]
+pub(crate) struct SvgImport {
+    pub shapes: Vec<Shape>,
+    // Elements that were imported only approximately, e.g. rounded rectangle corners
+    pub warnings: Vec<ImportIssue>,
+    // Elements that could not be converted and were left out
+    pub unsupported: Vec<ImportIssue>,
+}
+
+#[derive(Serialize,Debug)]
+pub(crate) struct ImportIssue {
+    pub element: String,
+    pub id: Option<String>,
+    pub reason: String,
+}
+
+// Reads `<rect>`, `<circle>`, `<ellipse>`, `<line>`, `<polygon>`, `<polyline>` and `<path>` elements
+// into shapes in internal pixels, Y up. Curves are flattened into polygons within `tolerance` pixels.
+pub(crate) fn import(text: &str, settings: &UnitSettings, tolerance: f64) -> Result<SvgImport, DocumentError> {
+    let xml = XmlDocument::parse(text).map_err(|err| DocumentError::Malformed {
+        format: "SVG",
+        reason: err.to_string(),
+    })?;
+    let root = xml.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(DocumentError::Malformed {
+            format: "SVG",
+            reason: "the root element is not <svg>".to_string(),
+        });
+    }
+
+    let scale = user_unit_scale(&root, settings);
+    let mut importer = Importer {
+        settings,
+        tolerance,
+        result: SvgImport {
+            shapes: Vec::new(),
+            warnings: Vec::new(),
+            unsupported: Vec::new(),
+        },
+    };
+    // SVG's Y axis points down, the drawing's points up
+    importer.visit_children(&root, Matrix::scale(scale, -scale));
+    Ok(importer.result)
+}
+
+// Internal pixels per SVG user unit, from the root's `width` and `viewBox`
+fn user_unit_scale(root: &Node, settings: &UnitSettings) -> f64 {
+    let view_box_width = root
+        .attribute("viewBox")
+        .and_then(|view_box| parse_numbers(view_box).ok())
+        .filter(|numbers| numbers.len() == 4 && numbers[2] > 0.0)
+        .map(|numbers| numbers[2]);
+    let width = root.attribute("width").and_then(|width| parse_length(width, settings));
+    match (width, view_box_width) {
+        (Some(width), Some(view_box_width)) => width / view_box_width,
+        _ => 1.0,
+    }
+}
+
+// A length with an optional absolute unit, in internal pixels; CSS pixels map one to one
+fn parse_length(text: &str, settings: &UnitSettings) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E' || c == '%')
+        .unwrap_or(text.len());
+    let value: f64 = text[..split].trim().parse().ok()?;
+    let inch = MeasurementUnit::Inch.pixels_per_unit(settings.dpi);
+    let factor = match text[split..].trim() {
+        "" | "px" => 1.0,
+        "in" => inch,
+        "cm" => MeasurementUnit::Centimeter.pixels_per_unit(settings.dpi),
+        "mm" => MeasurementUnit::Millimeter.pixels_per_unit(settings.dpi),
+        "pt" => inch / 72.0,
+        "pc" => inch / 6.0,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+struct Importer<'a> {
+    settings: &'a UnitSettings,
+    tolerance: f64,
+    result: SvgImport,
+}
+
+impl Importer<'_> {
+    fn visit_children(&mut self, parent: &Node, matrix: Matrix) {
+        for child in parent.children().filter(Node::is_element) {
+            self.visit(&child, matrix);
+        }
+    }
+
+    fn visit(&mut self, node: &Node, parent_matrix: Matrix) {
+        let tag = node.tag_name();
+        // Editor-specific elements such as `sodipodi:namedview` carry no geometry
+        if tag.namespace().is_some_and(|ns| ns != SVG_NAMESPACE) {
+            return;
+        }
+        let name = tag.name();
+        if NON_RENDERED.contains(&name) || node.attribute("display") == Some("none") {
+            return;
+        }
+        // Grid and axes written by our own SVG export
+        if node.attribute("class") == Some(svg::GUIDES_CLASS) {
+            return;
+        }
+
+        let matrix = match node.attribute("transform").map(Matrix::parse) {
+            None => parent_matrix,
+            Some(Ok(transform)) => parent_matrix.multiply(&transform),
+            Some(Err(reason)) => return self.unsupported(node, reason),
+        };
+        let outcome = match name {
+            "g" | "a" | "svg" | "switch" => {
+                self.visit_children(node, matrix);
+                Ok(())
+            }
+            "rect" => self.rect(node, &matrix),
+            "circle" => self.ellipse(node, &matrix, "r", "r"),
+            "ellipse" => self.ellipse(node, &matrix, "rx", "ry"),
+            "line" => self.line(node, &matrix),
+            "polygon" | "polyline" => self.poly(node, &matrix, name == "polyline"),
+            "path" => self.path(node, &matrix),
+            _ => Err(format!("<{}> elements are not supported", name)),
+        };
+        if let Err(reason) = outcome {
+            self.unsupported(node, reason);
+        }
+    }
+
+    fn number(&self, node: &Node, attribute: &str) -> Result<f64, String> {
+        match node.attribute(attribute) {
+            None => Ok(0.0),
+            Some(text) => parse_length(text, self.settings)
+                .ok_or_else(|| format!("unsupported {} value '{}'", attribute, text)),
+        }
+    }
+
+    fn rect(&mut self, node: &Node, matrix: &Matrix) -> Result<(), String> {
+        let x = self.number(node, "x")?;
+        let y = self.number(node, "y")?;
+        let width = self.number(node, "width")?;
+        let height = self.number(node, "height")?;
+        if width <= 0.0 || height <= 0.0 {
+            return Err("rectangle has no area".to_string());
+        }
+        if self.number(node, "rx")? > 0.0 || self.number(node, "ry")? > 0.0 {
+            self.warning(node, "rounded corners were imported as square corners");
+        }
+        let corners = [
+            Point { x, y },
+            Point { x: x + width, y },
+            Point { x: x + width, y: y + height },
+            Point { x, y: y + height },
+        ];
+        if matrix.is_axis_aligned() {
+            self.result.shapes.push(Shape::Rectangle(Rectangle {
+                top_left: matrix.apply(&corners[0]),
+                bottom_right: matrix.apply(&corners[2]),
+            }));
+        } else {
+            // Rotated rectangles keep their shape; skewed ones are only representable as polygons
+            let polygon = Polygon::new(corners.iter().map(|p| matrix.apply(p)).collect());
+            self.result.shapes.push(match OrientedRectangle::from_polygon(&polygon, &Precision::default()) {
+                Some(rect) => Shape::OrientedRectangle(rect),
+                None => Shape::Polygon(polygon),
+            });
+        }
+        Ok(())
+    }
+
+    fn ellipse(&mut self, node: &Node, matrix: &Matrix, rx_name: &str, ry_name: &str) -> Result<(), String> {
+        let center = Point {
+            x: self.number(node, "cx")?,
+            y: self.number(node, "cy")?,
+        };
+        let rx = self.number(node, rx_name)?;
+        let ry = self.number(node, ry_name)?;
+        if rx <= 0.0 || ry <= 0.0 {
+            return Err("radius must be positive".to_string());
+        }
+        match matrix.uniform_scale() {
+            Some(scale) if rx == ry => {
+                self.result.shapes.push(Shape::Circle(Circle {
+                    center: matrix.apply(&center),
+                    radius: rx * scale,
+                }));
+            }
+            _ => {
+                let ellipse = Ellipse { center, rx, ry, angle: 0.0 };
+                self.result.shapes.push(Shape::Ellipse(map_ellipse(&ellipse, &matrix.to_transform())));
+            }
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, node: &Node, matrix: &Matrix) -> Result<(), String> {
+        let start = Point { x: self.number(node, "x1")?, y: self.number(node, "y1")? };
+        let end = Point { x: self.number(node, "x2")?, y: self.number(node, "y2")? };
+        self.result.shapes.push(Shape::Segment(Segment {
+            start: matrix.apply(&start),
+            end: matrix.apply(&end),
+        }));
+        Ok(())
+    }
+
+    // A `<polyline>` stays open unless it ends where it starts
+    fn poly(&mut self, node: &Node, matrix: &Matrix, open: bool) -> Result<(), String> {
+        let numbers = parse_numbers(node.attribute("points").unwrap_or(""))?;
+        let mut points: Vec<Point> = numbers
+            .chunks_exact(2)
+            .map(|pair| Point { x: pair[0], y: pair[1] })
+            .collect();
+        let closes = points.len() > 1 && {
+            let (first, last) = (points[0], points[points.len() - 1]);
+            first.x == last.x && first.y == last.y
+        };
+        if open && !closes {
+            if points.len() < 2 {
+                return Err("fewer than 2 points".to_string());
+            }
+            self.result.shapes.push(Shape::Polyline(Polyline {
+                points: points.iter().map(|p| matrix.apply(p)).collect(),
+            }));
+            return Ok(());
+        }
+        dedup_closing_point(&mut points);
+        if points.len() < 3 {
+            return Err("fewer than 3 points".to_string());
+        }
+        self.result.shapes.push(Shape::Polygon(Polygon::new(
+            points.iter().map(|p| matrix.apply(p)).collect(),
+        )));
+        Ok(())
+    }
+
+    fn path(&mut self, node: &Node, matrix: &Matrix) -> Result<(), String> {
+        let commands = parse_path_data(node.attribute("d").unwrap_or(""))?;
+        let tolerance = self.local_tolerance(matrix);
+        let mut subpaths: Vec<Vec<Point>> = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        let mut start = Point { x: 0.0, y: 0.0 };
+        for command in commands {
+            let last = current.last().copied().unwrap_or(start);
+            match command {
+                PathCommand::MoveTo(to) => {
+                    subpaths.push(std::mem::take(&mut current));
+                    current.push(to);
+                    start = to;
+                }
+                PathCommand::LineTo(to) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    current.push(to);
+                }
+                PathCommand::QuadTo(control, to) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    current.extend(flatten::quadratic(last, control, to, tolerance));
+                }
+                PathCommand::CubicTo(c1, c2, to) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    current.extend(flatten::cubic(last, c1, c2, to, tolerance));
+                }
+                PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, to } => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    current.extend(flatten_arc(last, rx, ry, rotation, large_arc, sweep, to, tolerance));
+                }
+                PathCommand::Close => {
+                    subpaths.push(std::mem::take(&mut current));
+                }
+            }
+        }
+        subpaths.push(current);
+
+        let mut rings = Vec::new();
+        let mut dropped = 0;
+        for mut points in subpaths.into_iter().filter(|points| !points.is_empty()) {
+            dedup_closing_point(&mut points);
+            if points.len() < 3 {
+                dropped += 1;
+                continue;
+            }
+            rings.push(points.iter().map(|p| matrix.apply(p)).collect());
+        }
+        let imported = rings.len();
+        // Subpaths inside other subpaths are holes, as the fill rule paints them
+        let even_odd = node
+            .ancestors()
+            .find_map(|n| n.attribute("fill-rule"))
+            .is_some_and(|rule| rule == "evenodd");
+        for polygon in nest_rings(rings, even_odd) {
+            self.result.shapes.push(Shape::Polygon(polygon));
+        }
+        if dropped > 0 && imported > 0 {
+            self.warning(node, &format!("{} subpath(s) with fewer than 3 points were left out", dropped));
+        } else if imported == 0 {
+            return Err("no subpath encloses an area".to_string());
+        }
+        Ok(())
+    }
+
+    // Flattening happens before the transform, so scale the tolerance into local units
+    fn local_tolerance(&self, matrix: &Matrix) -> f64 {
+        let scale = matrix.determinant().abs().sqrt();
+        if scale > 0.0 {
+            self.tolerance / scale
+        } else {
+            self.tolerance
+        }
+    }
+
+    fn issue(node: &Node, reason: &str) -> ImportIssue {
+        ImportIssue {
+            element: node.tag_name().name().to_string(),
+            id: node.attribute("id").map(str::to_string),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn warning(&mut self, node: &Node, reason: &str) {
+        self.result.warnings.push(Importer::issue(node, reason));
+    }
+
+    fn unsupported(&mut self, node: &Node, reason: String) {
+        self.result.unsupported.push(Importer::issue(node, &reason));
+    }
+}
+
+// Drops a final point that repeats the first, as closed outlines often do
+pub(crate) fn dedup_closing_point(points: &mut Vec<Point>) {
+    if points.len() > 1 {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        if first.x == last.x && first.y == last.y {
+            points.pop();
+        }
+    }
+}
+
+// SVG transform matrix: x' = a·x + c·y + e, y' = b·x + d·y + f
+#[derive(Debug,Clone,Copy)]
+struct Matrix {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Matrix {
+    fn scale(sx: f64, sy: f64) -> Matrix {
+        Matrix { a: sx, b: 0.0, c: 0.0, d: sy, e: 0.0, f: 0.0 }
+    }
+
+    // `self` applied after `other`
+    fn multiply(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    fn apply(&self, p: &Point) -> Point {
+        Point {
+            x: self.a * p.x + self.c * p.y + self.e,
+            y: self.b * p.x + self.d * p.y + self.f,
+        }
+    }
+
+    fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    fn to_transform(self) -> Transform {
+        let Matrix { a, b, c, d, e, f } = self;
+        Transform { a, b, c, d, e, f }
+    }
+
+    // Maps axis-aligned rectangles to axis-aligned rectangles
+    fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+
+    // The scale factor when this only rotates, mirrors, translates and scales uniformly
+    fn uniform_scale(&self) -> Option<f64> {
+        let x_scale = self.a.hypot(self.b);
+        let y_scale = self.c.hypot(self.d);
+        let orthogonal = (self.a * self.c + self.b * self.d).abs() <= 1e-9 * x_scale * y_scale;
+        if orthogonal && (x_scale - y_scale).abs() <= 1e-9 * x_scale {
+            Some(x_scale)
+        } else {
+            None
+        }
+    }
+
+    // Parses a `transform` attribute such as `translate(10 20) rotate(45)`
+    fn parse(text: &str) -> Result<Matrix, String> {
+        let mut matrix = Matrix::scale(1.0, 1.0);
+        let mut rest = text.trim();
+        while !rest.is_empty() {
+            let open = rest.find('(').ok_or_else(|| format!("invalid transform '{}'", text))?;
+            let close = rest.find(')').ok_or_else(|| format!("invalid transform '{}'", text))?;
+            let name = rest[..open].trim();
+            let args = parse_numbers(&rest[open + 1..close])?;
+            let step = match (name, args.as_slice()) {
+                ("matrix", &[a, b, c, d, e, f]) => Matrix { a, b, c, d, e, f },
+                ("translate", &[tx]) => Matrix { e: tx, ..Matrix::scale(1.0, 1.0) },
+                ("translate", &[tx, ty]) => Matrix { e: tx, f: ty, ..Matrix::scale(1.0, 1.0) },
+                ("scale", &[s]) => Matrix::scale(s, s),
+                ("scale", &[sx, sy]) => Matrix::scale(sx, sy),
+                ("rotate", &[angle]) => Matrix::rotation(angle),
+                ("rotate", &[angle, cx, cy]) => {
+                    let to_center = Matrix { e: cx, f: cy, ..Matrix::scale(1.0, 1.0) };
+                    let from_center = Matrix { e: -cx, f: -cy, ..Matrix::scale(1.0, 1.0) };
+                    to_center.multiply(&Matrix::rotation(angle)).multiply(&from_center)
+                }
+                ("skewX", &[angle]) => Matrix { c: angle.to_radians().tan(), ..Matrix::scale(1.0, 1.0) },
+                ("skewY", &[angle]) => Matrix { b: angle.to_radians().tan(), ..Matrix::scale(1.0, 1.0) },
+                _ => return Err(format!("unsupported transform '{}'", rest[..=close].trim())),
+            };
+            matrix = matrix.multiply(&step);
+            rest = rest[close + 1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+        Ok(matrix)
+    }
+
+    fn rotation(degrees: f64) -> Matrix {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Matrix { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }
+    }
+}
+
+// Path data normalized to absolute coordinates; shorthand commands (H, V, S, T) are expanded
+#[derive(Debug,Clone,Copy)]
+pub(crate) enum PathCommand {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    ArcTo {
+        rx: f64,
+        ry: f64,
+        // Degrees, as written in the path data
+        rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        to: Point,
+    },
+    Close,
+}
+
+// Parses the `d` attribute of an SVG `<path>`
+pub(crate) fn parse_path_data(data: &str) -> Result<Vec<PathCommand>, String> {
+    let mut scanner = Scanner { bytes: data.as_bytes(), position: 0 };
+    let mut commands = Vec::new();
+    let mut current = Point { x: 0.0, y: 0.0 };
+    let mut subpath_start = current;
+    // Reflected control point for S and T, when the previous segment was of the same kind
+    let mut last_cubic_control: Option<Point> = None;
+    let mut last_quad_control: Option<Point> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        scanner.skip_separators();
+        let Some(next) = scanner.peek() else { break };
+        if next.is_ascii_alphabetic() {
+            command = Some(next);
+            scanner.position += 1;
+        } else if command.is_none() {
+            return Err("path data must start with a command".to_string());
+        }
+        let letter = command.unwrap();
+        let relative = letter.is_ascii_lowercase();
+        let origin = if relative { current } else { Point { x: 0.0, y: 0.0 } };
+        let point = |scanner: &mut Scanner| -> Result<Point, String> {
+            let x = scanner.number()?;
+            let y = scanner.number()?;
+            Ok(Point { x: origin.x + x, y: origin.y + y })
+        };
+
+        let mut cubic_control = None;
+        let mut quad_control = None;
+        match letter.to_ascii_uppercase() {
+            b'M' => {
+                current = point(&mut scanner)?;
+                subpath_start = current;
+                commands.push(PathCommand::MoveTo(current));
+                // Further coordinate pairs after a move are implicit line-tos
+                command = Some(if relative { b'l' } else { b'L' });
+            }
+            b'L' => {
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::LineTo(current));
+            }
+            b'H' => {
+                current = Point { x: origin.x + scanner.number()?, y: current.y };
+                commands.push(PathCommand::LineTo(current));
+            }
+            b'V' => {
+                current = Point { x: current.x, y: origin.y + scanner.number()? };
+                commands.push(PathCommand::LineTo(current));
+            }
+            b'C' => {
+                let c1 = point(&mut scanner)?;
+                let c2 = point(&mut scanner)?;
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::CubicTo(c1, c2, current));
+                cubic_control = Some(c2);
+            }
+            b'S' => {
+                let c1 = reflect(last_cubic_control, current);
+                let c2 = point(&mut scanner)?;
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::CubicTo(c1, c2, current));
+                cubic_control = Some(c2);
+            }
+            b'Q' => {
+                let control = point(&mut scanner)?;
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::QuadTo(control, current));
+                quad_control = Some(control);
+            }
+            b'T' => {
+                let control = reflect(last_quad_control, current);
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::QuadTo(control, current));
+                quad_control = Some(control);
+            }
+            b'A' => {
+                let rx = scanner.number()?;
+                let ry = scanner.number()?;
+                let rotation = scanner.number()?;
+                let large_arc = scanner.flag()?;
+                let sweep = scanner.flag()?;
+                current = point(&mut scanner)?;
+                commands.push(PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, to: current });
+            }
+            b'Z' => {
+                current = subpath_start;
+                commands.push(PathCommand::Close);
+                command = None;
+            }
+            _ => return Err(format!("unknown path command '{}'", letter as char)),
+        }
+        last_cubic_control = cubic_control;
+        last_quad_control = quad_control;
+    }
+    Ok(commands)
+}
+
+fn reflect(control: Option<Point>, about: Point) -> Point {
+    match control {
+        Some(control) => Point {
+            x: 2.0 * about.x - control.x,
+            y: 2.0 * about.y - control.y,
+        },
+        None => about,
+    }
+}
+
+// Points after `from` along an SVG elliptical arc, converted to center form as in
+// the SVG implementation notes (appendix B.2.4)
+#[allow(clippy::too_many_arguments)]
+fn flatten_arc(
+    from: Point,
+    rx: f64,
+    ry: f64,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+    tolerance: f64,
+) -> Vec<Point> {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 || (from.x == to.x && from.y == to.y) {
+        return vec![to];
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let dx = (from.x - to.x) / 2.0;
+    let dy = (from.y - to.y) / 2.0;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+
+    // Scale radii up when they are too small to span the endpoints
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut factor = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let cx1 = factor * rx * y1 / ry;
+    let cy1 = -factor * ry * x1 / rx;
+    let center = Point {
+        x: cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+        y: sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+    };
+
+    let angle = |ux: f64, uy: f64| uy.atan2(ux);
+    let start = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let end = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let mut delta = end - start;
+    if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    } else if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    }
+
+    let mut points: Vec<Point> = flatten::ellipse_arc(Point { x: 0.0, y: 0.0 }, rx, ry, start, delta, tolerance)
+        .into_iter()
+        .skip(1)
+        .map(|p| Point {
+            x: center.x + cos * p.x - sin * p.y,
+            y: center.y + sin * p.x + cos * p.y,
+        })
+        .collect();
+    // Land exactly on the endpoint despite rounding
+    if let Some(last) = points.last_mut() {
+        *last = to;
+    }
+    points
+}
+
+// Whitespace/comma separated numbers, e.g. the `points` attribute or `viewBox`
+fn parse_numbers(text: &str) -> Result<Vec<f64>, String> {
+    let mut scanner = Scanner { bytes: text.as_bytes(), position: 0 };
+    let mut numbers = Vec::new();
+    loop {
+        scanner.skip_separators();
+        if scanner.peek().is_none() {
+            return Ok(numbers);
+        }
+        numbers.push(scanner.number()?);
+    }
+}
+
+// Tokenizer for SVG number lists, which allow forms like `1.5.5` and `10-5`
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r' | b',')) {
+            self.position += 1;
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.position;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.position += 1;
+        }
+        let mut seen_dot = false;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'0'..=b'9' => self.position += 1,
+                b'.' if !seen_dot => {
+                    seen_dot = true;
+                    self.position += 1;
+                }
+                b'e' | b'E' => {
+                    self.position += 1;
+                    if matches!(self.peek(), Some(b'+' | b'-')) {
+                        self.position += 1;
+                    }
+                    while matches!(self.peek(), Some(b'0'..=b'9')) {
+                        self.position += 1;
+                    }
+                    break;
+                }
+                _ => break,
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap_or("");
+        text.parse()
+            .map_err(|_| format!("expected a number at offset {}", start))
+    }
+
+    // Arc flags are a single `0` or `1` and may be written without separators
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(format!("expected an arc flag at offset {}", self.position)),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import_svg(body: &str) -> SvgImport {
+        let text = format!(r#"<svg xmlns="{}" width="10cm" viewBox="0 0 100 50">{}</svg>"#, SVG_NAMESPACE, body);
+        import(&text, &UnitSettings::default(), flatten::DEFAULT_TOLERANCE).unwrap()
+    }
+
+    // Internal pixels per user unit of `import_svg` drawings: 10 cm across 100 user units
+    fn scale() -> f64 {
+        MeasurementUnit::Centimeter.pixels_per_unit(UnitSettings::default().dpi) / 10.0
+    }
+
+    fn assert_point(actual: Point, x: f64, y: f64) {
+        let expected = Point { x, y };
+        assert!(actual.distance(&expected) < 1e-9, "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn maps_the_view_box_and_nested_transforms() {
+        let import = import_svg(
+            r#"<g transform="translate(10 5)"><rect width="20" height="10" transform="scale(2)"/></g>"#,
+        );
+        assert!(import.unsupported.is_empty() && import.warnings.is_empty());
+        let s = scale();
+        match import.shapes.as_slice() {
+            // Y flips to point up
+            [Shape::Rectangle(rect)] => {
+                assert_point(rect.top_left, 10.0 * s, -5.0 * s);
+                assert_point(rect.bottom_right, 50.0 * s, -25.0 * s);
+            }
+            shapes => panic!("expected one rectangle, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn keeps_circles_round_only_under_uniform_scales() {
+        let import = import_svg(
+            r#"<g transform="rotate(30) scale(3)"><circle cx="5" cy="0" r="2"/></g>
+            <circle r="2" transform="scale(2 1)"/>"#,
+        );
+        let s = scale();
+        match import.shapes.as_slice() {
+            [Shape::Circle(circle), Shape::Ellipse(ellipse)] => {
+                let (sin, cos) = 30f64.to_radians().sin_cos();
+                assert_point(circle.center, 15.0 * cos * s, -15.0 * sin * s);
+                assert!((circle.radius - 6.0 * s).abs() < 1e-9);
+                assert!((ellipse.rx - 4.0 * s).abs() < 1e-9 && (ellipse.ry - 2.0 * s).abs() < 1e-9);
+            }
+            shapes => panic!("expected a circle and an ellipse, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn rotated_rectangles_stay_rectangles_and_skewed_ones_become_polygons() {
+        let import = import_svg(
+            r#"<rect width="20" height="10" transform="rotate(30 10 5)"/>
+            <rect width="20" height="10" transform="skewX(20)"/>"#,
+        );
+        match import.shapes.as_slice() {
+            [Shape::OrientedRectangle(rect), Shape::Polygon(polygon)] => {
+                assert_point(rect.center, 10.0 * scale(), -5.0 * scale());
+                assert!((rect.width - 20.0 * scale()).abs() < 1e-9);
+                assert_eq!(polygon.points.len(), 4);
+            }
+            shapes => panic!("expected an oriented rectangle and a polygon, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn inner_subpaths_become_holes() {
+        let import = import_svg(r#"<path fill-rule="evenodd" d="M0 0H40V40H0Z M10 10h20v20h-20z"/>"#);
+        match import.shapes.as_slice() {
+            [Shape::Polygon(polygon)] => assert_eq!((polygon.points.len(), polygon.holes.len()), (4, 1)),
+            shapes => panic!("expected one polygon, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn flattens_arcs_within_tolerance() {
+        // A half circle of radius 20 closed by its diameter
+        let import = import_svg(r#"<path d="M0 0 A20 20 0 0 1 40 0 Z"/>"#);
+        let s = scale();
+        match import.shapes.as_slice() {
+            [Shape::Polygon(polygon)] => {
+                for p in &polygon.points {
+                    let distance = (p.x - 20.0 * s).hypot(p.y);
+                    assert!(distance <= 20.0 * s + 1e-9 && distance >= 20.0 * s - flatten::DEFAULT_TOLERANCE);
+                }
+            }
+            shapes => panic!("expected one polygon, got {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn reports_what_it_could_not_import() {
+        let body = format!(
+            r#"<defs><rect width="5" height="5"/></defs>
+            <g class="{}"><line x2="10"/></g>
+            <rect width="5" height="5" rx="1" id="rounded"/>
+            <text id="label">Hi</text>
+            <rect width="5" height="5" transform="perspective(2)"/>
+            <polyline points="1 1"/>"#,
+            svg::GUIDES_CLASS
+        );
+        let import = import_svg(&body);
+        assert_eq!(import.shapes.len(), 1);
+        assert_eq!(import.warnings.len(), 1);
+        assert_eq!(import.warnings[0].id.as_deref(), Some("rounded"));
+        let elements: Vec<&str> = import.unsupported.iter().map(|issue| issue.element.as_str()).collect();
+        assert_eq!(elements, ["text", "rect", "polyline"]);
+        assert_eq!(import.unsupported[0].id.as_deref(), Some("label"));
+    }
+
+    #[test]
+    fn rejects_documents_that_are_not_svg() {
+        let settings = UnitSettings::default();
+        for text in ["<html/>", "<svg", "not xml"] {
+            assert!(
+                matches!(import(text, &settings, 0.25), Err(DocumentError::Malformed { format: "SVG", .. })),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn reads_lengths_in_absolute_units() {
+        let settings = UnitSettings::default();
+        let lengths = [
+            ("12", 12.0),
+            ("12px", 12.0),
+            ("1in", 96.0),
+            ("72pt", 96.0),
+            ("2.54cm", 96.0),
+            ("1e1mm", 96.0 / 2.54),
+        ];
+        for (text, pixels) in lengths {
+            let length = parse_length(text, &settings).unwrap();
+            assert!((length - pixels).abs() < 1e-9, "{} is {} px", text, length);
+        }
+        assert_eq!(parse_length("50%", &settings), None);
+        assert_eq!(parse_length("3em", &settings), None);
+    }
+}
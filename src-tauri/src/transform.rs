@@ -0,0 +1,526 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::GeometryError;
+use crate::precision::Precision;
+use crate::{check_finite, Arc, ArcClosure, Circle, Ellipse, MultiPolygon, OrientedRectangle, Point, Polygon, Polyline, Rectangle, Segment, Shape};
+
+// 2D affine map x' = a*x + c*y + e, y' = b*x + d*y + f, in the order of SVG's `matrix()`
+#[derive(Deserialize,Serialize,Debug,Clone,Copy,PartialEq)]
+pub(crate) struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+#[derive(Deserialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MirrorAxis {
+    // Across a horizontal line, flipping top and bottom
+    Horizontal,
+    // Across a vertical line, flipping left and right
+    Vertical,
+}
+
+// One step of a transform as sent by the frontend. Angles are in degrees, counter-clockwise
+// with Y up. Steps without a `center` act about the center of the shape's bounding box.
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum TransformStep {
+    Translate { dx: f64, dy: f64 },
+    Rotate { angle: f64, center: Option<Point> },
+    // `sy` defaults to `sx` for uniform scaling
+    Scale { sx: f64, sy: Option<f64>, center: Option<Point> },
+    Mirror { axis: MirrorAxis, center: Option<Point> },
+    Matrix(Transform),
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    pub fn translate(dx: f64, dy: f64) -> Transform {
+        Transform {
+            e: dx,
+            f: dy,
+            ..Transform::IDENTITY
+        }
+    }
+
+    // Counter-clockwise by `angle` radians about the origin
+    pub fn rotate(angle: f64) -> Transform {
+        let (sin, cos) = angle.sin_cos();
+        Transform {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Transform {
+        Transform {
+            a: sx,
+            d: sy,
+            ..Transform::IDENTITY
+        }
+    }
+
+    // `self` applied about `center` instead of the origin
+    pub fn about(&self, center: Point) -> Transform {
+        Transform::translate(-center.x, -center.y)
+            .then(self)
+            .then(&Transform::translate(center.x, center.y))
+    }
+
+    // `self` followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    // Composes `steps` in order. Steps without their own center act about `center` as moved
+    // by the steps before them.
+    pub fn from_steps(steps: &[TransformStep], center: Point) -> Result<Transform, GeometryError> {
+        let mut transform = Transform::IDENTITY;
+        for (i, step) in steps.iter().enumerate() {
+            let field = |name: &str| format!("steps[{}].{}", i, name);
+            let check_center = |at: Option<Point>| -> Result<Point, GeometryError> {
+                match at {
+                    Some(at) => {
+                        at.check_finite(&field("center"))?;
+                        Ok(at)
+                    }
+                    None => Ok(transform.apply(center)),
+                }
+            };
+            let next = match *step {
+                TransformStep::Translate { dx, dy } => {
+                    check_finite(dx, &field("dx"))?;
+                    check_finite(dy, &field("dy"))?;
+                    Transform::translate(dx, dy)
+                }
+                TransformStep::Rotate { angle, center: at } => {
+                    check_finite(angle, &field("angle"))?;
+                    Transform::rotate(angle.to_radians()).about(check_center(at)?)
+                }
+                TransformStep::Scale { sx, sy, center: at } => {
+                    check_finite(sx, &field("sx"))?;
+                    let sy = sy.unwrap_or(sx);
+                    check_finite(sy, &field("sy"))?;
+                    Transform::scale(sx, sy).about(check_center(at)?)
+                }
+                TransformStep::Mirror { axis, center: at } => {
+                    let flip = match axis {
+                        MirrorAxis::Horizontal => Transform::scale(1.0, -1.0),
+                        MirrorAxis::Vertical => Transform::scale(-1.0, 1.0),
+                    };
+                    flip.about(check_center(at)?)
+                }
+                TransformStep::Matrix(matrix) => {
+                    for (name, value) in matrix.entries() {
+                        check_finite(value, &field(name))?;
+                    }
+                    matrix
+                }
+            };
+            transform = transform.then(&next);
+        }
+        Ok(transform)
+    }
+
+    fn entries(&self) -> [(&'static str, f64); 6] {
+        [("a", self.a), ("b", self.b), ("c", self.c), ("d", self.d), ("e", self.e), ("f", self.f)]
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        Point {
+            x: self.a * p.x + self.c * p.y + self.e,
+            y: self.b * p.x + self.d * p.y + self.f,
+        }
+    }
+
+    // Area scale factor; negative when the transform mirrors
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    // Maps the axes onto the axes, so axis-aligned rectangles stay axis-aligned
+    fn keeps_axes(&self, precision: &Precision) -> bool {
+        let scale = self.linear_norm();
+        (precision.is_zero(self.b / scale) && precision.is_zero(self.c / scale))
+            || (precision.is_zero(self.a / scale) && precision.is_zero(self.d / scale))
+    }
+
+    // Rotation, uniform scaling and mirroring only, so circles stay circles
+    fn is_similarity(&self, precision: &Precision) -> bool {
+        let scale = self.linear_norm();
+        let rotates = precision.is_zero((self.a - self.d) / scale) && precision.is_zero((self.b + self.c) / scale);
+        let mirrors = precision.is_zero((self.a + self.d) / scale) && precision.is_zero((self.b - self.c) / scale);
+        rotates || mirrors
+    }
+
+    // Flattens the plane onto a line or point. Judged relative to the squared norm, so a small
+    // uniform scale such as 1e-5 still counts as invertible.
+    fn is_degenerate(&self, precision: &Precision) -> bool {
+        let norm = self.linear_norm();
+        norm == 0.0 || precision.is_zero(self.determinant() / (norm * norm))
+    }
+
+    // Frobenius norm of the linear part, an upper bound on how far it stretches any length
+    fn linear_norm(&self) -> f64 {
+        (self.a * self.a + self.b * self.b + self.c * self.c + self.d * self.d).sqrt()
+    }
+}
+
+fn map_ring(ring: &[Point], transform: &Transform, precision: &Precision) -> Vec<Point> {
+    let mut ring: Vec<Point> = ring
+        .iter()
+        .map(|&p| {
+            let p = transform.apply(p);
+            Point {
+                x: precision.round(p.x),
+                y: precision.round(p.y),
+            }
+        })
+        .collect();
+    // Mirroring reverses the winding; reverse back so outer rings and holes keep theirs
+    if transform.determinant() < 0.0 {
+        ring.reverse();
+    }
+    ring
+}
+
+fn round_ellipse(ellipse: Ellipse, precision: &Precision) -> Ellipse {
+    Ellipse {
+        center: Point {
+            x: precision.round(ellipse.center.x),
+            y: precision.round(ellipse.center.y),
+        },
+        rx: precision.round(ellipse.rx),
+        ry: precision.round(ellipse.ry),
+        angle: precision.round(ellipse.angle),
+    }
+}
+
+fn map_polygon(polygon: &Polygon, transform: &Transform, precision: &Precision) -> Polygon {
+    Polygon {
+        points: map_ring(&polygon.points, transform, precision),
+        holes: polygon.holes.iter().map(|hole| map_ring(hole, transform, precision)).collect(),
+    }
+}
+
+// The ellipse `transform` maps `ellipse` onto, which any affine transform does exactly. Its
+// semi-axes come from the eigen-decomposition of A·Aᵀ, A being the mapped semi-axis vectors.
+pub(crate) fn map_ellipse(ellipse: &Ellipse, transform: &Transform) -> Ellipse {
+    let (sin, cos) = ellipse.angle.to_radians().sin_cos();
+    let linear = |x: f64, y: f64| (transform.a * x + transform.c * y, transform.b * x + transform.d * y);
+    let (ux, uy) = linear(ellipse.rx * cos, ellipse.rx * sin);
+    let (vx, vy) = linear(-ellipse.ry * sin, ellipse.ry * cos);
+    let (s11, s12, s22) = (ux * ux + vx * vx, ux * uy + vx * vy, uy * uy + vy * vy);
+    let mean = (s11 + s22) / 2.0;
+    let spread = ((s11 - s22) / 2.0).hypot(s12);
+    Ellipse {
+        center: transform.apply(ellipse.center),
+        rx: (mean + spread).sqrt(),
+        ry: (mean - spread).max(0.0).sqrt(),
+        angle: (2.0 * s12).atan2(s11 - s22).to_degrees() / 2.0,
+    }
+}
+
+// Corners of a rectangle mapped through `transform`: an oriented rectangle while the corners
+// stay right angles, as under rotations, otherwise a parallelogram
+fn map_rectangle(corners: &[Point], transform: &Transform, precision: &Precision) -> Shape {
+    let mapped = Polygon::new(corners.iter().map(|&p| transform.apply(p)).collect());
+    match OrientedRectangle::from_polygon(&mapped, precision) {
+        Some(rect) => Shape::OrientedRectangle(OrientedRectangle {
+            center: Point {
+                x: precision.round(rect.center.x),
+                y: precision.round(rect.center.y),
+            },
+            width: precision.round(rect.width),
+            height: precision.round(rect.height),
+            angle: precision.round(rect.angle),
+        }),
+        None => Shape::Polygon(Polygon::new(map_ring(corners, transform, precision))),
+    }
+}
+
+// `shape` mapped through `transform`. Rectangles stay axis-aligned rectangles while the
+// transform keeps them so and become oriented rectangles under rotations; circles stay
+// circles and arcs stay arcs under similarities, and paths stay paths. Circles and ellipses otherwise become
+// ellipses, which is exact, and other shapes polygons, with arcs flattened to within
+// `tolerance` of the transformed outline; open arcs become polylines.
+pub(crate) fn apply(
+    shape: &Shape,
+    transform: &Transform,
+    tolerance: f64,
+    precision: &Precision,
+) -> Result<Shape, GeometryError> {
+    if transform.is_degenerate(precision) {
+        return Err(GeometryError::DegenerateTransform { field: "steps".to_string() });
+    }
+    let round = |p: Point| Point {
+        x: precision.round(p.x),
+        y: precision.round(p.y),
+    };
+    Ok(match shape {
+        Shape::Rectangle(rect) if transform.keeps_axes(precision) => {
+            let (p, q) = (transform.apply(rect.top_left), transform.apply(rect.bottom_right));
+            // Keep whichever corner convention the rectangle was drawn with
+            let order = |first: f64, second: f64, ascending: bool| {
+                if ascending {
+                    (first.min(second), first.max(second))
+                } else {
+                    (first.max(second), first.min(second))
+                }
+            };
+            let (left, right) = order(p.x, q.x, rect.top_left.x <= rect.bottom_right.x);
+            let (top, bottom) = order(p.y, q.y, rect.top_left.y <= rect.bottom_right.y);
+            Shape::Rectangle(Rectangle {
+                top_left: round(Point { x: left, y: top }),
+                bottom_right: round(Point { x: right, y: bottom }),
+            })
+        }
+        Shape::Rectangle(rect) => {
+            let (a, b) = (rect.top_left, rect.bottom_right);
+            map_rectangle(&[a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }], transform, precision)
+        }
+        Shape::OrientedRectangle(rect) => map_rectangle(&rect.corners(), transform, precision),
+        Shape::Circle(circle) if transform.is_similarity(precision) => Shape::Circle(Circle {
+            center: round(transform.apply(circle.center)),
+            radius: precision.round(circle.radius * transform.determinant().abs().sqrt()),
+        }),
+        Shape::Circle(circle) => {
+            let ellipse = Ellipse {
+                center: circle.center,
+                rx: circle.radius,
+                ry: circle.radius,
+                angle: 0.0,
+            };
+            Shape::Ellipse(round_ellipse(map_ellipse(&ellipse, transform), precision))
+        }
+        Shape::Ellipse(ellipse) => Shape::Ellipse(round_ellipse(map_ellipse(ellipse, transform), precision)),
+        Shape::Arc(arc) if transform.is_similarity(precision) => {
+            // Directions turn with the transform and reverse under mirroring
+            let start = transform.apply(arc.start());
+            let center = transform.apply(arc.center);
+            let sweep = arc.sweep_angle * transform.determinant().signum();
+            Shape::Arc(Arc {
+                center: round(center),
+                radius: precision.round(arc.radius * transform.determinant().abs().sqrt()),
+                start_angle: precision.round((start.y - center.y).atan2(start.x - center.x).to_degrees()),
+                sweep_angle: sweep,
+                closure: arc.closure,
+            })
+        }
+        Shape::Arc(arc) if arc.closure == ArcClosure::Open => Shape::Polyline(Polyline {
+            points: arc.points(tolerance / transform.linear_norm()).into_iter().map(|p| round(transform.apply(p))).collect(),
+        }),
+        Shape::Arc(arc) => {
+            let outline = arc.outline(tolerance / transform.linear_norm());
+            Shape::Polygon(Polygon::new(map_ring(&outline, transform, precision)))
+        }
+        Shape::Segment(segment) => Shape::Segment(Segment {
+            start: round(transform.apply(segment.start)),
+            end: round(transform.apply(segment.end)),
+        }),
+        Shape::Polyline(polyline) => Shape::Polyline(Polyline {
+            points: polyline.points.iter().map(|&p| round(transform.apply(p))).collect(),
+        }),
+        Shape::Path(path) => Shape::Path(path.map(|p| round(transform.apply(p)))),
+        Shape::Polygon(polygon) => Shape::Polygon(map_polygon(polygon, transform, precision)),
+        Shape::MultiPolygon(multi) => Shape::MultiPolygon(MultiPolygon {
+            polygons: multi.polygons.iter().map(|polygon| map_polygon(polygon, transform, precision)).collect(),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ring_signed_area;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    fn assert_point(actual: Point, expected: Point) {
+        assert!(actual.distance(&expected) < 1e-9, "{:?} is not {:?}", actual, expected);
+    }
+
+    fn transform(shape: &Shape, transform: &Transform) -> Result<Shape, GeometryError> {
+        apply(shape, transform, 0.25, &Precision::default())
+    }
+
+    fn mirror(axis: MirrorAxis) -> Transform {
+        Transform::from_steps(&[TransformStep::Mirror { axis, center: None }], point(0.0, 0.0)).unwrap()
+    }
+
+    fn ellipse(angle: f64) -> Ellipse {
+        Ellipse { center: point(1.0, 2.0), rx: 4.0, ry: 2.0, angle }
+    }
+
+    #[test]
+    fn composes_steps_about_the_moving_center() {
+        let steps = [
+            TransformStep::Translate { dx: 10.0, dy: 0.0 },
+            TransformStep::Rotate { angle: 90.0, center: None },
+            TransformStep::Scale { sx: 2.0, sy: None, center: Some(point(0.0, 0.0)) },
+        ];
+        let transform = Transform::from_steps(&steps, point(1.0, 1.0)).unwrap();
+        // (2, 1) moves to (12, 1), turns about the moved center (11, 1) to (11, 2), then doubles
+        assert_point(transform.apply(point(2.0, 1.0)), point(22.0, 4.0));
+        assert_close(transform.determinant(), 4.0);
+    }
+
+    #[test]
+    fn names_the_step_with_an_invalid_value() {
+        let steps = [
+            TransformStep::Translate { dx: 1.0, dy: 0.0 },
+            TransformStep::Rotate { angle: f64::NAN, center: None },
+        ];
+        let err = Transform::from_steps(&steps, point(0.0, 0.0)).unwrap_err();
+        assert_eq!(err, GeometryError::NonFinite { field: "steps[1].angle".to_string() });
+        let matrix = Transform { e: f64::INFINITY, ..Transform::IDENTITY };
+        let err = Transform::from_steps(&[TransformStep::Matrix(matrix)], point(0.0, 0.0)).unwrap_err();
+        assert_eq!(err, GeometryError::NonFinite { field: "steps[0].e".to_string() });
+    }
+
+    #[test]
+    fn rotates_and_mirrors_ellipses() {
+        // Angles come back in (-90, 90], where an ellipse at 120° is the one at -60°
+        let rotated = map_ellipse(&ellipse(30.0), &Transform::rotate(90f64.to_radians()));
+        assert_point(rotated.center, point(-2.0, 1.0));
+        assert_close(rotated.rx, 4.0);
+        assert_close(rotated.ry, 2.0);
+        assert_close(rotated.angle, -60.0);
+
+        for axis in [MirrorAxis::Horizontal, MirrorAxis::Vertical] {
+            let mirrored = map_ellipse(&ellipse(30.0), &mirror(axis));
+            assert_close(mirrored.rx, 4.0);
+            assert_close(mirrored.ry, 2.0);
+            assert_close(mirrored.angle, -30.0);
+        }
+        let mirrored = map_ellipse(&ellipse(30.0), &mirror(MirrorAxis::Vertical));
+        assert_point(mirrored.center, point(-1.0, 2.0));
+    }
+
+    #[test]
+    fn maps_ellipses_exactly_under_any_affine_transform() {
+        // A shear keeps the area, so the semi-axes multiply to the same product
+        let shear = Transform { c: 1.5, ..Transform::IDENTITY };
+        let sheared = map_ellipse(&ellipse(20.0), &shear);
+        assert_close(sheared.rx * sheared.ry, 8.0);
+        assert!(sheared.rx > 4.0 && sheared.ry < 2.0);
+
+        // Points on the ellipse map onto the mapped ellipse
+        let (sin, cos) = 20f64.to_radians().sin_cos();
+        for t in [0.0, 1.0, 2.5, 4.0] {
+            let (x, y) = (4.0 * f64::cos(t), 2.0 * f64::sin(t));
+            let p = shear.apply(point(1.0 + x * cos - y * sin, 2.0 + x * sin + y * cos));
+            let (sin, cos) = sheared.angle.to_radians().sin_cos();
+            let (dx, dy) = (p.x - sheared.center.x, p.y - sheared.center.y);
+            let (u, v) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+            assert_close((u / sheared.rx).powi(2) + (v / sheared.ry).powi(2), 1.0);
+        }
+    }
+
+    #[test]
+    fn circles_stay_circles_only_under_similarities() {
+        let circle = Shape::Circle(Circle { center: point(1.0, 0.0), radius: 2.0 });
+        let turned = Transform::rotate(1.0).then(&mirror(MirrorAxis::Horizontal)).then(&Transform::scale(3.0, 3.0));
+        match transform(&circle, &turned).unwrap() {
+            Shape::Circle(circle) => assert_close(circle.radius, 6.0),
+            shape => panic!("expected a circle, got {:?}", shape),
+        }
+        match transform(&circle, &Transform::scale(2.0, 1.0)).unwrap() {
+            Shape::Ellipse(ellipse) => {
+                assert_point(ellipse.center, point(2.0, 0.0));
+                assert_eq!((ellipse.rx, ellipse.ry, ellipse.angle), (4.0, 2.0, 0.0));
+            }
+            shape => panic!("expected an ellipse, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn mirroring_reverses_arcs_and_keeps_polygon_winding() {
+        let arc = Shape::Arc(Arc {
+            center: point(0.0, 0.0),
+            radius: 2.0,
+            start_angle: 0.0,
+            sweep_angle: 90.0,
+            closure: ArcClosure::Open,
+        });
+        match transform(&arc, &mirror(MirrorAxis::Vertical)).unwrap() {
+            Shape::Arc(arc) => assert_eq!((arc.start_angle, arc.sweep_angle), (180.0, -90.0)),
+            shape => panic!("expected an arc, got {:?}", shape),
+        }
+
+        let triangle = Polygon::new(vec![point(0.0, 0.0), point(4.0, 0.0), point(0.0, 3.0)]);
+        let before = ring_signed_area(&triangle.points);
+        match transform(&Shape::Polygon(triangle), &mirror(MirrorAxis::Horizontal)).unwrap() {
+            Shape::Polygon(polygon) => assert_close(ring_signed_area(&polygon.points), before),
+            shape => panic!("expected a polygon, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn rectangles_stay_rectangles_while_their_corners_stay_square() {
+        let rect = Shape::Rectangle(Rectangle { top_left: point(0.0, 0.0), bottom_right: point(4.0, 2.0) });
+        let quarter = Transform::rotate(90f64.to_radians());
+        assert!(matches!(transform(&rect, &quarter).unwrap(), Shape::Rectangle(_)));
+        match transform(&rect, &Transform::rotate(30f64.to_radians())).unwrap() {
+            Shape::OrientedRectangle(rect) => {
+                assert_eq!((rect.width, rect.height), (4.0, 2.0));
+                assert_close(rect.angle, 30.0);
+            }
+            shape => panic!("expected an oriented rectangle, got {:?}", shape),
+        }
+        let shear = Transform { c: 0.5, ..Transform::IDENTITY };
+        assert!(matches!(transform(&rect, &shear).unwrap(), Shape::Polygon(_)));
+    }
+
+    #[test]
+    fn judges_degenerate_transforms_relative_to_their_scale() {
+        let square = Shape::Rectangle(Rectangle { top_left: point(0.0, 0.0), bottom_right: point(1.0, 1.0) });
+        let degenerate = [
+            Transform::scale(0.0, 1.0),
+            Transform::scale(0.0, 0.0),
+            Transform { a: 1e6, b: 1e6, c: 1e6, d: 1e6, e: 0.0, f: 0.0 },
+            Transform { a: 1.0, b: 1.0, c: 1.0, d: 1.0 + 1e-12, e: 0.0, f: 0.0 },
+        ];
+        for matrix in degenerate {
+            assert_eq!(
+                transform(&square, &matrix).unwrap_err(),
+                GeometryError::DegenerateTransform { field: "steps".to_string() },
+                "{:?}",
+                matrix
+            );
+        }
+        // Small and large uniform scales are invertible however their determinant compares to 1
+        for scale in [1e-5, 1e6] {
+            assert!(transform(&square, &Transform::scale(scale, scale)).is_ok(), "{}", scale);
+        }
+    }
+}
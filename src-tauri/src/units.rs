@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GeometryError;
+
+// Standard screen density, matching `DPI` in src/utils/measurementUnits.ts
+pub(crate) const DEFAULT_DPI: f64 = 96.0;
+
+// Mirrors `MeasurementUnit` in src/types/shapes.ts
+#[derive(Deserialize,Serialize,Debug,Clone,Copy,PartialEq,Eq)]
+#[serde(try_from = "String")]
+pub(crate) enum MeasurementUnit {
+    #[serde(rename = "px")]
+    Pixel,
+    #[serde(rename = "cm")]
+    Centimeter,
+    #[serde(rename = "mm")]
+    Millimeter,
+    #[serde(rename = "in")]
+    Inch,
+}
+
+impl MeasurementUnit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MeasurementUnit::Pixel => "px",
+            MeasurementUnit::Centimeter => "cm",
+            MeasurementUnit::Millimeter => "mm",
+            MeasurementUnit::Inch => "in",
+        }
+    }
+
+    // Symbol for areas, e.g. `cm²`
+    pub fn squared_symbol(&self) -> String {
+        format!("{}²", self.symbol())
+    }
+
+    // Every n-th grid line is a major one, matching `getMajorGridInterval` in measurementUnits.ts
+    pub fn major_grid_interval(&self) -> u32 {
+        match self {
+            MeasurementUnit::Pixel => 5,
+            MeasurementUnit::Centimeter => 1,
+            MeasurementUnit::Millimeter => 10,
+            MeasurementUnit::Inch => 1,
+        }
+    }
+
+    // How many internal pixels one of this unit spans at the given density
+    pub fn pixels_per_unit(&self, dpi: f64) -> f64 {
+        match self {
+            MeasurementUnit::Pixel => 1.0,
+            MeasurementUnit::Centimeter => dpi / 2.54,
+            MeasurementUnit::Millimeter => dpi / 25.4,
+            MeasurementUnit::Inch => dpi,
+        }
+    }
+}
+
+impl fmt::Display for MeasurementUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for MeasurementUnit {
+    type Err = GeometryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "px" => Ok(MeasurementUnit::Pixel),
+            "cm" => Ok(MeasurementUnit::Centimeter),
+            "mm" => Ok(MeasurementUnit::Millimeter),
+            "in" => Ok(MeasurementUnit::Inch),
+            _ => Err(GeometryError::InvalidUnit {
+                field: "unit".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for MeasurementUnit {
+    type Error = GeometryError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+// User-selected display unit and the screen density used to map it to pixels
+#[derive(Deserialize,Serialize,Debug,Clone,Copy)]
+pub(crate) struct UnitSettings {
+    pub unit: MeasurementUnit,
+    pub dpi: f64,
+}
+
+impl Default for UnitSettings {
+    fn default() -> Self {
+        UnitSettings {
+            unit: MeasurementUnit::Pixel,
+            dpi: DEFAULT_DPI,
+        }
+    }
+}
+
+impl UnitSettings {
+    pub fn validate(&self) -> Result<(), GeometryError> {
+        if !self.dpi.is_finite() || self.dpi <= 0.0 {
+            return Err(GeometryError::InvalidDpi { field: "dpi".to_string() });
+        }
+        Ok(())
+    }
+
+    // Factor that converts a length in `from` into a length in `to`
+    pub fn length_factor(&self, from: MeasurementUnit, to: MeasurementUnit) -> f64 {
+        from.pixels_per_unit(self.dpi) / to.pixels_per_unit(self.dpi)
+    }
+
+    // Converts a value of the given dimension (1 for lengths, 2 for areas)
+    pub fn convert(&self, value: f64, from: MeasurementUnit, to: MeasurementUnit, dimension: i32) -> f64 {
+        value * self.length_factor(from, to).powi(dimension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNITS: [MeasurementUnit; 4] = [
+        MeasurementUnit::Pixel,
+        MeasurementUnit::Centimeter,
+        MeasurementUnit::Millimeter,
+        MeasurementUnit::Inch,
+    ];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= 1e-9 * expected.abs(), "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn converts_lengths_and_areas() {
+        let settings = UnitSettings::default();
+        assert_close(settings.convert(1.0, MeasurementUnit::Inch, MeasurementUnit::Pixel, 1), 96.0);
+        assert_close(settings.convert(1.0, MeasurementUnit::Inch, MeasurementUnit::Centimeter, 1), 2.54);
+        assert_close(settings.convert(3.0, MeasurementUnit::Centimeter, MeasurementUnit::Millimeter, 1), 30.0);
+        assert_close(settings.convert(1.0, MeasurementUnit::Centimeter, MeasurementUnit::Millimeter, 2), 100.0);
+        assert_close(settings.convert(96.0 * 96.0, MeasurementUnit::Pixel, MeasurementUnit::Inch, 2), 1.0);
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let settings = UnitSettings { unit: MeasurementUnit::Centimeter, dpi: 300.0 };
+        for from in UNITS {
+            for to in UNITS {
+                for dimension in [1, 2] {
+                    let there = settings.convert(12.5, from, to, dimension);
+                    assert_close(settings.convert(there, to, from, dimension), 12.5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_pixels_depend_on_the_dpi() {
+        let screen = UnitSettings::default();
+        let print = UnitSettings { dpi: 300.0, ..screen };
+        assert_close(print.convert(1.0, MeasurementUnit::Inch, MeasurementUnit::Pixel, 1), 300.0);
+        assert_eq!(
+            screen.length_factor(MeasurementUnit::Inch, MeasurementUnit::Millimeter),
+            print.length_factor(MeasurementUnit::Inch, MeasurementUnit::Millimeter)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dpi() {
+        assert_eq!(UnitSettings::default().validate(), Ok(()));
+        for dpi in [0.0, -96.0, f64::NAN, f64::INFINITY] {
+            let settings = UnitSettings { dpi, ..UnitSettings::default() };
+            assert_eq!(settings.validate(), Err(GeometryError::InvalidDpi { field: "dpi".to_string() }), "{}", dpi);
+        }
+    }
+
+    #[test]
+    fn parses_unit_symbols() {
+        for unit in UNITS {
+            assert_eq!(unit.symbol().parse::<MeasurementUnit>(), Ok(unit));
+            assert_eq!(serde_json::to_value(unit).unwrap(), unit.symbol());
+        }
+        assert_eq!(MeasurementUnit::Centimeter.squared_symbol(), "cm²");
+        assert!(matches!(
+            "ft".parse::<MeasurementUnit>(),
+            Err(GeometryError::InvalidUnit { value, .. }) if value == "ft"
+        ));
+        assert!(serde_json::from_str::<MeasurementUnit>(r#""CM""#).is_err());
+    }
+}
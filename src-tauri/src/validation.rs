@@ -0,0 +1,333 @@
+use serde::Serialize;
+
+use crate::error::GeometryError;
+use crate::precision::Precision;
+use crate::{cross, ring_contains, ring_edges, ring_signed_area, segment_intersection, Point, Polygon};
+
+#[derive(Serialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Winding {
+    CounterClockwise,
+    Clockwise,
+    // Zero net area
+    None,
+}
+
+// Problems found in a polygon. `ring` 0 is the outer ring and ring i is `holes[i - 1]`;
+// indices refer to the submitted points of that ring.
+#[derive(Serialize,Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum PolygonIssue {
+    TooFewPoints { ring: usize, count: usize },
+    // Same position as an earlier vertex it is joined to by a zero-length edge
+    DuplicateVertex { ring: usize, index: usize, duplicates: usize },
+    // On the line through its neighbors; `backtracks` when the outline folds back on itself there
+    CollinearVertex { ring: usize, index: usize, backtracks: bool },
+    // Two non-adjacent edges share `point`; edge i runs from vertex i to vertex i + 1
+    SelfIntersection { ring: usize, edges: [usize; 2], point: Point },
+    ZeroArea { ring: usize },
+    // A hole not inside the outer ring, or inside another hole
+    HoleOutside { ring: usize },
+    // Edges of two different rings share `point`
+    RingsIntersect { rings: [usize; 2], point: Point },
+}
+
+#[derive(Serialize,Debug)]
+pub(crate) struct PolygonValidation {
+    // Simple with a non-zero area, so its area is meaningful
+    pub valid: bool,
+    pub issues: Vec<PolygonIssue>,
+    // Net orientation of the outer ring with Y up; for self-intersecting outlines this follows
+    // the larger loop
+    pub winding: Winding,
+    // Of the outer ring
+    pub signed_area: f64,
+    // Duplicate and collinear vertices removed, the outer ring wound counter-clockwise and holes
+    // clockwise, when every ring keeps enough vertices
+    pub normalized: Option<Polygon>,
+}
+
+// A ring with duplicate and collinear vertices dropped, and its orientation
+struct Ring {
+    kept: Vec<Point>,
+    signed_area: f64,
+    winding: Winding,
+}
+
+// Reports everything that stops `polygon` from having a well-defined area. Only non-finite
+// coordinates are errors; everything else is an issue in the report.
+pub(crate) fn validate(polygon: &Polygon, precision: &Precision) -> Result<PolygonValidation, GeometryError> {
+    for (i, ring) in polygon.rings().enumerate() {
+        for (j, point) in ring.iter().enumerate() {
+            let field = if i == 0 { format!("points[{}]", j) } else { format!("holes[{}][{}]", i - 1, j) };
+            point.check_finite(&field)?;
+        }
+    }
+    let mut issues = Vec::new();
+    let rings: Vec<Ring> = polygon
+        .rings()
+        .enumerate()
+        .map(|(ring, points)| validate_ring(points, ring, precision, &mut issues))
+        .collect();
+
+    let all: Vec<&Vec<Point>> = polygon.rings().collect();
+    for (i, hole) in polygon.holes.iter().enumerate() {
+        let inside_outer = hole.iter().all(|p| ring_contains(&polygon.points, p));
+        let inside_hole = polygon.holes.iter().enumerate().any(|(j, other)| {
+            j != i && other.len() >= 3 && hole.iter().all(|p| ring_contains(other, p))
+        });
+        if hole.len() >= 3 && (!inside_outer || inside_hole) {
+            issues.push(PolygonIssue::HoleOutside { ring: i + 1 });
+        }
+    }
+    for i in 0..all.len() {
+        for j in (i + 1)..all.len() {
+            if let Some(point) = rings_crossing(all[i], all[j], precision) {
+                issues.push(PolygonIssue::RingsIntersect { rings: [i, j], point });
+            }
+        }
+    }
+
+    let outer = &rings[0];
+    let normalized = rings
+        .iter()
+        .all(|ring| ring.kept.len() >= 3 && ring.winding != Winding::None)
+        .then(|| {
+            let mut rings = rings.iter().enumerate().map(|(i, ring)| {
+                let mut points = ring.kept.clone();
+                // Outer ring counter-clockwise, holes clockwise
+                if (ring.winding == Winding::CounterClockwise) != (i == 0) {
+                    points.reverse();
+                }
+                points
+            });
+            let mut normalized = Polygon::new(rings.next().unwrap_or_default());
+            normalized.holes.extend(rings);
+            normalized
+        });
+    let valid = !issues.iter().any(|issue| {
+        !matches!(
+            issue,
+            PolygonIssue::DuplicateVertex { .. } | PolygonIssue::CollinearVertex { .. }
+        )
+    });
+    Ok(PolygonValidation {
+        valid,
+        issues,
+        winding: outer.winding,
+        signed_area: precision.round(outer.signed_area),
+        normalized,
+    })
+}
+
+fn validate_ring(points: &[Point], ring: usize, precision: &Precision, issues: &mut Vec<PolygonIssue>) -> Ring {
+    let n = points.len();
+    if n < 3 {
+        issues.push(PolygonIssue::TooFewPoints { ring, count: n });
+    }
+
+    // Vertices kept after dropping duplicates, by original index
+    let mut distinct: Vec<usize> = Vec::with_capacity(n);
+    for i in 0..n {
+        match distinct.last() {
+            Some(&last) if precision.is_zero(points[i].distance(&points[last])) => {
+                issues.push(PolygonIssue::DuplicateVertex { ring, index: i, duplicates: last });
+            }
+            _ => distinct.push(i),
+        }
+    }
+    // Outlines often repeat the first vertex at the end
+    while distinct.len() > 1 {
+        let last = distinct[distinct.len() - 1];
+        if !precision.is_zero(points[last].distance(&points[distinct[0]])) {
+            break;
+        }
+        distinct.pop();
+        issues.push(PolygonIssue::DuplicateVertex { ring, index: last, duplicates: distinct[0] });
+    }
+
+    let m = distinct.len();
+    let mut kept = Vec::with_capacity(m);
+    for k in 0..m {
+        let (before, at, after) = (distinct[(k + m - 1) % m], distinct[k], distinct[(k + 1) % m]);
+        if m >= 3 && is_collinear(&points[before], &points[at], &points[after], precision) {
+            let (p, q, r) = (&points[before], &points[at], &points[after]);
+            let backtracks = (q.x - p.x) * (r.x - q.x) + (q.y - p.y) * (r.y - q.y) < 0.0;
+            issues.push(PolygonIssue::CollinearVertex { ring, index: at, backtracks });
+        } else {
+            kept.push(points[at]);
+        }
+    }
+
+    if m >= 3 {
+        for (edges, point) in self_intersections(points, &distinct, precision) {
+            issues.push(PolygonIssue::SelfIntersection { ring, edges, point });
+        }
+    }
+
+    let signed_area = if n >= 3 { ring_signed_area(points) } else { 0.0 };
+    let winding = if n < 3 || precision.is_zero(signed_area) {
+        Winding::None
+    } else if signed_area > 0.0 {
+        Winding::CounterClockwise
+    } else {
+        Winding::Clockwise
+    };
+    if n >= 3 && winding == Winding::None {
+        issues.push(PolygonIssue::ZeroArea { ring });
+    }
+    Ring { kept, signed_area, winding }
+}
+
+// First point where an edge of one ring touches an edge of the other
+fn rings_crossing(first: &[Point], second: &[Point], precision: &Precision) -> Option<Point> {
+    ring_edges(first).find_map(|(a, b)| {
+        ring_edges(second).find_map(|(c, d)| segment_intersection(a, b, c, d, precision))
+    })
+}
+
+// Compares the turn against the squared edge lengths, which bounds the sine of the turn, so the
+// test does not depend on the drawing's scale
+fn is_collinear(p: &Point, q: &Point, r: &Point, precision: &Precision) -> bool {
+    let scale = p.distance(q).max(q.distance(r)).max(p.distance(r));
+    scale == 0.0 || precision.is_zero(cross(p, q, r) / (scale * scale))
+}
+
+// Crossings between non-adjacent edges of the outline through the `distinct` vertices.
+// Edges are reported by the original index of their start vertex.
+fn self_intersections(points: &[Point], distinct: &[usize], precision: &Precision) -> Vec<([usize; 2], Point)> {
+    let m = distinct.len();
+    let edge = |k: usize| (&points[distinct[k]], &points[distinct[(k + 1) % m]]);
+    let mut crossings = Vec::new();
+    for i in 0..m {
+        for j in (i + 2)..m {
+            if i == 0 && j == m - 1 {
+                continue;
+            }
+            let ((a, b), (c, d)) = (edge(i), edge(j));
+            if let Some(point) = segment_intersection(a, b, c, d, precision) {
+                crossings.push(([distinct[i], distinct[j]], point));
+            }
+        }
+    }
+    crossings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(f64, f64)]) -> Vec<Point> {
+        points.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    fn check(points: &[(f64, f64)]) -> PolygonValidation {
+        validate(&Polygon::new(ring(points)), &Precision::default()).unwrap()
+    }
+
+    #[test]
+    fn accepts_simple_polygons_of_either_winding() {
+        let counter_clockwise = check(&[(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]);
+        assert!(counter_clockwise.valid);
+        assert!(counter_clockwise.issues.is_empty(), "{:?}", counter_clockwise.issues);
+        assert_eq!(counter_clockwise.winding, Winding::CounterClockwise);
+        assert_eq!(counter_clockwise.signed_area, 12.0);
+
+        let clockwise = check(&[(0.0, 0.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0)]);
+        assert!(clockwise.valid);
+        assert_eq!(clockwise.winding, Winding::Clockwise);
+        assert_eq!(clockwise.signed_area, -12.0);
+        // Normalizing winds the outer ring counter-clockwise
+        let normalized = clockwise.normalized.unwrap();
+        assert!(ring_signed_area(&normalized.points) > 0.0);
+    }
+
+    #[test]
+    fn reports_bow_ties() {
+        let result = check(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]);
+        assert!(!result.valid);
+        match result.issues.as_slice() {
+            [PolygonIssue::SelfIntersection { ring: 0, edges: [0, 2], point }, PolygonIssue::ZeroArea { ring: 0 }] => {
+                assert!((point.x - 1.0).abs() < 1e-12 && (point.y - 1.0).abs() < 1e-12);
+            }
+            issues => panic!("expected a crossing at (1, 1) and zero area, got {:?}", issues),
+        }
+        assert_eq!(result.winding, Winding::None);
+        assert!(result.normalized.is_none());
+    }
+
+    #[test]
+    fn drops_collinear_runs_and_duplicates() {
+        let result = check(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (0.0, 0.0)]);
+        assert!(result.valid);
+        assert!(matches!(
+            result.issues.as_slice(),
+            [
+                PolygonIssue::DuplicateVertex { ring: 0, index: 3, duplicates: 2 },
+                PolygonIssue::DuplicateVertex { ring: 0, index: 6, duplicates: 0 },
+                PolygonIssue::CollinearVertex { ring: 0, index: 1, backtracks: false },
+            ]
+        ), "{:?}", result.issues);
+        assert_eq!(result.normalized.unwrap().points.len(), 4);
+    }
+
+    #[test]
+    fn reports_spikes_as_backtracking() {
+        // The outline runs out to (4, 1) and straight back
+        let result = check(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (4.0, 1.0), (2.0, 1.0), (2.0, 2.0), (0.0, 2.0)]);
+        assert!(result
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, PolygonIssue::CollinearVertex { index: 3, backtracks: true, .. })));
+        assert!(!result.valid, "the spike's edges overlap");
+    }
+
+    #[test]
+    fn collinearity_does_not_depend_on_scale() {
+        // A real corner, only 1e-4 off the straight line relative to the edge lengths
+        let bent = [(0.0, 0.0), (1.0, 1e-4), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let straight = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let collinear = |points: &[(f64, f64)], scale: f64| {
+            let scaled: Vec<(f64, f64)> = points.iter().map(|&(x, y)| (x * scale, y * scale)).collect();
+            check(&scaled)
+                .issues
+                .iter()
+                .any(|issue| matches!(issue, PolygonIssue::CollinearVertex { .. }))
+        };
+        for scale in [1e-6, 1.0, 1e6] {
+            assert!(!collinear(&bent, scale), "bent corner at scale {}", scale);
+            assert!(collinear(&straight, scale), "straight run at scale {}", scale);
+        }
+    }
+
+    #[test]
+    fn checks_holes() {
+        let mut polygon = Polygon::new(ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]));
+        polygon.holes.push(ring(&[(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)]));
+        let result = validate(&polygon, &Precision::default()).unwrap();
+        assert!(result.valid, "{:?}", result.issues);
+        // Holes are wound clockwise once normalized
+        let normalized = result.normalized.unwrap();
+        assert!(ring_signed_area(&normalized.holes[0]) < 0.0);
+
+        let mut outside = polygon.clone();
+        outside.holes.push(ring(&[(20.0, 20.0), (24.0, 20.0), (24.0, 24.0)]));
+        let result = validate(&outside, &Precision::default()).unwrap();
+        assert!(!result.valid);
+        assert!(matches!(result.issues.as_slice(), [PolygonIssue::HoleOutside { ring: 2 }]), "{:?}", result.issues);
+
+        let mut crossing = polygon;
+        crossing.holes[0] = ring(&[(8.0, 2.0), (12.0, 2.0), (12.0, 4.0), (8.0, 4.0)]);
+        let result = validate(&crossing, &Precision::default()).unwrap();
+        assert!(result.issues.iter().any(|issue| matches!(issue, PolygonIssue::RingsIntersect { rings: [0, 1], .. })));
+    }
+
+    #[test]
+    fn reports_rings_with_too_few_points() {
+        let result = check(&[(0.0, 0.0), (1.0, 1.0)]);
+        assert!(!result.valid);
+        assert!(matches!(result.issues.as_slice(), [PolygonIssue::TooFewPoints { ring: 0, count: 2 }]));
+        let not_finite = Polygon::new(ring(&[(0.0, f64::NAN), (1.0, 0.0), (0.0, 1.0)]));
+        assert!(validate(&not_finite, &Precision::default()).is_err());
+    }
+}
@@ -8,13 +8,71 @@ export interface Rectangle {
   bottom_right: Point;
 }
 
+// Rectangle turned `angle` degrees counter-clockwise (Y up) about its center
+export interface OrientedRectangle {
+  center: Point;
+  width: number;   // Along the rectangle's own X axis
+  height: number;
+  angle: number;
+}
+
 export interface Circle {
   center: Point;
   radius: number;
 }
 
+// Semi-axes rx and ry, turned `angle` degrees counter-clockwise (Y up) about the center
+export interface Ellipse {
+  center: Point;
+  rx: number;
+  ry: number;
+  angle: number;
+}
+
+// 'open' is just the curve, 'chord' joins its ends, 'sector' joins them through the center
+export type ArcClosure = 'open' | 'chord' | 'sector';
+
+// Part of a circle; angles in degrees, sweeping counter-clockwise (Y up) when positive
+export interface Arc {
+  center: Point;
+  radius: number;
+  start_angle: number;
+  sweep_angle: number;   // Between -360 and 360
+  closure?: ArcClosure;  // Defaults to 'sector'
+}
+
+// Straight line from start to end
+export interface Segment {
+  start: Point;
+  end: Point;
+}
+
+// Open chain of straight segments; measured by length, it has no area
+export interface Polyline {
+  points: Point[];
+}
+
+// One piece of a Path, starting where the previous one ends
+export type PathSegment =
+  | { kind: 'line'; to: Point }
+  | { kind: 'quadratic'; control: Point; to: Point }
+  | { kind: 'cubic'; control1: Point; control2: Point; to: Point };
+
+// Single subpath of straight and Bezier segments; closed paths return to start with a line
+export interface Path {
+  start: Point;
+  segments: PathSegment[];
+  closed?: boolean;  // Defaults to false
+}
+
 export interface Polygon {
   points: Point[];
+  holes?: Point[][];  // Inner rings cut out of the polygon
+}
+
+// Disjoint polygons measured and exported as one shape
+export interface MultiPolygon {
+  polygons: Polygon[];
 }
 
 export type Shape = Rectangle | Circle | Polygon;
@@ -30,3 +88,251 @@ export interface GridSettings {
   snapToGrid: boolean;      // Whether to snap points to grid
   conversionFactor: number; // Conversion factor from internal units to selected unit
 }
+
+// Returned by the Rust `measure_shape` command
+export interface ShapeMetrics {
+  area: number;
+  perimeter: number;
+  centroid: Point;
+  bbox: Rectangle;              // Axis-aligned, top_left holds the minimum corner
+  convex: boolean | null;       // Only reported for polygons
+  self_intersecting: boolean | null;
+  unit: MeasurementUnit;        // Unit of lengths and coordinates
+  area_unit: string;            // Squared unit symbol for the area, e.g. 'cm²'
+}
+
+// Returned by the Rust `measure_group` command for every shape in a group and its nested groups
+export interface GroupMetrics {
+  union_area: number;           // Area covered, counting overlapping shapes once
+  sum_area: number;             // Shape areas added up, counting overlaps once per shape
+  centroid: Point;
+  bbox: Rectangle;
+  shapes: number;               // Number of shapes measured
+  unit: MeasurementUnit;
+  area_unit: string;
+}
+
+// One segment of a path returned by `measure_path`; angles in degrees, counter-clockwise (Y up)
+export interface SegmentMetrics {
+  from: number;          // Index of the vertex the segment starts at
+  length: number;
+  angle: number;         // Direction from the X axis, in (-180, 180]
+  turn: number | null;   // Change of direction from the previous segment, positive turning left
+}
+
+// Returned by the Rust `measure_path` command
+export interface PathMetrics {
+  length: number;
+  segments: SegmentMetrics[];
+  closed: boolean;       // Whether the last segment returns to the first vertex
+  unit: MeasurementUnit;
+}
+
+// Display unit and screen density held by the Rust backend
+export interface UnitSettings {
+  unit: MeasurementUnit;
+  dpi: number;
+}
+
+// Error payload rejected by the Rust geometry commands
+export interface GeometryError {
+  code: string;    // Stable identifier, e.g. 'negative_extent', usable for localization
+  field: string;   // Path of the offending input, e.g. 'points[2].x'
+  message: string; // English fallback message
+}
+
+// Shape as sent to the Rust backend, tagged with its ShapeType
+export type TaggedShape =
+  | ({ type: 'rectangle' } & Rectangle)
+  | ({ type: 'oriented_rectangle' } & OrientedRectangle)
+  | ({ type: 'circle' } & Circle)
+  | ({ type: 'ellipse' } & Ellipse)
+  | ({ type: 'arc' } & Arc)
+  | ({ type: 'segment' } & Segment)
+  | ({ type: 'polyline' } & Polyline)
+  | ({ type: 'path' } & Path)
+  | ({ type: 'polygon' } & Polygon)
+  | ({ type: 'multi_polygon' } & MultiPolygon);
+
+export interface DocumentMetadata {
+  title: string | null;
+  author: string | null;
+  created: number | null;   // Seconds since the Unix epoch, set on save
+  modified: number | null;
+}
+
+// Shape held by the Rust scene, keyed by an id that survives edits, saves and reloads
+export interface SceneShape {
+  id: string;
+  layer: string;  // Id of the layer the shape is on
+  group: string | null;  // Id of the innermost group holding the shape
+  shape: TaggedShape;
+}
+
+// Group node of the Rust scene tree; groups nest shapes and each other
+export interface Group {
+  id: string;
+  parent: string | null;  // Id of the group this one is nested in
+}
+
+// Layer of the Rust scene; layers are listed bottom first
+export interface Layer {
+  id: string;
+  name: string;
+  color: string;    // '#rrggbb'
+  visible: boolean;
+  locked: boolean;  // Shapes on a locked layer cannot be added, edited, moved or deleted
+}
+
+// Label of an edit sent to the Rust `update_shape` command, shown on its undo step
+export type EditKind = 'move' | 'transform' | 'edit_property';
+
+export type OperationKind =
+  | 'add'
+  | 'delete'
+  | 'clear'
+  | EditKind
+  | 'add_layer'
+  | 'edit_layer'
+  | 'reorder_layer'
+  | 'move_to_layer'
+  | 'merge_layers'
+  | 'group'
+  | 'ungroup';
+
+// Payload of the Rust `history-changed` event: what the next undo and redo would revert
+export interface HistoryState {
+  undo: OperationKind | null;
+  redo: OperationKind | null;
+}
+
+// Payload of the Rust `scene-changed` event, sent after an undo or redo
+export interface SceneChange {
+  operation: OperationKind;
+  updated: SceneShape[];  // Shapes added back or edited, as they are now
+  removed: string[];      // Ids of shapes taken out
+  layers: Layer[] | null; // The whole layer stack, bottom first, when the step changed it
+  groups: Group[] | null; // Every group, when the step added, removed or renested one
+  history: HistoryState;
+}
+
+// Everything the Rust `save_document` and export commands need besides the scene's shapes
+export interface DocumentProperties {
+  grid: GridSettings;
+  unit: MeasurementUnit;
+  metadata: DocumentMetadata;
+}
+
+// Drawing document returned by the Rust `open_document` command, which also loads it into the scene
+export interface DrawingDocument extends DocumentProperties {
+  shapes: SceneShape[];
+}
+
+// Options shared by the Rust export commands
+export interface DrawingOptions {
+  show_grid?: boolean;  // Defaults to the document's grid setting
+  show_axes?: boolean;  // Defaults to true
+  show_labels?: boolean; // Vertex markers and coordinate labels, defaults to false
+  margin?: number;      // Space around the shapes in internal units, defaults to 20
+}
+
+// Options for the Rust `render_png` command
+export interface RasterOptions extends DrawingOptions {
+  dpi?: number;         // Output resolution, defaults to 96
+  width?: number;       // Fit the drawing into this many pixels instead of using the DPI scale
+  height?: number;
+}
+
+export type PageSize =
+  | 'a5' | 'a4' | 'a3' | 'letter' | 'legal' | 'tabloid'
+  | { custom: { width_mm: number; height_mm: number } };
+
+// Options for the Rust `export_pdf` command
+export interface PdfOptions extends DrawingOptions {
+  page_size?: PageSize;  // Defaults to 'a4'
+  orientation?: 'portrait' | 'landscape';
+  scale?: number;        // Paper size relative to real size, defaults to 1 (0.5 prints at 1:2)
+  margin_mm?: number;    // Defaults to 10
+  title_block?: boolean; // Title, unit, scale and page number, defaults to true
+}
+
+export interface ImportIssue {
+  element: string;      // SVG element name, e.g. 'text'
+  id: string | null;
+  reason: string;
+}
+
+// Result of the Rust `import_svg` command
+export interface SvgImport {
+  shapes: TaggedShape[];
+  warnings: ImportIssue[];     // Imported approximately
+  unsupported: ImportIssue[];  // Left out
+}
+
+// Result of the Rust `import_dxf` command
+export interface DxfImport {
+  shapes: TaggedShape[];
+  layers: string[];            // DXF layer of each shape, by index
+  unit: MeasurementUnit;       // Unit declared by the file's $INSUNITS header
+  warnings: ImportIssue[];     // Imported approximately
+  unsupported: ImportIssue[];  // Left out
+}
+
+export type GeometryFormat = 'geojson' | 'wkt';
+
+// Result of the Rust `parse_geometry` and `import_geometry` commands
+export interface GeometryImport {
+  shapes: TaggedShape[];
+  format: GeometryFormat;      // Detected from the text
+  warnings: ImportIssue[];     // Imported partly
+  unsupported: ImportIssue[];  // Left out, e.g. points and lines
+}
+
+// Issue reported by the Rust `validate_polygon` command. Ring 0 is the outline and ring i is
+// holes[i - 1]; indices refer to the submitted points of that ring.
+export type PolygonIssue =
+  | { kind: 'too_few_points'; ring: number; count: number }
+  | { kind: 'duplicate_vertex'; ring: number; index: number; duplicates: number }
+  | { kind: 'collinear_vertex'; ring: number; index: number; backtracks: boolean }
+  | { kind: 'self_intersection'; ring: number; edges: [number, number]; point: Point }  // Edge i runs from point i to i + 1
+  | { kind: 'zero_area'; ring: number }
+  | { kind: 'hole_outside'; ring: number }
+  | { kind: 'rings_intersect'; rings: [number, number]; point: Point };
+
+export interface PolygonValidation {
+  valid: boolean;              // Simple with a non-zero area
+  issues: PolygonIssue[];
+  winding: 'counter_clockwise' | 'clockwise' | 'none';
+  signed_area: number;
+  normalized: Polygon | null;  // Cleaned up, outline counter-clockwise and holes clockwise
+}
+
+export type BooleanOp = 'union' | 'intersection' | 'difference' | 'xor';
+
+// Result of the Rust `boolean_op` command. Polygons are in internal pixels with outer rings
+// counter-clockwise and holes clockwise.
+export interface BooleanResult {
+  polygons: Polygon[];
+  area: number;      // Holes subtracted, in area_unit
+  unit: MeasurementUnit;
+  area_unit: string;
+}
+
+// 2D affine matrix: x' = a*x + c*y + e, y' = b*x + d*y + f
+export interface Transform {
+  a: number;
+  b: number;
+  c: number;
+  d: number;
+  e: number;
+  f: number;
+}
+
+// Step of the Rust `transform_shape` command. Angles are in degrees, counter-clockwise with
+// Y up; lengths are in internal units. Without a center, steps act about the shape's bbox center.
+export type TransformStep =
+  | { kind: 'translate'; dx: number; dy: number }
+  | { kind: 'rotate'; angle: number; center?: Point }
+  | { kind: 'scale'; sx: number; sy?: number; center?: Point }
+  | { kind: 'mirror'; axis: 'horizontal' | 'vertical'; center?: Point }
+  | ({ kind: 'matrix' } & Transform);
@@ -0,0 +1,67 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Shape, ShapeType, Rectangle, Circle, Polygon, TaggedShape, SceneShape, Layer, Group } from '../types/shapes';
+
+/**
+ * Tags a canvas shape with its type for the Rust backend, which works in the same
+ * mathematical coordinates as the canvas (origin at center, Y up) and normalizes
+ * rectangle corners itself
+ */
+export function toBackendShape(shape: Shape, type: ShapeType): TaggedShape {
+  if (type === 'rectangle') {
+    const rect = shape as Rectangle;
+    return {
+      type,
+      top_left: rect.top_left,
+      bottom_right: rect.bottom_right
+    };
+  } else if (type === 'circle') {
+    const circle = shape as Circle;
+    return {
+      type,
+      center: circle.center,
+      radius: circle.radius
+    };
+  }
+  const polygon = shape as Polygon;
+  return {
+    type,
+    points: polygon.points
+  };
+}
+
+/**
+ * Whether a canvas shape has nothing drawn yet, e.g. after a cancelled polygon
+ */
+export function isEmptyShape(shape: Shape, type: ShapeType): boolean {
+  return type === 'polygon' && (shape as Polygon).points.length === 0;
+}
+
+/**
+ * Writes a canvas shape to the Rust scene, adding it to `layer` (the top layer when null) if
+ * `id` is null. Writes with the same `gesture` undo as one step. Returns the shape as stored,
+ * with its id.
+ */
+export function saveSceneShape(
+  id: string | null,
+  shape: Shape,
+  type: ShapeType,
+  layer: string | null,
+  gesture: string | null
+): Promise<SceneShape> {
+  const tagged = toBackendShape(shape, type);
+  return id === null
+    ? invoke<SceneShape>('add_shape', { shape: tagged, layer, gesture })
+    : invoke<SceneShape>('update_shape', { id, shape: tagged, gesture });
+}
+
+export function listSceneShapes(): Promise<SceneShape[]> {
+  return invoke<SceneShape[]>('list_shapes');
+}
+
+export function listLayers(): Promise<Layer[]> {
+  return invoke<Layer[]>('list_layers');
+}
+
+export function listGroups(): Promise<Group[]> {
+  return invoke<Group[]>('list_groups');
+}